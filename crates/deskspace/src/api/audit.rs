@@ -0,0 +1,121 @@
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, Extensions, HeaderMap, Method, Uri};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::api::AppState;
+
+/// Marks a response refusing paths that escape the workspace, with each
+/// path as the client sent it: one for an error, any number for a batch.
+/// Set by `ApiError` and `batch_files`, read by `log_traversals`.
+#[derive(Debug, Clone)]
+pub struct TraversalRejected(pub Vec<String>);
+
+/// Who sent a request and what it asked for, as the audit log reports it.
+#[derive(Debug, Clone)]
+pub struct Requester {
+    client: Option<SocketAddr>,
+    user_agent: Option<String>,
+    method: Method,
+    uri: Uri,
+}
+
+impl Requester {
+    pub fn new(method: &Method, uri: &Uri, headers: &HeaderMap, extensions: &Extensions) -> Self {
+        Self {
+            client: extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| *addr),
+            user_agent: headers
+                .get(header::USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            method: method.clone(),
+            uri: uri.clone(),
+        }
+    }
+
+    /// Warn that `path` was refused for escaping the workspace, and count
+    /// it in `traversal_rejections`.
+    pub fn traversal_rejected(&self, state: &AppState, path: &str) {
+        state.traversal_rejections.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+            target: "deskspace::audit",
+            client = self.client.map(tracing::field::display),
+            user_agent = self.user_agent,
+            method = %self.method,
+            uri = %self.uri,
+            path,
+            "path traversal rejected"
+        );
+    }
+}
+
+/// Audit middleware: warns about every path refused for traversal, with
+/// who sent it, and counts them in `traversal_rejections`. Probing shows
+/// up as a run of these from one client.
+pub async fn log_traversals(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let requester = Requester::new(
+        request.method(),
+        request.uri(),
+        request.headers(),
+        request.extensions(),
+    );
+    let response = next.run(request).await;
+    if let Some(TraversalRejected(paths)) = response.extensions().get() {
+        for path in paths {
+            requester.traversal_rejected(&state, path);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::projections::text_raw::TextRaw;
+    use crate::registry::ProjectionRegistry;
+
+    #[tokio::test]
+    async fn traversals_are_counted_in_requests_and_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        let mut registry = ProjectionRegistry::new();
+        registry.register(Arc::new(TextRaw));
+        let state = Arc::new(AppState::for_tests(dir.path(), registry));
+        let app = crate::api::router(state.clone());
+        let count = || state.traversal_rejections.load(Ordering::Relaxed);
+
+        let request = Request::get("/api/files/../x").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(count(), 1);
+
+        // The batch itself succeeds, but its refused item is still audited
+        let request = Request::post("/api/files/batch")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"paths":["a.txt","../y"]}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        assert_eq!(count(), 2);
+
+        let request = Request::post("/api/files/batch")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"paths":["a.txt","missing.txt"]}"#))
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+        assert_eq!(count(), 2);
+    }
+}
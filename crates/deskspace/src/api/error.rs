@@ -0,0 +1,370 @@
+use axum::extract::Request;
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::api::audit::TraversalRejected;
+use crate::projection::ProjectionError;
+use crate::thumbnail::ThumbnailError;
+use crate::upload::UploadError;
+use crate::workspace::WorkspaceError;
+
+/// Where a chunked upload stands, sent with offset mismatches.
+pub const UPLOAD_OFFSET: header::HeaderName = header::HeaderName::from_static("upload-offset");
+
+/// Largest framework error body rewritten by `normalize_errors`.
+const MAX_REWRITTEN_BODY: usize = 64 * 1024;
+
+/// A failed API request. Each variant has a fixed status and a stable
+/// machine-readable code; the body is `{ "error": message, "code": code }`.
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("path escapes workspace root: {0}")]
+    PathTraversal(String),
+    #[error("operation not permitted on the workspace root")]
+    WorkspaceRoot,
+    #[error("{0}")]
+    InvalidOption(String),
+    #[error("unknown projection: {0}")]
+    UnknownProjection(String),
+    #[error("is a directory")]
+    IsDirectory,
+    #[error("missing or invalid access token")]
+    Unauthorized,
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("server is read-only")]
+    ReadOnly,
+    #[error("{0}")]
+    NotFound(String),
+    #[error("no projection available")]
+    NoProjection,
+    #[error("{0}")]
+    NotAcceptable(String),
+    #[error("{0}")]
+    AlreadyExists(String),
+    #[error("{0}")]
+    NotADirectory(String),
+    #[error("directory not empty")]
+    DirectoryNotEmpty,
+    /// A chunk didn't start where the upload left off, at this offset.
+    #[error("upload is at offset {0}")]
+    UploadOffset(u64),
+    #[error("{0}")]
+    PreconditionFailed(String),
+    #[error("{0}")]
+    PreconditionRequired(String),
+    /// The requested byte range lies outside a file of this many bytes.
+    #[error("range not satisfiable")]
+    RangeNotSatisfiable(u64),
+    #[error("{0}")]
+    Unprocessable(String),
+    /// Over the write rate limit; a token frees up after this long.
+    #[error("too many requests")]
+    TooManyRequests(std::time::Duration),
+    #[error("{0}")]
+    Internal(String),
+    #[error("{0}")]
+    NotImplemented(String),
+    /// An error produced by axum itself, such as an extractor rejection.
+    #[error("{message}")]
+    Rejected { status: StatusCode, message: String },
+}
+
+impl ApiError {
+    pub fn not_found() -> Self {
+        Self::NotFound("not found".to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_)
+            | Self::PathTraversal(_)
+            | Self::WorkspaceRoot
+            | Self::InvalidOption(_)
+            | Self::UnknownProjection(_)
+            | Self::IsDirectory => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) | Self::ReadOnly => StatusCode::FORBIDDEN,
+            Self::NotFound(_) | Self::NoProjection => StatusCode::NOT_FOUND,
+            Self::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            Self::AlreadyExists(_)
+            | Self::NotADirectory(_)
+            | Self::DirectoryNotEmpty
+            | Self::UploadOffset(_) => StatusCode::CONFLICT,
+            Self::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            Self::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            Self::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            Self::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotImplemented(_) => StatusCode::NOT_IMPLEMENTED,
+            Self::Rejected { status, .. } => *status,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::PathTraversal(_) => "path_traversal",
+            Self::WorkspaceRoot => "workspace_root",
+            Self::InvalidOption(_) => "invalid_option",
+            Self::UnknownProjection(_) => "unknown_projection",
+            Self::IsDirectory => "is_directory",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::ReadOnly => "read_only",
+            Self::NotFound(_) => "not_found",
+            Self::NoProjection => "no_projection",
+            Self::NotAcceptable(_) => "not_acceptable",
+            Self::AlreadyExists(_) => "already_exists",
+            Self::NotADirectory(_) => "not_a_directory",
+            Self::DirectoryNotEmpty => "directory_not_empty",
+            Self::UploadOffset(_) => "offset_mismatch",
+            Self::PreconditionFailed(_) => "precondition_failed",
+            Self::PreconditionRequired(_) => "precondition_required",
+            Self::RangeNotSatisfiable(_) => "range_not_satisfiable",
+            Self::Unprocessable(_) => "unprocessable",
+            Self::TooManyRequests(_) => "rate_limited",
+            Self::Internal(_) => "internal",
+            Self::NotImplemented(_) => "not_implemented",
+            Self::Rejected { status, .. } if status.is_server_error() => "internal",
+            Self::Rejected { .. } => "invalid_request",
+        }
+    }
+}
+
+/// JSON body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Human-readable message.
+    pub error: String,
+    /// Stable machine-readable code, e.g. `not_found`.
+    pub code: &'static str,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        metrics::counter!("deskspace_errors_total", "code" => self.code()).increment(1);
+        let range = match self {
+            Self::RangeNotSatisfiable(len) => Some(format!("bytes */{len}")),
+            _ => None,
+        };
+        // Whole seconds, rounded up so a client retrying on time succeeds
+        let retry_after = match self {
+            Self::TooManyRequests(wait) => {
+                Some(wait.as_secs() + u64::from(wait.subsec_nanos() > 0))
+            }
+            _ => None,
+        };
+        let upload_offset = match self {
+            Self::UploadOffset(offset) => Some(offset),
+            _ => None,
+        };
+        let traversal = match &self {
+            Self::PathTraversal(path) => Some(TraversalRejected(vec![path.clone()])),
+            _ => None,
+        };
+        let challenge = matches!(self, Self::Unauthorized);
+        let body = Json(ErrorBody {
+            error: self.to_string(),
+            code: self.code(),
+        });
+        let mut response = (status, body).into_response();
+        if let Some(range) = range.and_then(|r| r.parse().ok()) {
+            response.headers_mut().insert(header::CONTENT_RANGE, range);
+        }
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.max(1).into());
+        }
+        if let Some(offset) = upload_offset {
+            response.headers_mut().insert(UPLOAD_OFFSET, offset.into());
+        }
+        if let Some(traversal) = traversal {
+            response.extensions_mut().insert(traversal);
+        }
+        if challenge {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+        response
+    }
+}
+
+/// Bad paths are the client's fault, missing files are 404, conflicts are
+/// 409, anything else is a server error.
+impl From<WorkspaceError> for ApiError {
+    fn from(e: WorkspaceError) -> Self {
+        use std::io::ErrorKind;
+        match e {
+            WorkspaceError::PathTraversal(path) => Self::PathTraversal(path),
+            WorkspaceError::Root => Self::WorkspaceRoot,
+            WorkspaceError::NotFound(_) => Self::not_found(),
+            WorkspaceError::NotLocal(path) => {
+                Self::Unprocessable(format!("not a file on disk: {path}"))
+            }
+            WorkspaceError::Io(io) => match io.kind() {
+                ErrorKind::InvalidInput => Self::BadRequest(io.to_string()),
+                ErrorKind::NotFound => Self::not_found(),
+                ErrorKind::DirectoryNotEmpty => Self::DirectoryNotEmpty,
+                ErrorKind::AlreadyExists => Self::AlreadyExists(io.to_string()),
+                ErrorKind::NotADirectory => Self::NotADirectory(io.to_string()),
+                ErrorKind::IsADirectory => Self::IsDirectory,
+                ErrorKind::Unsupported => Self::NotImplemented(io.to_string()),
+                _ => Self::Internal(io.to_string()),
+            },
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        WorkspaceError::Io(e).into()
+    }
+}
+
+impl From<ProjectionError> for ApiError {
+    fn from(e: ProjectionError) -> Self {
+        match e {
+            ProjectionError::InvalidOption(_) => Self::InvalidOption(e.to_string()),
+            ProjectionError::Workspace(e) => e.into(),
+            _ => Self::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<ThumbnailError> for ApiError {
+    fn from(e: ThumbnailError) -> Self {
+        match e {
+            ThumbnailError::NotAnImage => Self::BadRequest("not an image".to_string()),
+            ThumbnailError::Decode(_) => Self::Unprocessable(e.to_string()),
+            ThumbnailError::Io(io) => io.into(),
+        }
+    }
+}
+
+impl From<UploadError> for ApiError {
+    fn from(e: UploadError) -> Self {
+        match e {
+            UploadError::Unknown(_) => Self::NotFound(e.to_string()),
+            UploadError::OffsetMismatch { expected, .. } => Self::UploadOffset(expected),
+            UploadError::TooLarge(_) => Self::BadRequest(e.to_string()),
+            UploadError::Incomplete { .. } => Self::Unprocessable(e.to_string()),
+            UploadError::Workspace(e) => e.into(),
+            UploadError::Io(io) => io.into(),
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for ApiError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+/// Middleware giving errors that axum produces on its own (extractor
+/// rejections, 405s, oversized bodies) the same JSON shape as handler
+/// errors. Responses that are already JSON pass through untouched.
+pub async fn normalize_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let message = match axum::body::to_bytes(body, MAX_REWRITTEN_BODY).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => status.canonical_reason().unwrap_or("error").to_string(),
+    };
+    let mut rewritten = ApiError::Rejected { status, message }.into_response();
+    for (name, value) in &parts.headers {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            rewritten.headers_mut().insert(name, value.clone());
+        }
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_errors_map_to_codes() {
+        let code = |e: WorkspaceError| {
+            let e = ApiError::from(e);
+            (e.status(), e.code())
+        };
+        assert_eq!(
+            code(WorkspaceError::PathTraversal("../x".into())),
+            (StatusCode::BAD_REQUEST, "path_traversal")
+        );
+        assert_eq!(
+            code(WorkspaceError::NotFound("gone/../x".into())),
+            (StatusCode::NOT_FOUND, "not_found")
+        );
+        assert_eq!(
+            code(std::io::Error::from(std::io::ErrorKind::NotFound).into()),
+            (StatusCode::NOT_FOUND, "not_found")
+        );
+        assert_eq!(
+            code(std::io::Error::from(std::io::ErrorKind::DirectoryNotEmpty).into()),
+            (StatusCode::CONFLICT, "directory_not_empty")
+        );
+        assert_eq!(
+            code(std::io::Error::other("disk on fire").into()),
+            (StatusCode::INTERNAL_SERVER_ERROR, "internal")
+        );
+    }
+
+    #[tokio::test]
+    async fn body_has_error_and_code() {
+        let response = ApiError::UnknownProjection("nope".into()).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"error": "unknown projection: nope", "code": "unknown_projection"})
+        );
+
+        let response = ApiError::RangeNotSatisfiable(10).into_response();
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */10");
+
+        // Traversals are marked for the audit log, and only echo the
+        // client's own path back
+        let response = ApiError::PathTraversal("../etc".into()).into_response();
+        let marked = response.extensions().get::<TraversalRejected>();
+        assert_eq!(
+            marked.map(|t| t.0.as_slice()),
+            Some(&["../etc".to_string()][..])
+        );
+        assert!(ApiError::not_found()
+            .into_response()
+            .extensions()
+            .get::<TraversalRejected>()
+            .is_none());
+
+        let wait = std::time::Duration::from_millis(1500);
+        let response = ApiError::TooManyRequests(wait).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+    }
+}
@@ -0,0 +1,194 @@
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{Path as UrlPath, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use tokio_util::io::{ReaderStream, SyncIoBridge};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+use crate::api::files::{attachment_disposition, basename};
+use crate::api::{ApiError, AppState};
+use crate::workspace::{Result, WalkOptions, Workspace};
+
+/// Bytes buffered between the zip writer and the response body. The writer
+/// blocks once this fills, so a slow client throttles the walk instead of
+/// the archive piling up in memory.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// Write a zip of the directory at `path` to `out`, with entry names
+/// relative to it. Symlinks are skipped, so nothing outside the directory
+/// (or the root) can end up in the archive. Returns the number of files
+/// written.
+///
+/// Writes to `out` synchronously, so a slow reader on the other end holds
+/// up the walk.
+pub fn write_zip(workspace: &Workspace, path: &Path, out: impl Write) -> Result<usize> {
+    let base = workspace.relative(&workspace.resolve(path)?);
+    let mut zip = ZipWriter::new_stream(out);
+    let mut files = 0;
+    let mut error = None;
+    workspace.walk(path, &WalkOptions::default(), |entry| {
+        if entry.is_symlink {
+            tracing::debug!("zip: skipping symlink {}", entry.path);
+            return ControlFlow::Continue(());
+        }
+        let name = match entry.path.strip_prefix(&base) {
+            Some(rest) if !base.is_empty() => rest.trim_start_matches('/'),
+            _ => entry.path.as_str(),
+        };
+        let result = if entry.is_dir {
+            zip.add_directory(name, SimpleFileOptions::default())
+        } else {
+            add_file(&mut zip, workspace, &entry.path, name, entry.size)
+        };
+        match result {
+            Ok(()) => {
+                files += usize::from(!entry.is_dir);
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    })?;
+    if let Some(e) = error {
+        return Err(std::io::Error::from(e).into());
+    }
+    zip.finish().map_err(std::io::Error::from)?;
+    Ok(files)
+}
+
+fn add_file<W: Write>(
+    zip: &mut ZipWriter<zip::write::StreamWriter<W>>,
+    workspace: &Workspace,
+    path: &str,
+    name: &str,
+    size: u64,
+) -> zip::result::ZipResult<()> {
+    let abs = workspace.join(path);
+    let mut file = std::fs::File::open(&abs)?;
+    let meta = file.metadata()?;
+    let mut options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(size > u64::from(u32::MAX));
+    if let Some(modified) = meta.modified().ok().and_then(zip_time) {
+        options = options.last_modified_time(modified);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        options = options.unix_permissions(meta.permissions().mode());
+    }
+    zip.start_file(name, options)?;
+    std::io::copy(&mut file, zip)?;
+    Ok(())
+}
+
+/// Zip timestamps are local-less calendar times from 1980 to 2107; anything
+/// outside that range keeps the format's default.
+fn zip_time(time: std::time::SystemTime) -> Option<DateTime> {
+    let time = time::OffsetDateTime::from(time);
+    DateTime::try_from(time::PrimitiveDateTime::new(time.date(), time.time())).ok()
+}
+
+/// Download a directory as a zip archive, built while it streams.
+pub async fn zip_directory(
+    State(state): State<Arc<AppState>>,
+    UrlPath(path): UrlPath<String>,
+) -> std::result::Result<Response, ApiError> {
+    zip_response(state, path).await
+}
+
+pub async fn zip_root(
+    State(state): State<Arc<AppState>>,
+) -> std::result::Result<Response, ApiError> {
+    zip_response(state, String::new()).await
+}
+
+async fn zip_response(
+    state: Arc<AppState>,
+    path: String,
+) -> std::result::Result<Response, ApiError> {
+    let meta = state.workspace.metadata(Path::new(&path)).await?;
+    if !meta.is_dir() {
+        return Err(ApiError::NotADirectory(format!(
+            "{path} is not a directory"
+        )));
+    }
+    let name = match basename(&path) {
+        "" => state.workspace.root().file_name().map_or_else(
+            || "workspace".to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        ),
+        name => name.to_string(),
+    };
+
+    let (reader, writer) = tokio::io::duplex(PIPE_CAPACITY);
+    let workspace = state.workspace.clone();
+    tokio::task::spawn_blocking(move || {
+        // Fails with a broken pipe when the client goes away, ending the walk
+        if let Err(e) = write_zip(&workspace, Path::new(&path), SyncIoBridge::new(writer)) {
+            tracing::debug!("zip of {path:?} ended early: {e}");
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                attachment_disposition(&format!("{name}.zip")),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn zips_directory_relative_to_itself() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("project/src/empty")).unwrap();
+        std::fs::write(dir.path().join("project/src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("project/README.md"), "# hi").unwrap();
+        std::fs::write(dir.path().join("outside.txt"), "secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(
+            dir.path().join("outside.txt"),
+            dir.path().join("project/link.txt"),
+        )
+        .unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let mut bytes = Vec::new();
+        let files = write_zip(&ws, Path::new("project"), &mut bytes).unwrap();
+        assert_eq!(files, 2);
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut names: Vec<_> = archive
+            .file_names()
+            .map(|n| n.unwrap().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["README.md", "src/", "src/empty/", "src/main.rs"]);
+        let mut content = String::new();
+        archive
+            .by_name("src/main.rs")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "fn main() {}");
+    }
+}
@@ -1,141 +1,2559 @@
-use std::path::Path;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Component, Path};
 use std::sync::Arc;
 
 use axum::body::Body;
-use axum::extract::{Query, State};
-use axum::http::{header, StatusCode};
+use axum::extract::{FromRequest, Multipart, Query, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Json, Response};
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::{ReaderStream, SyncIoBridge};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::api::AppState;
-use crate::projection::Resource;
-use crate::registry::ProjectionInfo;
+use crate::api::audit::TraversalRejected;
+use crate::api::error::ErrorBody;
+use crate::api::{metrics, ApiError, AppState};
+use crate::archive::{self, ArchiveKind};
+use crate::cache::{CacheKey, CachedOutput};
+use crate::projection::{
+    versioned_raw_url, DirectoryEntry, Projection, ProjectionError, ProjectionMeta,
+    ProjectionOutput, ProjectionRequest, RenderFormat, Resource,
+};
+use crate::projections::text_raw::TextRaw;
+use crate::registry::{ProjectionInfo, Selection, SelectionReason};
+use crate::store::FileStat;
+use crate::workspace::{content_version, LockedFile, WorkspaceError};
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct FileQuery {
+    /// Projection to run instead of the best match. Any other query
+    /// parameter is passed to the projection as an option.
     pub projection: Option<String>,
+    /// Remaining query params, passed to the projection as options.
+    #[serde(flatten)]
+    #[param(ignore)]
+    pub options: HashMap<String, String>,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RawQuery {
+    /// Path of a file inside a zip/tar archive to stream instead of the
+    /// archive itself.
+    pub entry: Option<String>,
+    /// `1`/`true`/`yes` to serve the file as an attachment under its own
+    /// name instead of inline.
+    pub download: Option<String>,
+    /// Content version the URL was built for (see `content_version`). When
+    /// it matches the file, the response may be cached forever.
+    pub v: Option<String>,
+}
+
+impl RawQuery {
+    fn download(&self) -> bool {
+        matches!(self.download.as_deref(), Some("1" | "true" | "yes"))
+    }
+}
+
+/// `Content-Disposition: attachment` value for `name`: a quoted ASCII
+/// fallback for old clients plus the exact name as RFC 5987 `filename*`.
+pub(crate) fn attachment_disposition(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
+        })
+        .collect();
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        match byte {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+/// Final path component of a `/`-separated path.
+pub(crate) fn basename(path: &str) -> &str {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WriteQuery {
+    /// Create missing parent directories (default true). When false, a
+    /// write under a missing directory fails with 400.
+    pub create_dirs: Option<bool>,
+    /// Only replace an existing file (default false); a missing one is 404.
+    pub must_exist: Option<bool>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteQuery {
+    /// Remove non-empty directories with their contents (default true).
+    pub recursive: Option<bool>,
+    /// Must repeat the target path exactly for a recursive directory
+    /// delete to go ahead; without it the request fails with 428.
+    pub confirm: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RenameRequest {
+    /// Destination path relative to the workspace root.
+    pub to: String,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CopyRequest {
+    /// Destination path relative to the workspace root.
+    pub to: String,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Bytes to write into an existing file in place.
+#[derive(Deserialize, ToSchema)]
+pub struct PatchRequest {
+    /// Where the data goes, in bytes from the start of the file.
+    pub offset: u64,
+    /// The bytes to write, base64-encoded.
+    pub data_base64: String,
+    /// Allow an offset past the end of the file, filling the gap with
+    /// zeros. Without it such a write fails with 416.
+    #[serde(default)]
+    pub extend: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ChmodRequest {
+    /// Permission bits in octal, e.g. `"0644"` or `"755"`.
+    pub mode: String,
+    /// Also set the mode of everything below a directory.
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+impl ChmodRequest {
+    /// `mode` as bits: up to four octal digits, optionally after `0o`.
+    fn bits(&self) -> Result<u32, ApiError> {
+        let digits = self.mode.strip_prefix("0o").unwrap_or(&self.mode);
+        let valid = (1..=4).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit());
+        match u32::from_str_radix(digits, 8) {
+            Ok(bits) if valid => Ok(bits),
+            _ => Err(ApiError::BadRequest(format!(
+                "invalid mode {:?}: expected octal like \"0644\"",
+                self.mode
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct FileResponse {
     pub path: String,
+    /// Containing directory (`""` for the root); `None` for the root itself.
+    pub parent: Option<String>,
+    /// Trail from the root's first child down to this resource.
+    pub breadcrumbs: Vec<Breadcrumb>,
     pub is_dir: bool,
+    /// Content type sniffed from the file's bytes, else guessed from its
+    /// extension. `None` for directories and unrecognised files.
+    pub mime_type: Option<String>,
     pub projections: Vec<ProjectionInfo>,
     pub active_projection: String,
-    pub output: serde_json::Value,
+    /// How `active_projection` was picked, for explaining the choice.
+    pub selection: Selection,
+    /// Projections run for this response in order, ending with the one
+    /// whose output is shown unless all of them failed, in which case the
+    /// first failure is shown.
+    pub attempted: Vec<String>,
+    #[schema(value_type = ProjectionOutput)]
+    pub output: Arc<serde_json::Value>,
+    /// Headers and caching the projection asked for. Applied to the HTTP
+    /// response of a single-file GET rather than sent in the body.
+    #[serde(skip)]
+    pub meta: ProjectionMeta,
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct Breadcrumb {
+    pub name: String,
+    /// Path relative to the workspace root.
+    pub path: String,
 }
 
-fn error_response(status: StatusCode, msg: impl Into<String>) -> Response {
-    let body = Json(ErrorResponse { error: msg.into() });
-    (status, body).into_response()
+/// Breadcrumbs and parent for a request path, ignoring empty segments from
+/// leading, trailing or doubled slashes.
+fn navigation(path: &str) -> (Option<String>, Vec<Breadcrumb>) {
+    let mut crumbs: Vec<Breadcrumb> = Vec::new();
+    for name in path.split('/').filter(|s| !s.is_empty()) {
+        let path = match crumbs.last() {
+            Some(prev) => format!("{}/{name}", prev.path),
+            None => name.to_string(),
+        };
+        crumbs.push(Breadcrumb {
+            name: name.to_string(),
+            path,
+        });
+    }
+    let parent = match crumbs.len() {
+        0 => None,
+        1 => Some(String::new()),
+        n => Some(crumbs[n - 2].path.clone()),
+    };
+    (parent, crumbs)
+}
+
+/// Leading bytes read for content sniffing.
+const SNIFF_BYTES: u64 = 8192;
+
+/// Build the resource for `path`, sniffing a file's content type from its
+/// first bytes so extensionless or misnamed files still find a viewer.
+pub(crate) async fn sniffed_resource(state: &AppState, path: &str, meta: &FileStat) -> Resource {
+    let resource = Resource::new(path.to_string(), meta.is_dir);
+    if meta.is_dir {
+        return resource;
+    }
+    let mime_type = match state.store.read_at(Path::new(path), 0, SNIFF_BYTES).await {
+        // A text name wins over a binary signature when the bytes do look
+        // like text, so odd-looking source isn't stuck on the hex viewer.
+        Ok(head) if TextRaw::claims_name(&resource) && TextRaw::looks_like_text(&head) => None,
+        Ok(head) => infer::get(&head).map(|t| t.mime_type().to_string()),
+        Err(e) => {
+            tracing::debug!("content sniffing failed for {path:?}: {e}");
+            None
+        }
+    };
+    resource.with_mime_type(mime_type)
+}
+
+/// MIME type reported for `resource`: the sniffed type, else a guess from
+/// the name. `None` for directories and unrecognised files.
+pub(crate) fn mime_type(resource: &Resource) -> Option<String> {
+    if resource.is_dir {
+        return None;
+    }
+    resource.mime_type.clone().or_else(|| {
+        mime_guess::from_path(&resource.path)
+            .first()
+            .map(|m| m.to_string())
+    })
+}
+
+/// Cache key for a projection of `path` in its current version. Directory
+/// listings depend on their children's metadata, which the directory's own
+/// mtime doesn't track, so only files are cached.
+fn cache_key(
+    path: &str,
+    projection: &str,
+    meta: &FileStat,
+    options: &HashMap<String, String>,
+) -> Option<CacheKey> {
+    if meta.is_dir {
+        return None;
+    }
+    let modified = meta.modified?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(CacheKey {
+        path: path.to_string(),
+        projection: projection.to_string(),
+        modified_nanos: modified.as_nanos(),
+        size: meta.len,
+        options: options.clone().into_iter().collect(),
+    })
+}
+
+/// Turn a projection failure that means "this projection can't handle the
+/// file" into an in-band output, so the client still gets the list of
+/// alternatives. Bad options and missing or inaccessible files are handed
+/// back to keep their status codes.
+fn in_band_error(e: ProjectionError) -> Result<ProjectionOutput, ProjectionError> {
+    match e {
+        // Undecodable content is the projection's problem, not the file's
+        ProjectionError::Workspace(WorkspaceError::Io(io))
+            if io.kind() == std::io::ErrorKind::InvalidData =>
+        {
+            Ok(ProjectionOutput::Error {
+                message: io.to_string(),
+            })
+        }
+        // So is needing a file on disk when the store has none
+        ProjectionError::Unsupported
+        | ProjectionError::Other(_)
+        | ProjectionError::Workspace(WorkspaceError::NotLocal(_)) => Ok(ProjectionOutput::Error {
+            message: e.to_string(),
+        }),
+        ProjectionError::InvalidOption(_) | ProjectionError::Workspace(_) => Err(e),
+    }
+}
+
+/// Stat `path` in the store projections read from.
+async fn stat(state: &AppState, path: &str) -> Result<FileStat, ApiError> {
+    Ok(state.store.stat(Path::new(path)).await?)
 }
 
 async fn project_resource(
     state: &Arc<AppState>,
     path: &str,
     query: &FileQuery,
-) -> Result<Response, Response> {
-    // Resolve the path to check it exists and stays in workspace
-    let resolved = state
-        .workspace
-        .resolve(path)
-        .map_err(|e| error_response(StatusCode::BAD_REQUEST, e.to_string()))?;
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let meta = stat(state, path).await?;
 
-    let meta = tokio::fs::metadata(&resolved)
-        .await
-        .map_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => error_response(StatusCode::NOT_FOUND, "not found"),
-            _ => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
-        })?;
+    let request = ProjectionRequest::new(query.options.clone());
+    if request.get("stream") == Some("ndjson") {
+        if query
+            .projection
+            .as_deref()
+            .is_some_and(|id| id != "dir.list")
+        {
+            return Err(ApiError::InvalidOption(
+                "stream=ndjson is only supported by dir.list".to_string(),
+            ));
+        }
+        if !meta.is_dir {
+            return Err(ApiError::NotADirectory(format!(
+                "{path} is not a directory"
+            )));
+        }
+        return stream_entries(state, path, &request).await;
+    }
+    if request.flag("stream")? == Some(true) {
+        if query
+            .projection
+            .as_deref()
+            .is_some_and(|id| id != "text.raw")
+        {
+            return Err(ApiError::InvalidOption(
+                "stream=true is only supported by text.raw".to_string(),
+            ));
+        }
+        if meta.is_dir {
+            return Err(ApiError::IsDirectory);
+        }
+        let (file, _) = state.workspace.open(Path::new(path)).await?;
+        return Ok(stream_lines(file));
+    }
+
+    // `?format=` must be honoured; `Accept` is a preference, so projections
+    // without an HTML form still answer in JSON
+    let explicit_format = request.format()?;
+    let format = explicit_format.or(accepts_html(headers).then_some(RenderFormat::Html));
+    let mut query = Cow::Borrowed(query);
+    if let (Some(format), None) = (format, explicit_format) {
+        let options = &mut query.to_mut().options;
+        options.insert("format".to_string(), format.to_string());
+    }
 
-    let resource = Resource::new(path.to_string(), meta.is_dir());
+    let mut response = file_response(state, path, &query, &meta).await?;
+    let meta = std::mem::take(&mut response.meta);
+    let rendered = format.and_then(|format| {
+        let declared = state
+            .registry
+            .get(&response.active_projection)
+            .is_some_and(|p| p.formats().contains(&format));
+        let body = declared
+            .then(|| format.render(&response.output))
+            .flatten()?;
+        Some((format, body.to_string()))
+    });
+    let mut response = match (rendered, explicit_format) {
+        (Some((format, body)), _) => {
+            ([(header::CONTENT_TYPE, format.mime_type())], body).into_response()
+        }
+        (None, Some(format)) if !response.output["type"].as_str().is_some_and(is_failure) => {
+            return Err(ApiError::NotAcceptable(format!(
+                "{} has no {format} form",
+                response.active_projection
+            )));
+        }
+        (None, _) => {
+            // Still a full response, so the client can offer the other
+            // projections
+            let status = if response.output["type"] == "Timeout" {
+                StatusCode::GATEWAY_TIMEOUT
+            } else {
+                StatusCode::OK
+            };
+            (status, Json(response)).into_response()
+        }
+    };
+    apply_meta(response.headers_mut(), meta);
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("Accept"));
+    Ok(response)
+}
+
+/// Output types that report a failure rather than the file's contents.
+fn is_failure(output_type: &str) -> bool {
+    matches!(output_type, "Error" | "Timeout" | "TooLarge")
+}
+
+/// Whether `Accept` ranks HTML above JSON. Wildcards count toward JSON,
+/// the default, so only clients naming HTML get it.
+fn accepts_html(headers: &HeaderMap) -> bool {
+    let (mut html, mut json) = (0.0_f32, 0.0_f32);
+    let ranges = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','));
+    for range in ranges {
+        let mut params = range.split(';');
+        let media = params.next().unwrap_or("").trim().to_ascii_lowercase();
+        let q = params
+            .find_map(|p| p.trim().strip_prefix("q="))
+            .and_then(|q| q.parse().ok())
+            .unwrap_or(1.0);
+        match media.as_str() {
+            "text/html" => html = html.max(q),
+            "application/json" | "application/*" | "*/*" => json = json.max(q),
+            _ => {}
+        }
+    }
+    html > json
+}
+
+/// Set the headers a projection asked for. Ones that aren't valid HTTP
+/// are logged and dropped rather than failing the request.
+fn apply_meta(headers: &mut HeaderMap, meta: ProjectionMeta) {
+    use axum::http::HeaderName;
+
+    for (name, value) in meta.headers {
+        match (
+            HeaderName::try_from(name.as_str()),
+            HeaderValue::try_from(value.as_str()),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => tracing::warn!("projection set an invalid header {name:?}, ignoring"),
+        }
+    }
+    if let Some(ttl) = meta.cache_ttl {
+        let value = format!("private, max-age={}", ttl.as_secs());
+        headers.insert(header::CACHE_CONTROL, value.parse().unwrap());
+    }
+}
+
+/// Project an existing resource and wrap the output with its navigation
+/// and the projections available for it.
+async fn file_response(
+    state: &AppState,
+    path: &str,
+    query: &FileQuery,
+    meta: &FileStat,
+) -> Result<FileResponse, ApiError> {
+    let resource = sniffed_resource(state, path, meta).await;
     let projections = state.registry.available_for(&resource);
 
     // Pick the projection
     let projection = if let Some(ref id) = query.projection {
-        state.registry.get(id).ok_or_else(|| {
-            error_response(StatusCode::BAD_REQUEST, format!("unknown projection: {id}"))
-        })?
+        state
+            .registry
+            .get(id)
+            .ok_or_else(|| ApiError::UnknownProjection(id.clone()))?
     } else {
         state
             .registry
             .best_for(&resource)
-            .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "no projection available"))?
+            .ok_or(ApiError::NoProjection)?
     };
 
-    let active_projection = projection.id().to_string();
-    let output = projection
-        .project(&resource, &state.workspace)
-        .await
-        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // A projection that can't handle the file hands over to the next in
+    // the fallback chain. Explicit requests show their failure instead
+    let mut candidates = vec![projection.clone()];
+    if query.projection.is_none() {
+        candidates.extend(state.registry.fallbacks_for(&resource, projection.id()));
+    }
+    let mut attempted = Vec::new();
+    let mut first_failure = None;
+    let mut succeeded = None;
+    for candidate in candidates {
+        attempted.push(candidate.id().to_string());
+        match run_projection(state, path, &resource, &candidate, query, meta).await? {
+            Ok(output) => {
+                succeeded = Some((candidate, output));
+                break;
+            }
+            Err(error) => {
+                let output = CachedOutput {
+                    value: error,
+                    meta: ProjectionMeta::default(),
+                };
+                first_failure.get_or_insert((candidate, output));
+            }
+        }
+    }
+    let (projection, output) = succeeded
+        .or(first_failure)
+        .expect("at least one projection was attempted");
 
-    let output_value = serde_json::to_value(&output)
-        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let active_projection = projection.id().to_string();
+    let mut selection =
+        state
+            .registry
+            .selection(projection.as_ref(), &resource, query.projection.is_some());
+    if attempted[0] != active_projection {
+        selection.reason = SelectionReason::Fallback;
+    }
+    tracing::Span::current().record("projection", active_projection.as_str());
 
-    let response = FileResponse {
+    let mime_type = mime_type(&resource);
+    let (parent, breadcrumbs) = navigation(path);
+    Ok(FileResponse {
         path: path.to_string(),
-        is_dir: meta.is_dir(),
+        parent,
+        breadcrumbs,
+        is_dir: meta.is_dir,
+        mime_type,
         projections,
         active_projection,
-        output: output_value,
+        selection,
+        attempted,
+        output: output.value,
+        meta: output.meta,
+    })
+}
+
+/// Output of `projection` for `resource`, from the cache when possible.
+/// A failure that means the projection can't handle the file comes back as
+/// `Err` holding an in-band error output, so the caller can try another.
+async fn run_projection(
+    state: &AppState,
+    path: &str,
+    resource: &Resource,
+    projection: &Arc<dyn Projection>,
+    query: &FileQuery,
+    meta: &FileStat,
+) -> Result<Result<CachedOutput, Arc<serde_json::Value>>, ApiError> {
+    let to_value = |output: &ProjectionOutput| {
+        serde_json::to_value(output)
+            .map(Arc::new)
+            .map_err(|e| ApiError::Internal(e.to_string()))
     };
+    let id = projection.id();
+    let too_large = state
+        .projection_size_limit(projection.as_ref())
+        .filter(|&limit| !meta.is_dir && meta.len > limit);
+    let cache_key = cache_key(path, id, meta, &query.options);
+    if let Some(cached) = cache_key.as_ref().and_then(|key| state.cache.get(key)) {
+        metrics::projection_run(id, "cached", None);
+        return Ok(Ok(cached));
+    }
+    if let Some(limit) = too_large {
+        metrics::projection_run(id, "too_large", None);
+        return Ok(Ok(CachedOutput {
+            value: to_value(&ProjectionOutput::TooLarge {
+                size: meta.len,
+                limit,
+                raw_url: versioned_raw_url(path, meta.version().as_deref()),
+            })?,
+            meta: ProjectionMeta::default(),
+        }));
+    }
 
-    Ok(Json(response).into_response())
+    let request = ProjectionRequest::new(query.options.clone());
+    let started = std::time::Instant::now();
+    let projected = projection.project_with_meta(resource, state.store.as_ref(), &request);
+    // Dropping the future abandons the request, though blocking work the
+    // projection handed to another thread runs on until it finishes
+    let result = match state.projection_timeout {
+        Some(limit) => match tokio::time::timeout(limit, projected).await {
+            Ok(result) => result,
+            Err(_) => {
+                metrics::projection_run(id, "timeout", Some(limit));
+                tracing::warn!("projection {id} timed out after {limit:?} for {path:?}");
+                return Ok(Err(to_value(&ProjectionOutput::Timeout {
+                    seconds: limit.as_secs(),
+                })?));
+            }
+        },
+        None => projected.await,
+    };
+    let elapsed = started.elapsed();
+    tracing::debug!("projected {path:?} with {id} in {elapsed:?}");
+    match result {
+        Ok((output, output_meta)) => {
+            metrics::projection_run(id, "ok", Some(elapsed));
+            let output = CachedOutput {
+                value: to_value(&output)?,
+                meta: output_meta,
+            };
+            if let Some(key) = cache_key {
+                state.cache.insert(key, output.clone());
+            }
+            Ok(Ok(output))
+        }
+        Err(e) => {
+            metrics::projection_run(id, "error", Some(elapsed));
+            tracing::debug!("projection {id} failed for {path:?}: {e}");
+            // Not cached, so the next request retries
+            Ok(Err(to_value(&in_band_error(e)?)?))
+        }
+    }
+}
+
+/// Most paths accepted by one batch request.
+const MAX_BATCH_PATHS: usize = 200;
+
+/// Paths projected at once while serving a batch.
+const BATCH_CONCURRENCY: usize = 8;
+
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    pub paths: Vec<String>,
+    /// Projection for every path; each path's best projection when absent.
+    pub projection: Option<String>,
+}
+
+/// Result for one path of a batch: the same body as a single GET, or the
+/// error that GET would have returned.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BatchItem {
+    File(FileResponse),
+    Error {
+        path: String,
+        status: u16,
+        error: String,
+        code: &'static str,
+    },
+}
+
+/// Project many paths in one round-trip, e.g. every image in a folder.
+/// Results come back in request order, one per path.
+async fn batch_files(state: &Arc<AppState>, body: BatchRequest) -> Result<Response, ApiError> {
+    use futures::StreamExt;
+
+    if body.paths.len() > MAX_BATCH_PATHS {
+        return Err(ApiError::BadRequest(format!(
+            "at most {MAX_BATCH_PATHS} paths per batch"
+        )));
+    }
+    let query = FileQuery {
+        projection: body.projection,
+        options: HashMap::new(),
+    };
+    let items: Vec<_> = futures::stream::iter(body.paths)
+        .map(|path| {
+            let query = &query;
+            async move {
+                let result = match stat(state, &path).await {
+                    Ok(meta) => file_response(state, &path, query, &meta).await,
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(file) => (BatchItem::File(file), None),
+                    Err(e) => {
+                        let traversal = match &e {
+                            ApiError::PathTraversal(path) => Some(path.clone()),
+                            _ => None,
+                        };
+                        let item = BatchItem::Error {
+                            path,
+                            status: e.status().as_u16(),
+                            error: e.to_string(),
+                            code: e.code(),
+                        };
+                        (item, traversal)
+                    }
+                }
+            }
+        })
+        .buffered(BATCH_CONCURRENCY)
+        .collect()
+        .await;
+    let (items, traversals): (Vec<BatchItem>, Vec<Option<String>>) = items.into_iter().unzip();
+    let traversals: Vec<String> = traversals.into_iter().flatten().collect();
+
+    let mut response = Json(items).into_response();
+    // Refused items are audited like refused requests
+    if !traversals.is_empty() {
+        response
+            .extensions_mut()
+            .insert(TraversalRejected(traversals));
+    }
+    Ok(response)
+}
+
+/// Longest line sent in full by `stream_lines`; the rest of a longer line
+/// is skipped so a file without newlines can't be buffered whole.
+const MAX_STREAMED_LINE: usize = 1024 * 1024;
+
+#[derive(Serialize)]
+struct StreamedLine<'a> {
+    /// One-based line number.
+    line: usize,
+    text: &'a str,
+    /// The line was longer than `MAX_STREAMED_LINE` and was cut short.
+    truncated: bool,
+}
+
+/// Read the next line into `buf` without its terminator, keeping at most
+/// `MAX_STREAMED_LINE` bytes. Returns whether it was truncated, or `None`
+/// at end of file.
+async fn next_line<R: tokio::io::AsyncBufRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<Option<bool>> {
+    use tokio::io::AsyncBufReadExt;
+
+    buf.clear();
+    let mut truncated = false;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok((!buf.is_empty() || truncated).then_some(truncated));
+        }
+        let (chunk, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (&available[..i], Some(i + 1)),
+            None => (available, None),
+        };
+        let room = MAX_STREAMED_LINE - buf.len();
+        truncated |= chunk.len() > room;
+        buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        let consumed = done.unwrap_or(available.len());
+        reader.consume(consumed);
+        if done.is_some() {
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+            return Ok(Some(truncated));
+        }
+    }
+}
+
+/// Stream a text file as newline-delimited JSON, one `StreamedLine` per
+/// line, reading incrementally so memory stays bounded however large the
+/// file is. Invalid UTF-8 is replaced rather than failing the stream.
+fn stream_lines(file: LockedFile) -> Response {
+    let reader = tokio::io::BufReader::new(file);
+    let lines = futures::stream::unfold(Some((reader, 0usize, Vec::new())), |state| async move {
+        let (mut reader, n, mut buf) = state?;
+        match next_line(&mut reader, &mut buf).await {
+            Ok(None) => None,
+            Ok(Some(truncated)) => {
+                let line = StreamedLine {
+                    line: n + 1,
+                    text: &String::from_utf8_lossy(&buf),
+                    truncated,
+                };
+                let mut json = serde_json::to_vec(&line).expect("line serializes");
+                json.push(b'\n');
+                Some((
+                    Ok(axum::body::Bytes::from(json)),
+                    Some((reader, n + 1, buf)),
+                ))
+            }
+            // Ends the body; the client sees an incomplete response
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .body(Body::from_stream(lines))
+        .unwrap()
+}
+
+/// Stream a directory listing as newline-delimited JSON, one `dir.list`
+/// entry per line in the order the filesystem returns them. Unlike the
+/// buffered listing this is unsorted and has no `total`, but memory stays
+/// constant and the first entries arrive before the directory is fully
+/// read. Honours `hidden=false` and `limit`; other `dir.list` options
+/// aren't supported here.
+async fn stream_entries(
+    state: &AppState,
+    path: &str,
+    request: &ProjectionRequest,
+) -> Result<Response, ApiError> {
+    use futures::StreamExt;
+
+    let show_hidden = request.flag("hidden")?.unwrap_or(true);
+    let limit = request.limit()?.unwrap_or(usize::MAX);
+    let entries = state
+        .workspace
+        .read_dir_stream(Path::new(path))
+        .await?
+        .filter(move |entry| {
+            let hidden = entry.as_ref().is_ok_and(|e| e.name.starts_with('.'));
+            std::future::ready(show_hidden || !hidden)
+        })
+        .take(limit)
+        .map(|entry| {
+            let mut json =
+                serde_json::to_vec(&DirectoryEntry::from(entry?)).expect("entry serializes");
+            json.push(b'\n');
+            Ok::<_, WorkspaceError>(axum::body::Bytes::from(json))
+        });
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .body(Body::from_stream(entries))
+        .unwrap())
+}
+
+/// Metadata-only counterpart of `project_resource`: reports what a GET would
+/// project without running the projection.
+async fn head_resource(
+    state: &AppState,
+    path: &str,
+    query: &FileQuery,
+) -> Result<Response, ApiError> {
+    let meta = stat(state, path).await?;
+    let resource = sniffed_resource(state, path, &meta).await;
+    let active = match &query.projection {
+        Some(id) => Some(
+            state
+                .registry
+                .get(id)
+                .ok_or_else(|| ApiError::UnknownProjection(id.clone()))?,
+        ),
+        None => state.registry.best_for(&resource),
+    };
+
+    let mut builder = Response::builder().header("x-is-dir", meta.is_dir.to_string());
+    if let Some(projection) = active {
+        builder = builder.header("x-active-projection", projection.id());
+    }
+    if !meta.is_dir {
+        // The headers a raw GET of the file would send
+        let mut mime = raw_mime(path);
+        if is_text_mime(&mime) {
+            let head = state.store.read_at(Path::new(path), 0, SNIFF_BYTES).await?;
+            mime = with_charset(mime, &head);
+        }
+        builder = builder
+            .header(header::CONTENT_LENGTH, meta.len)
+            .header(header::CONTENT_TYPE, mime);
+        if let Some(version) = meta.version() {
+            builder = builder.header(header::ETAG, format!("\"{version}\""));
+        }
+    }
+    if let Some(modified) = meta.modified {
+        builder = builder.header(header::LAST_MODIFIED, httpdate::fmt_http_date(modified));
+    }
+    Ok(builder.body(Body::empty()).unwrap())
+}
+
+pub async fn head_root(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FileQuery>,
+) -> Result<Response, ApiError> {
+    head_resource(&state, "", &query).await
+}
+
+pub async fn head_file(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    Query(query): Query<FileQuery>,
+) -> Result<Response, ApiError> {
+    head_resource(&state, &path, &query).await
 }
 
+/// Project the workspace root.
+#[utoipa::path(
+    get,
+    path = "/api/files/",
+    tag = "files",
+    params(FileQuery),
+    responses(
+        (status = 200, description = "The resource and its projection output", body = FileResponse),
+        (status = 400, description = "Invalid path, option or projection", body = ErrorBody),
+        (status = 401, description = "Access token missing or wrong", body = ErrorBody),
+        (status = 404, description = "No such file or directory", body = ErrorBody),
+        (status = 504, description = "The projection timed out; `output` is a `Timeout`", body = FileResponse),
+    ),
+)]
 pub async fn get_root(
     State(state): State<Arc<AppState>>,
     Query(query): Query<FileQuery>,
-) -> Response {
-    match project_resource(&state, "", &query).await {
-        Ok(r) => r,
-        Err(r) => r,
-    }
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    project_resource(&state, "", &query, &headers).await
 }
 
+/// Project a file or directory with its best projection, or the one named
+/// by `?projection=`. With `?format=html`, or an `Accept` header preferring
+/// `text/html`, projections that have an HTML form send it alone instead.
+#[utoipa::path(
+    get,
+    path = "/api/files/{path}",
+    tag = "files",
+    params(
+        ("path" = String, Path, description = "Path relative to the workspace root"),
+        FileQuery,
+    ),
+    responses(
+        (status = 200, description = "The resource and its projection output", body = FileResponse),
+        (status = 400, description = "Invalid path, option or projection", body = ErrorBody),
+        (status = 401, description = "Access token missing or wrong", body = ErrorBody),
+        (status = 404, description = "No such file or directory", body = ErrorBody),
+        (status = 406, description = "`format` asks for a form the projection doesn't have", body = ErrorBody),
+        (status = 504, description = "The projection timed out; `output` is a `Timeout`", body = FileResponse),
+    ),
+)]
 pub async fn get_file(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(path): axum::extract::Path<String>,
     Query(query): Query<FileQuery>,
-) -> Response {
-    match project_resource(&state, &path, &query).await {
-        Ok(r) => r,
-        Err(r) => r,
-    }
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    project_resource(&state, &path, &query, &headers).await
 }
 
+/// `Cache-Control` for raw responses whose `?v=` matches the file.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Stream a file's bytes. Supports `Range` and conditional requests.
+#[utoipa::path(
+    get,
+    path = "/api/files/raw/{path}",
+    tag = "files",
+    params(
+        ("path" = String, Path, description = "Path relative to the workspace root"),
+        RawQuery,
+    ),
+    responses(
+        (status = 200, description = "The file's contents", content_type = "application/octet-stream"),
+        (status = 206, description = "The requested byte range", content_type = "application/octet-stream"),
+        (status = 304, description = "Unchanged since the client's copy"),
+        (status = 400, description = "Invalid path, option or projection", body = ErrorBody),
+        (status = 401, description = "Access token missing or wrong", body = ErrorBody),
+        (status = 404, description = "No such file or directory", body = ErrorBody),
+        (status = 416, description = "Range outside the file", body = ErrorBody),
+    ),
+)]
 pub async fn raw_file(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(path): axum::extract::Path<String>,
-) -> Response {
-    let data = match state.workspace.read(Path::new(&path)).await {
-        Ok(d) => d,
-        Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    Query(query): Query<RawQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let download = query.download();
+    if let Some(entry) = query.entry {
+        return archive_entry(&state, &path, entry, download).await;
+    }
+
+    let (mut file, meta) = state.workspace.open(Path::new(&path)).await?;
+    if meta.is_dir() {
+        return Err(ApiError::IsDirectory);
+    }
+    let len = meta.len();
+
+    let etag = etag(&meta);
+    let modified = meta.modified().ok();
+    // A versioned URL names one version of the file, so a match can be
+    // cached for good. A stale one still gets the current content, which
+    // must then be revalidated like an unversioned request.
+    let cache_control = match &query.v {
+        Some(v) if content_version(&meta).as_ref() == Some(v) => IMMUTABLE,
+        _ => "no-cache",
     };
+    if not_modified(&headers, etag.as_deref(), modified) {
+        let mut builder = Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::CACHE_CONTROL, cache_control);
+        if let Some(etag) = &etag {
+            builder = builder.header(header::ETAG, etag);
+        }
+        return Ok(builder.body(Body::empty()).unwrap());
+    }
+
+    let mut mime = raw_mime(&path);
+    if is_text_mime(&mime) {
+        let mut head = Vec::with_capacity(SNIFF_BYTES as usize);
+        (&mut file).take(SNIFF_BYTES).read_to_end(&mut head).await?;
+        file.rewind().await?;
+        mime = with_charset(mime, &head);
+    }
+
+    let sandbox = runs_scripts(&mime);
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, cache_control);
+    if sandbox {
+        builder = builder.header(header::CONTENT_SECURITY_POLICY, "sandbox");
+    }
+    if let Some(etag) = &etag {
+        builder = builder.header(header::ETAG, etag);
+    }
+    if let Some(modified) = modified {
+        builder = builder.header(header::LAST_MODIFIED, httpdate::fmt_http_date(modified));
+    }
+    if download {
+        builder = builder.header(
+            header::CONTENT_DISPOSITION,
+            attachment_disposition(basename(&path)),
+        );
+    }
 
-    let mime = mime_guess::from_path(&path)
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_range(v, len));
+
+    match range {
+        None => Ok(builder
+            .header(header::CONTENT_LENGTH, len)
+            .body(Body::from_stream(ReaderStream::new(file)))
+            .unwrap()),
+        Some(Ok((start, end))) => {
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+            let count = end - start + 1;
+            Ok(builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_LENGTH, count)
+                .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{len}"))
+                .body(Body::from_stream(ReaderStream::new(file.take(count))))
+                .unwrap())
+        }
+        Some(Err(())) => Err(ApiError::RangeNotSatisfiable(len)),
+    }
+}
+
+/// `Content-Type` guessed from `path` for its raw bytes, before any charset.
+fn raw_mime(path: &str) -> String {
+    mime_guess::from_path(path)
+        .first()
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string())
+}
+
+/// `mime` with the charset sniffed from the file's leading bytes, if any.
+fn with_charset(mime: String, head: &[u8]) -> String {
+    match TextRaw::charset(head) {
+        Some(encoding) => format!("{mime}; charset={}", encoding.name().to_ascii_lowercase()),
+        None => mime,
+    }
+}
+
+/// Whether `mime` names text the browser decodes, and so wants a
+/// `charset`: `text/*` plus the JSON, XML and JavaScript types.
+fn is_text_mime(mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or(mime).trim();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence,
+            "application/json" | "application/xml" | "application/javascript"
+        )
+}
+
+/// Whether `mime` names a document that can run scripts when opened on its
+/// own, like SVG. Those are served with `Content-Security-Policy: sandbox`,
+/// so following a raw link can't run anything with the server's origin.
+/// Showing them through `<img>` is unaffected.
+fn runs_scripts(mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or(mime).trim();
+    essence == "image/svg+xml"
+}
+
+/// Stream a single entry out of the archive at `path`. Decompression runs on
+/// a blocking thread that feeds the response body through a pipe, so large
+/// entries are never buffered in memory.
+async fn archive_entry(
+    state: &AppState,
+    path: &str,
+    entry: String,
+    download: bool,
+) -> Result<Response, ApiError> {
+    let meta = state.workspace.metadata(Path::new(path)).await?;
+    if meta.is_dir() {
+        return Err(ApiError::IsDirectory);
+    }
+    let resource = sniffed_resource(state, path, &FileStat::from(&meta)).await;
+    let Some(kind) = ArchiveKind::for_resource(&resource) else {
+        return Err(ApiError::BadRequest("not an archive".to_string()));
+    };
+    let archive_path = state.workspace.resolve(path)?;
+
+    // The entry's own name decides the content type; nosniff below keeps
+    // the browser from second-guessing it.
+    let mime = mime_guess::from_path(&entry)
         .first()
         .map(|m| m.to_string())
         .unwrap_or_else(|| "application/octet-stream".to_string());
 
-    Response::builder()
+    let disposition = download.then(|| attachment_disposition(basename(&entry)));
+
+    let (found_tx, found_rx) = tokio::sync::oneshot::channel();
+    let (reader, writer) = tokio::io::duplex(64 * 1024);
+    tokio::task::spawn_blocking(move || {
+        let mut found_tx = Some(found_tx);
+        let result = archive::extract(&archive_path, kind, &entry, |size| {
+            if let Some(tx) = found_tx.take() {
+                let _ = tx.send(Ok(size));
+            }
+            SyncIoBridge::new(writer)
+        });
+        match (result, found_tx) {
+            (Err(e), Some(tx)) => {
+                let _ = tx.send(Err(e));
+            }
+            (Err(e), None) => {
+                tracing::debug!("archive entry stream for {entry:?} ended early: {e}");
+            }
+            (Ok(_), _) => {}
+        }
+    });
+
+    let size = match found_rx.await {
+        Ok(Ok(size)) => size,
+        Ok(Err(e)) => {
+            return Err(match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    ApiError::NotFound("entry not found in archive".to_string())
+                }
+                std::io::ErrorKind::InvalidInput => {
+                    ApiError::BadRequest("entry is a directory".to_string())
+                }
+                _ => ApiError::Unprocessable(format!("unreadable archive: {e}")),
+            });
+        }
+        Err(_) => return Err(ApiError::Internal("archive reader failed".to_string())),
+    };
+
+    let sandbox = runs_scripts(&mime);
+    let mut builder = Response::builder()
         .header(header::CONTENT_TYPE, mime)
-        .body(Body::from(data))
-        .unwrap()
+        .header(header::CONTENT_LENGTH, size)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    if sandbox {
+        builder = builder.header(header::CONTENT_SECURITY_POLICY, "sandbox");
+    }
+    if let Some(disposition) = disposition {
+        builder = builder.header(header::CONTENT_DISPOSITION, disposition);
+    }
+    Ok(builder
+        .body(Body::from_stream(ReaderStream::new(reader)))
+        .unwrap())
+}
+
+/// Strong validator derived from modification time and size, so it stays
+/// stable across restarts while the file is unchanged.
+pub(crate) fn etag(meta: &std::fs::Metadata) -> Option<String> {
+    content_version(meta).map(|v| format!("\"{v}\""))
+}
+
+/// Evaluate `If-None-Match` / `If-Modified-Since` against the current
+/// validators. `If-None-Match` takes precedence when present.
+pub(crate) fn not_modified(
+    headers: &HeaderMap,
+    etag: Option<&str>,
+    modified: Option<std::time::SystemTime>,
+) -> bool {
+    if let Some(inm) = headers.get(header::IF_NONE_MATCH) {
+        let Some(etag) = etag else {
+            return false;
+        };
+        return inm.to_str().is_ok_and(|v| {
+            v.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == etag
+            })
+        });
+    }
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok());
+    match (since, modified) {
+        // HTTP dates have second precision
+        (Some(since), Some(modified)) => httpdate::HttpDate::from(modified) <= since.into(),
+        _ => false,
+    }
 }
 
+/// Parse a single-range `Range` header against a resource of `len` bytes,
+/// returning the inclusive byte range. `None` means the header should be
+/// ignored (malformed or multi-range) and the full body served; `Err` means
+/// the range can't be satisfied.
+fn parse_range(header: &str, len: u64) -> Option<std::result::Result<(u64, u64), ()>> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return None,
+        // Suffix range: the last N bytes
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 || len == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let start: u64 = start.parse().ok()?;
+            let end: u64 = end.parse().ok()?;
+            if end < start {
+                return None;
+            }
+            (start, end.min(len.saturating_sub(1)))
+        }
+    };
+    if start >= len {
+        return Some(Err(()));
+    }
+    Some(Ok((start, end)))
+}
+
+/// Evaluate `If-Match` against the current ETag (`None` when the file
+/// doesn't exist). Weak tags never match, as the comparison is strong.
+/// Without the header every write proceeds.
+fn if_match_allows(headers: &HeaderMap, etag: Option<&str>) -> bool {
+    let Some(im) = headers.get(header::IF_MATCH) else {
+        return true;
+    };
+    let Some(etag) = etag else {
+        return false;
+    };
+    im.to_str().is_ok_and(|v| {
+        v.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag == etag
+        })
+    })
+}
+
+/// Write a file. With `If-Match`, the write only happens if the file is
+/// still the version the client last read (412 otherwise), so concurrent
+/// editors can't silently overwrite each other. Writes to one path run one
+/// at a time, each seeing the last. The new ETag is returned.
+#[utoipa::path(
+    put,
+    path = "/api/files/{path}",
+    tag = "files",
+    params(
+        ("path" = String, Path, description = "Path relative to the workspace root"),
+        WriteQuery,
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 204, description = "Written; the new version is in `ETag`"),
+        (status = 400, description = "Invalid path, option or projection", body = ErrorBody),
+        (status = 401, description = "Access token missing or wrong", body = ErrorBody),
+        (status = 403, description = "Server is read-only or the request is cross-origin", body = ErrorBody),
+        (status = 404, description = "No such file or directory", body = ErrorBody),
+        (status = 412, description = "`If-Match` no longer matches the file", body = ErrorBody),
+        (status = 429, description = "Over the write rate limit; see `Retry-After`", body = ErrorBody),
+    ),
+)]
 pub async fn put_file(
     State(state): State<Arc<AppState>>,
     axum::extract::Path(path): axum::extract::Path<String>,
+    Query(query): Query<WriteQuery>,
+    headers: HeaderMap,
     body: axum::body::Bytes,
-) -> Response {
-    match state.workspace.write(Path::new(&path), &body).await {
-        Ok(()) => StatusCode::NO_CONTENT.into_response(),
-        Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+) -> Result<Response, ApiError> {
+    // Held from the checks through the write, so they still hold when it lands
+    let guard = state.workspace.lock(Path::new(&path)).await?;
+    let current = state.workspace.metadata(Path::new(&path)).await.ok();
+    if query.must_exist == Some(true) {
+        match &current {
+            Some(meta) if meta.is_dir() => return Err(ApiError::IsDirectory),
+            Some(_) => {}
+            None => return Err(ApiError::NotFound(format!("{path} does not exist"))),
+        }
+    }
+    if query.create_dirs == Some(false) && current.is_none() {
+        let parent = path
+            .trim_end_matches('/')
+            .rsplit_once('/')
+            .map_or("", |(p, _)| p);
+        let parent_is_dir = state
+            .workspace
+            .metadata(Path::new(parent))
+            .await
+            .is_ok_and(|meta| meta.is_dir());
+        if !parent_is_dir {
+            return Err(ApiError::BadRequest(format!(
+                "parent directory does not exist: {parent}"
+            )));
+        }
+    }
+    if headers.contains_key(header::IF_MATCH) {
+        let current = current
+            .filter(|meta| meta.is_file())
+            .and_then(|meta| etag(&meta));
+        if !if_match_allows(&headers, current.as_deref()) {
+            return Err(ApiError::PreconditionFailed(
+                "file changed since it was read".to_string(),
+            ));
+        }
+    }
+    state.workspace.write_locked(&guard, &body).await?;
+    let mut builder = Response::builder().status(StatusCode::NO_CONTENT);
+    if let Some(etag) = state
+        .workspace
+        .metadata(Path::new(&path))
+        .await
+        .ok()
+        .and_then(|meta| etag(&meta))
+    {
+        builder = builder.header(header::ETAG, etag);
+    }
+    Ok(builder.body(Body::empty()).unwrap())
+}
+
+/// Overwrite part of a file in place, without sending the rest of it. The
+/// file must exist, and `If-Match` guards the write as for `PUT`. Writes
+/// past the end extend the file. The new ETag is returned.
+#[utoipa::path(
+    patch,
+    path = "/api/files/{path}",
+    tag = "files",
+    params(("path" = String, Path, description = "Path relative to the workspace root")),
+    request_body = PatchRequest,
+    responses(
+        (status = 204, description = "Written; the new version is in `ETag`"),
+        (status = 400, description = "Invalid path or data", body = ErrorBody),
+        (status = 401, description = "Access token missing or wrong", body = ErrorBody),
+        (status = 403, description = "Server is read-only or the request is cross-origin", body = ErrorBody),
+        (status = 404, description = "No such file", body = ErrorBody),
+        (status = 412, description = "`If-Match` no longer matches the file", body = ErrorBody),
+        (status = 416, description = "Offset past the end of the file without `extend`", body = ErrorBody),
+        (status = 429, description = "Over the write rate limit; see `Retry-After`", body = ErrorBody),
+    ),
+)]
+pub async fn patch_file(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<PatchRequest>,
+) -> Result<Response, ApiError> {
+    use base64::Engine;
+
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(&request.data_base64)
+        .map_err(|e| ApiError::BadRequest(format!("data_base64: {e}")))?;
+    let guard = state.workspace.lock(Path::new(&path)).await?;
+    let meta = state.workspace.metadata(Path::new(&path)).await?;
+    if meta.is_dir() {
+        return Err(ApiError::IsDirectory);
+    }
+    if !if_match_allows(&headers, etag(&meta).as_deref()) {
+        return Err(ApiError::PreconditionFailed(
+            "file changed since it was read".to_string(),
+        ));
+    }
+    if request.offset > meta.len() && !request.extend {
+        return Err(ApiError::RangeNotSatisfiable(meta.len()));
+    }
+    if request
+        .offset
+        .checked_add(data.len() as u64)
+        .is_none_or(|end| end > i64::MAX as u64)
+    {
+        return Err(ApiError::BadRequest("offset out of range".to_string()));
+    }
+    state
+        .workspace
+        .write_at_locked(&guard, request.offset, &data)
+        .await?;
+    let mut builder = Response::builder().status(StatusCode::NO_CONTENT);
+    if let Some(etag) = state
+        .workspace
+        .metadata(Path::new(&path))
+        .await
+        .ok()
+        .and_then(|meta| etag(&meta))
+    {
+        builder = builder.header(header::ETAG, etag);
+    }
+    Ok(builder.body(Body::empty()).unwrap())
+}
+
+/// Delete a file or directory. Removing a directory with its contents
+/// needs `?confirm=` set to the same path, as a guard against slips.
+#[utoipa::path(
+    delete,
+    path = "/api/files/{path}",
+    tag = "files",
+    params(
+        ("path" = String, Path, description = "Path relative to the workspace root"),
+        DeleteQuery,
+    ),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = 400, description = "Invalid path, option or projection", body = ErrorBody),
+        (status = 401, description = "Access token missing or wrong", body = ErrorBody),
+        (status = 403, description = "Server is read-only or the request is cross-origin", body = ErrorBody),
+        (status = 404, description = "No such file or directory", body = ErrorBody),
+        (status = 409, description = "Directory not empty and `recursive=false`", body = ErrorBody),
+        (status = 428, description = "Recursive directory delete without a matching `confirm`", body = ErrorBody),
+        (status = 429, description = "Over the write rate limit; see `Retry-After`", body = ErrorBody),
+    ),
+)]
+pub async fn delete_file(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<StatusCode, ApiError> {
+    let recursive = query.recursive.unwrap_or(true);
+    if recursive && query.confirm.as_deref() != Some(path.as_str()) {
+        let meta = state.workspace.symlink_metadata(Path::new(&path)).await?;
+        if meta.is_dir() {
+            return Err(ApiError::PreconditionRequired(format!(
+                "deleting a directory with its contents needs ?confirm={path}"
+            )));
+        }
+    }
+    state.workspace.remove(Path::new(&path), recursive).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Split a trailing action segment off a path, e.g. `docs/a.md/rename` →
+/// (`docs/a.md`, `rename`). Catch-all routes can't carry a suffix, so POST
+/// actions are addressed this way.
+fn split_action(path: &str) -> Option<(&str, &str)> {
+    path.trim_end_matches('/').rsplit_once('/')
+}
+
+pub async fn post_root(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+) -> Result<Response, ApiError> {
+    match Multipart::from_request(request, &state).await {
+        Ok(multipart) => upload_files(&state, "", multipart).await,
+        Err(rejection) => Ok(rejection.into_response()),
+    }
+}
+
+pub async fn post_file(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    request: Request,
+) -> Result<Response, ApiError> {
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    if is_multipart {
+        return match Multipart::from_request(request, &state).await {
+            Ok(multipart) => upload_files(&state, &path, multipart).await,
+            Err(rejection) => Ok(rejection.into_response()),
+        };
+    }
+
+    if path == "batch" {
+        return match Json::<BatchRequest>::from_request(request, &state).await {
+            Ok(Json(body)) => batch_files(&state, body).await,
+            Err(rejection) => Ok(rejection.into_response()),
+        };
+    }
+
+    match split_action(&path) {
+        Some((target, "rename")) => {
+            match Json::<RenameRequest>::from_request(request, &state).await {
+                Ok(Json(body)) => rename_file(&state, target, body).await,
+                Err(rejection) => Ok(rejection.into_response()),
+            }
+        }
+        Some((target, "mkdir")) => make_dir(&state, target).await,
+        Some((target, "chmod")) => {
+            match Json::<ChmodRequest>::from_request(request, &state).await {
+                Ok(Json(body)) => chmod_file(&state, target, body).await,
+                Err(rejection) => Ok(rejection.into_response()),
+            }
+        }
+        Some((target, "copy")) => match Json::<CopyRequest>::from_request(request, &state).await {
+            Ok(Json(body)) => copy_file(&state, target, body).await,
+            Err(rejection) => Ok(rejection.into_response()),
+        },
+        _ => Err(ApiError::NotFound("unknown action".to_string())),
+    }
+}
+
+#[derive(Serialize)]
+pub struct UploadResponse {
+    /// Paths written, relative to the workspace root.
+    pub written: Vec<String>,
+}
+
+/// Write each file part of a multipart body into the directory `dir`,
+/// named after the part's filename. Parts are streamed to disk.
+async fn upload_files(
+    state: &Arc<AppState>,
+    dir: &str,
+    mut multipart: Multipart,
+) -> Result<Response, ApiError> {
+    let mut written = Vec::new();
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return Ok(e.into_response()),
+        };
+        let Some(file_name) = field.file_name().map(str::to_string) else {
+            tracing::debug!("upload: skipping part without a filename");
+            continue;
+        };
+        // Only the final component of the client-supplied name is used, and
+        // a name that climbs out of the directory is refused outright
+        let climbs = Path::new(&file_name)
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+        let name = match Path::new(&file_name).file_name() {
+            Some(name) if !climbs => name.to_string_lossy().into_owned(),
+            _ => return Err(ApiError::PathTraversal(file_name)),
+        };
+        let dest = Path::new(dir).join(&name);
+
+        // Staged beside the destination and swapped in under its lock once
+        // complete, so a cut-off upload leaves the old file as it was
+        let (staged, mut file) = state.workspace.stage(&dest).await?;
+        let copied = async {
+            while let Some(chunk) = field.chunk().await.map_err(IntoResponse::into_response)? {
+                file.write_all(&chunk)
+                    .await
+                    .map_err(|e| ApiError::Internal(e.to_string()).into_response())?;
+            }
+            file.sync_all()
+                .await
+                .map_err(|e| ApiError::Internal(e.to_string()).into_response())
+        }
+        .await;
+        drop(file);
+        let installed = match copied {
+            Ok(()) => state
+                .workspace
+                .install(&dest, &staged)
+                .await
+                .map_err(|e| ApiError::from(e).into_response()),
+            Err(response) => Err(response),
+        };
+        if let Err(response) = installed {
+            let _ = tokio::fs::remove_file(&staged).await;
+            return Ok(response);
+        }
+        written.push(dest.to_string_lossy().into_owned());
+    }
+    Ok(Json(UploadResponse { written }).into_response())
+}
+
+async fn make_dir(state: &Arc<AppState>, path: &str) -> Result<Response, ApiError> {
+    state.workspace.create_dir(Path::new(path)).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+async fn copy_file(
+    state: &Arc<AppState>,
+    from: &str,
+    body: CopyRequest,
+) -> Result<Response, ApiError> {
+    let summary = state
+        .workspace
+        .copy(Path::new(from), Path::new(&body.to), body.overwrite)
+        .await?;
+    Ok(Json(summary).into_response())
+}
+
+async fn chmod_file(
+    state: &Arc<AppState>,
+    path: &str,
+    body: ChmodRequest,
+) -> Result<Response, ApiError> {
+    let summary = state
+        .workspace
+        .set_permissions(Path::new(path), body.bits()?, body.recursive)
+        .await?;
+    Ok(Json(summary).into_response())
+}
+
+async fn rename_file(
+    state: &Arc<AppState>,
+    from: &str,
+    body: RenameRequest,
+) -> Result<Response, ApiError> {
+    state
+        .workspace
+        .rename(Path::new(from), Path::new(&body.to), body.overwrite)
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_modified_matches_etag_and_date() {
+        let etag = "\"abc-10\"";
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_NONE_MATCH,
+            "\"other\", \"abc-10\"".parse().unwrap(),
+        );
+        assert!(not_modified(&headers, Some(etag), Some(modified)));
+        headers.insert(header::IF_NONE_MATCH, "\"other\"".parse().unwrap());
+        assert!(!not_modified(&headers, Some(etag), Some(modified)));
+
+        let mut headers = HeaderMap::new();
+        let date = httpdate::fmt_http_date(modified);
+        headers.insert(header::IF_MODIFIED_SINCE, date.parse().unwrap());
+        assert!(not_modified(&headers, Some(etag), Some(modified)));
+        let later = modified + std::time::Duration::from_secs(5);
+        assert!(!not_modified(&headers, Some(etag), Some(later)));
+    }
+
+    #[test]
+    fn if_match_uses_strong_comparison() {
+        let etag = "\"abc-10\"";
+        let with = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_MATCH, value.parse().unwrap());
+            headers
+        };
+        assert!(if_match_allows(&HeaderMap::new(), None));
+        assert!(if_match_allows(&with("\"x\", \"abc-10\""), Some(etag)));
+        assert!(!if_match_allows(&with("W/\"abc-10\""), Some(etag)));
+        assert!(!if_match_allows(&with("\"abc-11\""), Some(etag)));
+        assert!(if_match_allows(&with("*"), Some(etag)));
+        // A missing file matches nothing, not even `*`
+        assert!(!if_match_allows(&with("*"), None));
+    }
+
+    #[test]
+    fn attachment_disposition_escapes_names() {
+        assert_eq!(
+            attachment_disposition("report.pdf"),
+            "attachment; filename=\"report.pdf\"; filename*=UTF-8''report.pdf"
+        );
+        assert_eq!(
+            attachment_disposition("a \"b\".txt"),
+            "attachment; filename=\"a _b_.txt\"; filename*=UTF-8''a%20%22b%22.txt"
+        );
+        assert_eq!(
+            attachment_disposition("résumé.md"),
+            "attachment; filename=\"r_sum_.md\"; filename*=UTF-8''r%C3%A9sum%C3%A9.md"
+        );
+        assert_eq!(basename("docs/guide/intro.md"), "intro.md");
+        assert_eq!(basename("top.txt"), "top.txt");
+    }
+
+    #[test]
+    fn navigation_trail() {
+        assert_eq!(navigation(""), (None, vec![]));
+        let (parent, crumbs) = navigation("docs");
+        assert_eq!(parent.as_deref(), Some(""));
+        assert_eq!(crumbs.len(), 1);
+        let (parent, crumbs) = navigation("docs/guide//intro.md/");
+        assert_eq!(parent.as_deref(), Some("docs/guide"));
+        let paths: Vec<_> = crumbs.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, ["docs", "docs/guide", "docs/guide/intro.md"]);
+        assert_eq!(crumbs[2].name, "intro.md");
+    }
+
+    #[tokio::test]
+    async fn next_line_splits_and_caps_lines() {
+        let mut data = b"one\r\ntwo\n".to_vec();
+        data.extend(std::iter::repeat_n(b'x', MAX_STREAMED_LINE + 10));
+        data.extend_from_slice(b"\nlast");
+        let mut reader = &data[..];
+        let mut buf = Vec::new();
+
+        assert_eq!(next_line(&mut reader, &mut buf).await.unwrap(), Some(false));
+        assert_eq!(buf, b"one");
+        assert_eq!(next_line(&mut reader, &mut buf).await.unwrap(), Some(false));
+        assert_eq!(buf, b"two");
+        assert_eq!(next_line(&mut reader, &mut buf).await.unwrap(), Some(true));
+        assert_eq!(buf.len(), MAX_STREAMED_LINE);
+        assert_eq!(next_line(&mut reader, &mut buf).await.unwrap(), Some(false));
+        assert_eq!(buf, b"last");
+        assert_eq!(next_line(&mut reader, &mut buf).await.unwrap(), None);
+    }
+
+    #[test]
+    fn parse_range_forms() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(Ok((500, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some(Ok((900, 999))));
+    }
+
+    #[test]
+    fn parse_range_unsatisfiable_and_ignored() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=9-3", 1000), None);
+    }
+
+    /// State over `dir` with the directory, text and hex projections.
+    fn test_state(dir: &Path) -> AppState {
+        use crate::projections::{dir_list::DirList, hex_view::HexView, text_raw::TextRaw};
+        use crate::registry::ProjectionRegistry;
+
+        let mut registry = ProjectionRegistry::new();
+        registry.register(Arc::new(DirList));
+        registry.register(Arc::new(TextRaw));
+        registry.register(Arc::new(HexView));
+        AppState::for_tests(dir, registry)
+    }
+
+    #[tokio::test]
+    async fn file_response_reports_mime_type() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/notes.md"), "# hi").unwrap();
+        std::fs::write(dir.path().join("picture"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        std::fs::write(dir.path().join("mystery"), "plain").unwrap();
+        let state = test_state(dir.path());
+        let query = FileQuery {
+            projection: None,
+            options: HashMap::new(),
+        };
+        let root = dir.path();
+        let mime = |path: &'static str| {
+            let state = &state;
+            let query = &query;
+            async move {
+                let meta = FileStat::from(&std::fs::metadata(root.join(path)).unwrap());
+                file_response(state, path, query, &meta)
+                    .await
+                    .unwrap()
+                    .mime_type
+            }
+        };
+        assert_eq!(
+            mime("docs/notes.md").await.as_deref(),
+            Some("text/markdown")
+        );
+        // Sniffed from the content when the name gives nothing away
+        assert_eq!(mime("picture").await.as_deref(), Some("image/png"));
+        assert_eq!(mime("mystery").await, None);
+        assert_eq!(mime("docs").await, None);
+    }
+
+    #[tokio::test]
+    async fn projects_from_a_memory_store() {
+        use crate::store::MemoryStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = test_state(dir.path());
+        state.store = Arc::new(
+            MemoryStore::new()
+                .with_file("docs/notes.txt", "hello\n")
+                .with_file("blob.bin", [0u8; 32]),
+        );
+        let state = Arc::new(state);
+        let query = FileQuery {
+            projection: None,
+            options: HashMap::new(),
+        };
+        let get = |path: &'static str| {
+            let (state, query) = (&state, &query);
+            async move {
+                let meta = stat(state, path).await?;
+                file_response(state, path, query, &meta).await
+            }
+        };
+
+        let root = get("").await.unwrap();
+        assert_eq!(root.active_projection, "dir.list");
+        let names: Vec<&str> = root.output["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["docs", "blob.bin"]);
+
+        let notes = get("docs/notes.txt").await.unwrap();
+        assert_eq!(notes.active_projection, "text.raw");
+        assert_eq!(notes.output["content"], "hello\n");
+        assert_eq!(get("blob.bin").await.unwrap().active_projection, "hex.view");
+        assert!(matches!(get("nope.txt").await, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn text_names_outrank_binary_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        // Starts with the PDF signature but is plainly text
+        std::fs::write(dir.path().join("notes.txt"), "%PDF-1.7 layout notes\n").unwrap();
+        let utf16: Vec<u8> = "let x = 1;\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        std::fs::write(dir.path().join("lib.rs"), utf16).unwrap();
+        std::fs::write(dir.path().join("picture"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        let state = test_state(dir.path());
+        let query = FileQuery {
+            projection: None,
+            options: HashMap::new(),
+        };
+        for (path, projection, mime) in [
+            ("notes.txt", "text.raw", "text/plain"),
+            ("lib.rs", "text.raw", "text/x-rust"),
+            ("picture", "hex.view", "image/png"),
+        ] {
+            let meta = FileStat::from(&std::fs::metadata(dir.path().join(path)).unwrap());
+            let response = file_response(&state, path, &query, &meta).await.unwrap();
+            assert_eq!(response.active_projection, projection, "{path}");
+            assert_eq!(response.attempted, [projection], "{path}");
+            assert_eq!(response.mime_type.as_deref(), Some(mime), "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_projection_falls_back_to_the_next() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("data.txt"), [0u8; 64]).unwrap();
+        let state = test_state(dir.path());
+        let meta = FileStat::from(&std::fs::metadata(dir.path().join("data.txt")).unwrap());
+        let respond = |projection: Option<&str>| {
+            let query = FileQuery {
+                projection: projection.map(str::to_string),
+                options: HashMap::new(),
+            };
+            let (state, meta) = (&state, &meta);
+            async move {
+                file_response(state, "data.txt", &query, meta)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let response = respond(None).await;
+        assert_eq!(response.active_projection, "hex.view");
+        assert_eq!(response.attempted, ["text.raw", "hex.view"]);
+        assert_eq!(response.selection.reason, SelectionReason::Fallback);
+
+        // Asked for by name, the failure is shown as is
+        let response = respond(Some("text.raw")).await;
+        assert_eq!(response.active_projection, "text.raw");
+        assert_eq!(response.attempted, ["text.raw"]);
+        assert_eq!(response.output["type"], "Error");
+    }
+
+    #[tokio::test]
+    async fn put_respects_must_exist_and_create_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "old").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let put = |path: &str, must_exist: Option<bool>, create_dirs: Option<bool>| {
+            let state = state.clone();
+            let path = path.to_string();
+            async move {
+                put_file(
+                    State(state),
+                    axum::extract::Path(path),
+                    Query(WriteQuery {
+                        create_dirs,
+                        must_exist,
+                    }),
+                    HeaderMap::new(),
+                    axum::body::Bytes::from_static(b"new"),
+                )
+                .await
+                .map(|r| r.status())
+            }
+        };
+
+        assert!(matches!(
+            put("b.txt", Some(true), None).await,
+            Err(ApiError::NotFound(_))
+        ));
+        assert!(!dir.path().join("b.txt").exists());
+        assert_eq!(
+            put("a.txt", Some(true), None).await.unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(std::fs::read(dir.path().join("a.txt")).unwrap(), b"new");
+
+        assert!(matches!(
+            put("typo/deep/c.txt", None, Some(false)).await,
+            Err(ApiError::BadRequest(_))
+        ));
+        assert!(!dir.path().join("typo").exists());
+        assert!(put("c.txt", None, Some(false)).await.is_ok());
+        assert!(put("new/dir/c.txt", None, None).await.is_ok());
+        assert!(dir.path().join("new/dir/c.txt").exists());
+    }
+
+    /// A multipart POST to `dir` carrying `parts` as (filename, contents),
+    /// cut off before the closing boundary unless `complete`.
+    fn multipart(dir: &str, parts: &[(&str, &str)], complete: bool) -> (String, Request) {
+        let mut body = String::new();
+        for (name, contents) in parts {
+            body.push_str(&format!(
+                "--BOUNDARY\r\nContent-Disposition: form-data; name=\"file\"; \
+                 filename=\"{name}\"\r\n\r\n{contents}\r\n"
+            ));
+        }
+        if complete {
+            body.push_str("--BOUNDARY--\r\n");
+        }
+        let request = Request::builder()
+            .method("POST")
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=BOUNDARY",
+            )
+            .body(Body::from(body))
+            .unwrap();
+        (dir.to_string(), request)
+    }
+
+    #[tokio::test]
+    async fn multipart_upload_writes_each_part() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        let state = Arc::new(test_state(dir.path()));
+
+        let (path, request) = multipart(
+            "docs",
+            &[("a.txt", "alpha"), ("nested/b.txt", "beta")],
+            true,
+        );
+        let response = post_file(State(state.clone()), axum::extract::Path(path), request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({ "written": ["docs/a.txt", "docs/b.txt"] })
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("docs/a.txt")).unwrap(),
+            "alpha"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("docs/b.txt")).unwrap(),
+            "beta"
+        );
+    }
+
+    #[tokio::test]
+    async fn multipart_upload_refuses_escaping_names() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        let state = Arc::new(test_state(dir.path()));
+
+        for name in ["../x", "/etc/x", ".."] {
+            let (path, request) = multipart("docs", &[(name, "bad")], true);
+            assert!(
+                matches!(
+                    post_file(State(state.clone()), axum::extract::Path(path), request).await,
+                    Err(ApiError::PathTraversal(_))
+                ),
+                "{name}"
+            );
+        }
+        let (path, request) = multipart("../outside", &[("x", "bad")], true);
+        assert!(matches!(
+            post_file(State(state.clone()), axum::extract::Path(path), request).await,
+            Err(ApiError::PathTraversal(_))
+        ));
+        assert!(!dir.path().join("x").exists());
+        assert!(!dir.path().join("docs/x").exists());
+        assert!(!dir.path().parent().unwrap().join("outside").exists());
+    }
+
+    #[tokio::test]
+    async fn cut_off_multipart_upload_keeps_the_old_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/a.txt"), "original").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+
+        let (path, request) = multipart("docs", &[("a.txt", "replacement")], false);
+        let response = post_file(State(state), axum::extract::Path(path), request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("docs/a.txt")).unwrap(),
+            "original"
+        );
+        // The staged part is cleaned up too
+        let entries: Vec<_> = std::fs::read_dir(dir.path().join("docs"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["a.txt"]);
+    }
+
+    #[tokio::test]
+    async fn malformed_multipart_is_a_bad_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let request = Request::builder()
+            .method("POST")
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=BOUNDARY",
+            )
+            .body(Body::from("not a multipart body"))
+            .unwrap();
+        let response = post_file(State(state), axum::extract::Path(String::new()), request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn patch_writes_in_place() {
+        use base64::Engine;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.bin"), "hello world").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let patch = |path: &str, offset: u64, data: &[u8], extend: bool| {
+            let request = PatchRequest {
+                offset,
+                data_base64: base64::engine::general_purpose::STANDARD.encode(data),
+                extend,
+            };
+            patch_file(
+                State(state.clone()),
+                axum::extract::Path(path.to_string()),
+                HeaderMap::new(),
+                Json(request),
+            )
+        };
+        let contents = || std::fs::read(dir.path().join("a.bin")).unwrap();
+
+        let response = patch("a.bin", 6, b"WORLD", false).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(response.headers().contains_key(header::ETAG));
+        assert_eq!(contents(), b"hello WORLD");
+        assert_eq!(
+            patch("a.bin", 11, b"!", false).await.unwrap().status(),
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(contents(), b"hello WORLD!");
+
+        assert!(matches!(
+            patch("a.bin", 14, b"?", false).await,
+            Err(ApiError::RangeNotSatisfiable(12))
+        ));
+        assert_eq!(contents(), b"hello WORLD!");
+        patch("a.bin", 14, b"?", true).await.unwrap();
+        assert_eq!(contents(), b"hello WORLD!\0\0?");
+
+        assert!(patch("missing.bin", 0, b"x", true).await.is_err());
+        assert!(!dir.path().join("missing.bin").exists());
+        let bad = PatchRequest {
+            offset: 0,
+            data_base64: "not base64!".to_string(),
+            extend: false,
+        };
+        assert!(matches!(
+            patch_file(
+                State(state.clone()),
+                axum::extract::Path("a.bin".to_string()),
+                HeaderMap::new(),
+                Json(bad),
+            )
+            .await,
+            Err(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn recursive_directory_deletes_need_confirmation() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("docs/old")).unwrap();
+        std::fs::write(dir.path().join("docs/old/a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "b").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let delete = |path: &str, confirm: Option<&str>| {
+            delete_file(
+                State(state.clone()),
+                axum::extract::Path(path.to_string()),
+                Query(DeleteQuery {
+                    recursive: None,
+                    confirm: confirm.map(str::to_string),
+                }),
+            )
+        };
+
+        assert!(matches!(
+            delete("docs", None).await,
+            Err(ApiError::PreconditionRequired(_))
+        ));
+        assert!(matches!(
+            delete("docs", Some("docs/old")).await,
+            Err(ApiError::PreconditionRequired(_))
+        ));
+        assert!(dir.path().join("docs/old/a.txt").exists());
+
+        assert_eq!(delete("b.txt", None).await.unwrap(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            delete("docs", Some("docs")).await.unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        assert!(!dir.path().join("docs").exists());
+    }
+
+    #[tokio::test]
+    async fn slow_projections_time_out() {
+        struct Slow;
+
+        #[async_trait::async_trait]
+        impl Projection for Slow {
+            fn id(&self) -> &str {
+                "slow"
+            }
+            fn name(&self) -> &str {
+                "Slow"
+            }
+            fn confidence(&self, _resource: &Resource) -> f32 {
+                0.0
+            }
+            async fn project(
+                &self,
+                _resource: &Resource,
+                _workspace: &dyn crate::store::FileStore,
+                _request: &ProjectionRequest,
+            ) -> crate::projection::Result<ProjectionOutput> {
+                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                Err(ProjectionError::Unsupported)
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        let mut state = test_state(dir.path());
+        state.registry.register(Arc::new(Slow));
+        state.projection_timeout = Some(std::time::Duration::from_millis(20));
+        let query = FileQuery {
+            projection: Some("slow".to_string()),
+            options: HashMap::new(),
+        };
+        let response = project_resource(&Arc::new(state), "a.txt", &query, &HeaderMap::new())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["output"]["type"], "Timeout");
+        assert!(!body["projections"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn projection_headers_reach_the_response() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        struct Tagged;
+
+        #[async_trait::async_trait]
+        impl Projection for Tagged {
+            fn id(&self) -> &str {
+                "tagged"
+            }
+            fn name(&self) -> &str {
+                "Tagged"
+            }
+            fn confidence(&self, _resource: &Resource) -> f32 {
+                0.0
+            }
+            async fn project(
+                &self,
+                _resource: &Resource,
+                _workspace: &dyn crate::store::FileStore,
+                _request: &ProjectionRequest,
+            ) -> crate::projection::Result<ProjectionOutput> {
+                CALLS.fetch_add(1, Ordering::SeqCst);
+                Ok(ProjectionOutput::Env { entries: vec![] })
+            }
+            async fn project_with_meta(
+                &self,
+                resource: &Resource,
+                workspace: &dyn crate::store::FileStore,
+                request: &ProjectionRequest,
+            ) -> crate::projection::Result<(ProjectionOutput, ProjectionMeta)> {
+                let output = self.project(resource, workspace, request).await?;
+                let meta = ProjectionMeta {
+                    headers: vec![
+                        ("X-Row-Count".to_string(), "0".to_string()),
+                        ("bad header".to_string(), "x".to_string()),
+                    ],
+                    cache_ttl: Some(std::time::Duration::from_secs(30)),
+                };
+                Ok((output, meta))
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        let mut state = test_state(dir.path());
+        state.registry.register(Arc::new(Tagged));
+        state.cache = crate::cache::ProjectionCache::new(8);
+        let state = Arc::new(state);
+        let query = FileQuery {
+            projection: Some("tagged".to_string()),
+            options: HashMap::new(),
+        };
+        // The second request is served from the cache, headers and all
+        for _ in 0..2 {
+            let response = project_resource(&state, "a.txt", &query, &HeaderMap::new())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let headers = response.headers();
+            assert_eq!(headers["x-row-count"], "0");
+            assert_eq!(headers[header::CACHE_CONTROL], "private, max-age=30");
+            assert!(!headers.contains_key("bad header"));
+        }
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn chmod_modes_are_octal() {
+        let bits = |mode: &str| {
+            ChmodRequest {
+                mode: mode.to_string(),
+                recursive: false,
+            }
+            .bits()
+            .ok()
+        };
+        assert_eq!(bits("0644"), Some(0o644));
+        assert_eq!(bits("755"), Some(0o755));
+        // Parsed here, refused by the workspace
+        assert_eq!(bits("0o4755"), Some(0o4755));
+        for bad in ["", "0888", "rwxr-xr-x", "+x", "07777x", "00644"] {
+            assert_eq!(bits(bad), None, "{bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn html_is_negotiated_from_accept_or_format() {
+        use crate::projections::text_markdown::TextMarkdown;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# Title").unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        let mut state = test_state(dir.path());
+        state.registry.register(Arc::new(TextMarkdown));
+        let state = Arc::new(state);
+        let get = |path: &'static str, options: &[(&str, &str)], accept: &'static str| {
+            let query = FileQuery {
+                projection: None,
+                options: options
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            };
+            let state = state.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+                project_resource(&state, path, &query, &headers).await
+            }
+        };
+        let browser = "text/html,application/xhtml+xml,*/*;q=0.8";
+
+        for response in [
+            get("a.md", &[], browser).await.unwrap(),
+            get("a.md", &[("format", "html")], "*/*").await.unwrap(),
+        ] {
+            assert_eq!(
+                response.headers()[header::CONTENT_TYPE],
+                "text/html; charset=utf-8"
+            );
+            assert_eq!(response.headers()[header::VARY], "Accept");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(String::from_utf8_lossy(&body).contains("<h1"));
+        }
+
+        // JSON unless HTML is preferred, and when the projection has none
+        for (path, accept) in [
+            ("a.md", "*/*"),
+            ("a.md", "application/json, text/html;q=0.5"),
+            ("a.txt", browser),
+        ] {
+            let response = get(path, &[], accept).await.unwrap();
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        }
+        assert!(matches!(
+            get("a.txt", &[("format", "html")], "*/*").await,
+            Err(ApiError::NotAcceptable(_))
+        ));
+        assert!(matches!(
+            get("a.md", &[("format", "pdf")], "*/*").await,
+            Err(ApiError::InvalidOption(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn versioned_raw_urls_are_immutable() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.png"), "png").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let version =
+            content_version(&std::fs::metadata(dir.path().join("a.png")).unwrap()).unwrap();
+        let get = |v: Option<&str>| {
+            let state = state.clone();
+            let query = RawQuery {
+                entry: None,
+                download: None,
+                v: v.map(str::to_string),
+            };
+            async move {
+                let response = raw_file(
+                    State(state),
+                    axum::extract::Path("a.png".to_string()),
+                    Query(query),
+                    HeaderMap::new(),
+                )
+                .await
+                .unwrap();
+                response.headers()[header::CACHE_CONTROL].clone()
+            }
+        };
+        assert_eq!(get(Some(&version)).await, IMMUTABLE);
+        assert_eq!(get(Some("stale-1")).await, "no-cache");
+        assert_eq!(get(None).await, "no-cache");
+    }
+
+    #[tokio::test]
+    async fn head_sends_the_raw_headers_without_a_body() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "some text").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let query = || FileQuery {
+            projection: None,
+            options: HashMap::new(),
+        };
+
+        let head = head_file(
+            State(state.clone()),
+            axum::extract::Path("a.txt".to_string()),
+            Query(query()),
+        )
+        .await
+        .unwrap();
+        let get = raw_file(
+            State(state.clone()),
+            axum::extract::Path("a.txt".to_string()),
+            Query(RawQuery {
+                entry: None,
+                download: None,
+                v: None,
+            }),
+            HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(head.status(), StatusCode::OK);
+        for name in [header::CONTENT_LENGTH, header::ETAG, header::CONTENT_TYPE] {
+            assert_eq!(
+                head.headers().get(&name),
+                get.headers().get(&name),
+                "{name}"
+            );
+        }
+        assert_eq!(head.headers()[header::CONTENT_LENGTH], "9");
+        assert_eq!(head.headers()["x-active-projection"], "text.raw");
+        let body = axum::body::to_bytes(head.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+
+        let missing = head_file(
+            State(state),
+            axum::extract::Path("nope.txt".to_string()),
+            Query(query()),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(missing, ApiError::NotFound(_)), "{missing:?}");
+    }
+
+    #[tokio::test]
+    async fn raw_text_declares_its_charset() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "plain").unwrap();
+        std::fs::write(dir.path().join("b.csv"), b"\xff\xfea\x00,\x00b\x00").unwrap();
+        std::fs::write(dir.path().join("c.json"), b"{\"n\": \"caf\xe9\"}").unwrap();
+        std::fs::write(dir.path().join("d.png"), "png").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let content_type = |path: &str| {
+            let state = state.clone();
+            let path = path.to_string();
+            async move {
+                let query = RawQuery {
+                    entry: None,
+                    download: None,
+                    v: None,
+                };
+                let response = raw_file(
+                    State(state),
+                    axum::extract::Path(path),
+                    Query(query),
+                    HeaderMap::new(),
+                )
+                .await
+                .unwrap();
+                let content_type = response.headers()[header::CONTENT_TYPE].clone();
+                // Sniffing the head mustn't eat into the body
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (content_type, body.len())
+            }
+        };
+        assert_eq!(
+            content_type("a.txt").await,
+            ("text/plain; charset=utf-8".parse().unwrap(), 5)
+        );
+        assert_eq!(
+            content_type("b.csv").await,
+            ("text/csv; charset=utf-16le".parse().unwrap(), 8)
+        );
+        assert_eq!(
+            content_type("c.json").await,
+            (
+                "application/json; charset=windows-1252".parse().unwrap(),
+                13
+            )
+        );
+        assert_eq!(
+            content_type("d.png").await,
+            ("image/png".parse().unwrap(), 3)
+        );
+    }
+
+    #[tokio::test]
+    async fn raw_svg_is_sandboxed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.svg"), "<svg onload=\"alert(1)\"/>").unwrap();
+        std::fs::write(dir.path().join("b.png"), "png").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        for (path, csp) in [("a.svg", Some("sandbox")), ("b.png", None)] {
+            let query = RawQuery {
+                entry: None,
+                download: None,
+                v: None,
+            };
+            let response = raw_file(
+                State(state.clone()),
+                axum::extract::Path(path.to_string()),
+                Query(query),
+                HeaderMap::new(),
+            )
+            .await
+            .unwrap();
+            let policy = response.headers().get(header::CONTENT_SECURITY_POLICY);
+            assert_eq!(policy.map(|v| v.to_str().unwrap()), csp, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn directories_stream_as_ndjson() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+        let state = Arc::new(test_state(dir.path()));
+        let query = |pairs: &[(&str, &str)]| FileQuery {
+            projection: None,
+            options: pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+
+        let response = project_resource(
+            &state,
+            "",
+            &query(&[("stream", "ndjson"), ("hidden", "false")]),
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/x-ndjson"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let mut names: Vec<_> = body
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let entry: serde_json::Value = serde_json::from_slice(line).unwrap();
+                entry["name"].as_str().unwrap().to_string()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, ["a.txt", "sub"]);
+
+        assert!(matches!(
+            project_resource(
+                &state,
+                "a.txt",
+                &query(&[("stream", "ndjson")]),
+                &HeaderMap::new()
+            )
+            .await,
+            Err(ApiError::NotADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn in_band_error_keeps_file_errors_out_of_band() {
+        let invalid_utf8 =
+            ProjectionError::Workspace(WorkspaceError::Io(std::io::ErrorKind::InvalidData.into()));
+        assert!(matches!(
+            in_band_error(invalid_utf8),
+            Ok(ProjectionOutput::Error { .. })
+        ));
+        assert!(in_band_error(ProjectionError::Other("bad".into())).is_ok());
+        let not_local = ProjectionError::Workspace(WorkspaceError::NotLocal("a.db".into()));
+        assert!(in_band_error(not_local).is_ok());
+
+        let missing =
+            ProjectionError::Workspace(WorkspaceError::Io(std::io::ErrorKind::NotFound.into()));
+        assert!(in_band_error(missing).is_err());
+        assert!(in_band_error(ProjectionError::InvalidOption("x".into())).is_err());
     }
 }
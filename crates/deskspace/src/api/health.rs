@@ -0,0 +1,89 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use serde::Serialize;
+
+use crate::api::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct Health {
+    /// `ok`, or `unavailable` when a readiness check failed.
+    pub status: &'static str,
+    /// Absolute path of the workspace root.
+    pub root: String,
+    /// Number of registered projections.
+    pub projections: usize,
+    /// Paths refused for reaching outside the workspace, since startup,
+    /// whether asked for alone, in a batch or over the WebSocket.
+    pub traversal_rejections: u64,
+    /// Why the server isn't ready; only set by `/readyz`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Health {
+    fn new(state: &AppState) -> Self {
+        Self {
+            status: "ok",
+            root: state.workspace.root().display().to_string(),
+            projections: state.registry.len(),
+            traversal_rejections: state.traversal_rejections.load(Ordering::Relaxed),
+            error: None,
+        }
+    }
+}
+
+/// Liveness: the process is up and serving. Touches no files.
+pub async fn healthz(State(state): State<Arc<AppState>>) -> Json<Health> {
+    Json(Health::new(&state))
+}
+
+/// Readiness: also checks the workspace root can still be stat'ed, e.g. a
+/// network mount hasn't gone away. 503 when it can't.
+pub async fn readyz(State(state): State<Arc<AppState>>) -> Response {
+    let mut health = Health::new(&state);
+    match tokio::fs::metadata(state.workspace.root()).await {
+        Ok(meta) if meta.is_dir() => Json(health).into_response(),
+        result => {
+            health.status = "unavailable";
+            health.error = Some(match result {
+                Ok(_) => "workspace root is not a directory".to_string(),
+                Err(e) => format!("workspace root unreadable: {e}"),
+            });
+            (StatusCode::SERVICE_UNAVAILABLE, Json(health)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projections::text_raw::TextRaw;
+    use crate::registry::ProjectionRegistry;
+
+    #[tokio::test]
+    async fn readiness_fails_once_the_root_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("ws");
+        std::fs::create_dir(&root).unwrap();
+        let mut registry = ProjectionRegistry::new();
+        registry.register(Arc::new(TextRaw));
+        let state = Arc::new(AppState::for_tests(&root, registry));
+
+        let Json(health) = healthz(State(state.clone())).await;
+        assert_eq!(health.status, "ok");
+        assert_eq!(health.projections, 1);
+        assert_eq!(health.root, state.workspace.root().display().to_string());
+        assert_eq!(readyz(State(state.clone())).await.status(), StatusCode::OK);
+
+        std::fs::remove_dir(&root).unwrap();
+        assert_eq!(healthz(State(state.clone())).await.status, "ok");
+        assert_eq!(
+            readyz(State(state)).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::api::{ApiError, AppState};
+
+/// Histogram buckets for request and projection durations, in seconds.
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// How often histogram samples are folded into their buckets.
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+fn builder() -> Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), DURATION_BUCKETS)
+}
+
+/// Install the process-wide Prometheus recorder and start its upkeep.
+/// Call once, from inside the runtime; the handle renders `/metrics`.
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    let handle = builder()?.install_recorder()?;
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            let mut tick = tokio::time::interval(UPKEEP_INTERVAL);
+            loop {
+                tick.tick().await;
+                handle.run_upkeep();
+            }
+        }
+    });
+    Ok(handle)
+}
+
+/// Metrics middleware: counts requests by method, route template and
+/// status, and times them. Routes are the templates, e.g.
+/// `/api/files/{*path}`, so paths don't blow up the label set.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+    let method = request.method().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status().as_u16().to_string();
+    metrics::counter!(
+        "deskspace_http_requests_total",
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => status,
+    )
+    .increment(1);
+    metrics::histogram!(
+        "deskspace_http_request_duration_seconds",
+        "method" => method,
+        "route" => route,
+    )
+    .record(started.elapsed());
+    response
+}
+
+/// Record a projection run: `outcome` is `ok`, `error`, `timeout`,
+/// `too_large` or `cached`, and `elapsed` is set when it actually ran.
+pub(crate) fn projection_run(id: &str, outcome: &'static str, elapsed: Option<Duration>) {
+    metrics::counter!(
+        "deskspace_projection_runs_total",
+        "projection" => id.to_string(),
+        "outcome" => outcome,
+    )
+    .increment(1);
+    if let Some(elapsed) = elapsed {
+        metrics::histogram!(
+            "deskspace_projection_duration_seconds",
+            "projection" => id.to_string(),
+        )
+        .record(elapsed);
+    }
+}
+
+/// Prometheus text exposition of everything recorded since startup.
+pub async fn metrics(State(state): State<Arc<AppState>>) -> Result<Response, ApiError> {
+    let handle = state
+        .metrics
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("metrics are disabled".to_string()))?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projection_runs_render_with_labels() {
+        let recorder = builder().unwrap().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            projection_run("text.raw", "ok", Some(Duration::from_millis(20)));
+            projection_run("text.raw", "cached", None);
+            projection_run("hex.view", "error", Some(Duration::from_millis(2)));
+        });
+        handle.run_upkeep();
+        let text = handle.render();
+        for line in [
+            r#"deskspace_projection_runs_total{projection="text.raw",outcome="ok"} 1"#,
+            r#"deskspace_projection_runs_total{projection="text.raw",outcome="cached"} 1"#,
+            r#"deskspace_projection_runs_total{projection="hex.view",outcome="error"} 1"#,
+            r#"deskspace_projection_duration_seconds_bucket{projection="text.raw",le="0.025"} 1"#,
+            r#"deskspace_projection_duration_seconds_count{projection="hex.view"} 1"#,
+        ] {
+            assert!(text.contains(line), "missing {line} in:\n{text}");
+        }
+    }
+}
@@ -1,61 +1,585 @@
+pub mod audit;
+pub mod error;
+pub mod export;
 pub mod files;
+pub mod health;
+pub mod metrics;
+pub mod mounts;
+pub mod openapi;
+pub mod rate_limit;
+pub mod search;
+pub mod size;
+pub mod stat;
+pub mod stats;
+pub mod thumb;
+pub mod upload;
+pub mod watch;
+pub mod ws;
 
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 
-use axum::extract::Request;
-use axum::http::StatusCode;
+use axum::extract::{DefaultBodyLimit, Request, State};
+use axum::http::{HeaderMap, StatusCode};
 use axum::middleware::Next;
-use axum::response::Response;
+use axum::response::{IntoResponse, Response};
+use metrics_exporter_prometheus::PrometheusHandle;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate};
+use tower_http::compression::CompressionLayer;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::Level;
 
+pub use error::ApiError;
+pub use rate_limit::RateLimiter;
+
+use crate::cache::ProjectionCache;
+use crate::index::PathIndex;
+use crate::projection::Projection;
 use crate::registry::ProjectionRegistry;
+use crate::store::FileStore;
+use crate::thumbnail::ThumbnailCache;
+use crate::upload::UploadStaging;
 use crate::workspace::Workspace;
 
+/// Largest request body accepted for writes and uploads.
+const MAX_BODY_BYTES: usize = 1024 * 1024 * 1024;
+
+/// Content types that are already compressed, so re-encoding them only
+/// costs CPU. Images and SSE are excluded by the default predicate.
+const PRECOMPRESSED_TYPES: &[&str] = &[
+    "video/",
+    "audio/",
+    "font/woff",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/x-7z-compressed",
+    "application/zstd",
+    "application/pdf",
+    "application/wasm",
+];
+
+/// Which responses get compressed. Range (206) responses are never
+/// compressed by the layer, so byte ranges on raw files stay exact.
+fn compression_predicate() -> impl Predicate {
+    DefaultPredicate::new().and(
+        |_: StatusCode, _: axum::http::Version, headers: &HeaderMap, _: &axum::http::Extensions| {
+            let content_type = headers
+                .get(axum::http::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            !PRECOMPRESSED_TYPES
+                .iter()
+                .any(|ty| content_type.starts_with(ty))
+        },
+    )
+}
+
 pub struct AppState {
     pub workspace: Workspace,
+    /// What projections and the file GET/HEAD endpoints read through:
+    /// `workspace` itself in the server, a [`MemoryStore`] tree in tests
+    /// and demos. Endpoints that stream, write or watch files go to
+    /// `workspace` directly.
+    ///
+    /// [`MemoryStore`]: crate::store::MemoryStore
+    pub store: Arc<dyn FileStore>,
     pub registry: ProjectionRegistry,
+    /// Serialized outputs of recent file projections.
+    pub cache: ProjectionCache,
+    /// Reject every mutating request with 403.
+    pub read_only: bool,
+    /// Reject mutating requests that carry neither an `Origin` header nor
+    /// `Sec-Fetch-Site: same-origin`.
+    pub strict_csrf: bool,
+    /// Largest file a projection may load whole; 0 disables the check.
+    pub max_projection_bytes: u64,
+    /// Per-projection limits overriding `max_projection_bytes`, with 0
+    /// meaning unlimited. These also apply to projections that don't read
+    /// whole files.
+    pub projection_size_limits: HashMap<String, u64>,
+    /// On-disk store for `/api/thumb` output.
+    pub thumbnails: ThumbnailCache,
+    /// How long a projection may run before the request gets a `Timeout`
+    /// output instead; `None` waits indefinitely.
+    pub projection_timeout: Option<std::time::Duration>,
+    /// Token every API request must present; `None` leaves the API open.
+    pub auth_token: Option<String>,
+    /// Shared budget for mutating requests and WebSocket writes; `None`
+    /// doesn't limit them.
+    pub write_limit: Option<RateLimiter>,
+    /// Background path index serving filename search; `None` walks the
+    /// tree per query.
+    pub search_index: Option<Arc<PathIndex>>,
+    /// Chunked uploads in progress.
+    pub uploads: Arc<UploadStaging>,
+    /// Paths refused for reaching outside the workspace, since startup,
+    /// whether asked for alone, in a batch or over the WebSocket.
+    pub traversal_rejections: AtomicU64,
+    /// Renders `/metrics`; `None` when no recorder is installed.
+    pub metrics: Option<PrometheusHandle>,
 }
 
-/// CSRF middleware: reject mutating requests unless Origin is localhost.
-pub async fn csrf_check(request: Request, next: Next) -> Result<Response, StatusCode> {
-    let method = request.method().clone();
+impl AppState {
+    /// Size above which `projection` is not run, if any.
+    pub fn projection_size_limit(&self, projection: &dyn Projection) -> Option<u64> {
+        match self.projection_size_limits.get(projection.id()) {
+            Some(0) => None,
+            Some(&limit) => Some(limit),
+            None if projection.reads_whole_file() && self.max_projection_bytes > 0 => {
+                Some(self.max_projection_bytes)
+            }
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State over `root` serving `registry`, with every limit, cache and
+    /// optional feature off.
+    pub(crate) fn for_tests(root: &std::path::Path, registry: ProjectionRegistry) -> Self {
+        use crate::upload::DEFAULT_IDLE_TIMEOUT;
+
+        Self {
+            workspace: Workspace::new(root).unwrap(),
+            store: Arc::new(Workspace::new(root).unwrap()),
+            registry,
+            cache: ProjectionCache::new(0),
+            read_only: false,
+            strict_csrf: false,
+            max_projection_bytes: 0,
+            projection_size_limits: HashMap::new(),
+            thumbnails: ThumbnailCache::new(root.join("thumbs")),
+            projection_timeout: None,
+            auth_token: None,
+            write_limit: None,
+            search_index: None,
+            uploads: Arc::new(UploadStaging::new(
+                root.join("uploads"),
+                DEFAULT_IDLE_TIMEOUT,
+            )),
+            traversal_rejections: Default::default(),
+            metrics: None,
+        }
+    }
+}
+
+/// Cookie the UI stores the access token in, for requests it can't add an
+/// `Authorization` header to: images, downloads and the watch stream.
+pub const TOKEN_COOKIE: &str = "deskspace_token";
+
+/// Whether `headers` carry `token`, as `Authorization: Bearer <token>` or in
+/// the `TOKEN_COOKIE` cookie.
+pub(crate) fn token_presented(headers: &HeaderMap, token: &str) -> bool {
+    use axum::http::header::{AUTHORIZATION, COOKIE};
+
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    let cookie = headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == TOKEN_COOKIE)
+        .map(|(_, value)| value);
+    [bearer, cookie]
+        .into_iter()
+        .flatten()
+        .any(|presented| constant_time_eq(presented.as_bytes(), token.as_bytes()))
+}
+
+/// Compare without stopping at the first difference, so response timing
+/// doesn't reveal how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+/// Auth middleware: when a token is configured, reject requests that don't
+/// present it with 401.
+pub async fn auth_check(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    match &state.auth_token {
+        Some(token) if !token_presented(request.headers(), token) => Err(ApiError::Unauthorized),
+        _ => Ok(next.run(request).await),
+    }
+}
+
+/// Read-only middleware: reject mutating requests when the server is locked.
+pub async fn read_only_check(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method();
+    let is_safe = method == axum::http::Method::GET
+        || method == axum::http::Method::HEAD
+        || method == axum::http::Method::OPTIONS;
+    if state.read_only && !is_safe {
+        return ApiError::ReadOnly.into_response();
+    }
+    next.run(request).await
+}
+
+/// Whether a request passes the CSRF check. Safe methods always pass;
+/// mutations need a localhost `Origin`. Without an `Origin` header, mutations
+/// pass in lenient mode (non-browser clients) but in strict mode only when
+/// `Sec-Fetch-Site` vouches for a same-origin request.
+pub(crate) fn csrf_allowed(method: &axum::http::Method, headers: &HeaderMap, strict: bool) -> bool {
     if method == axum::http::Method::GET || method == axum::http::Method::HEAD {
-        return Ok(next.run(request).await);
+        return true;
     }
 
-    let origin = request
-        .headers()
+    let origin = headers
         .get("origin")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
     if origin.is_empty() {
-        // No origin header — could be same-origin or non-browser. Allow for now
-        // but in production this should be tightened.
-        return Ok(next.run(request).await);
+        let same_origin = headers
+            .get("sec-fetch-site")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v == "same-origin");
+        return !strict || same_origin;
     }
 
-    let is_localhost = origin.starts_with("http://127.0.0.1")
-        || origin.starts_with("http://localhost")
-        || origin.starts_with("http://[::1]");
+    is_local_origin(origin)
+}
+
+/// Whether `origin` is plain `http` on a loopback host, on any port. The
+/// host is compared exactly, so `http://localhost.evil.com` is foreign.
+fn is_local_origin(origin: &str) -> bool {
+    let Some(authority) = origin.strip_prefix("http://") else {
+        return false;
+    };
+    ["127.0.0.1", "localhost", "[::1]"]
+        .iter()
+        .any(|host| match authority.strip_prefix(host) {
+            Some("") => true,
+            Some(rest) => rest
+                .strip_prefix(':')
+                .is_some_and(|port| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit())),
+            None => false,
+        })
+}
 
-    if is_localhost {
+/// CSRF middleware: reject mutating requests unless Origin is localhost.
+pub async fn csrf_check(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if csrf_allowed(request.method(), request.headers(), state.strict_csrf) {
         Ok(next.run(request).await)
     } else {
-        Err(StatusCode::FORBIDDEN)
+        Err(ApiError::Forbidden(
+            "cross-origin request rejected".to_string(),
+        ))
     }
 }
 
 pub fn router(state: Arc<AppState>) -> axum::Router {
     use axum::middleware;
-    use axum::routing::get;
+    use axum::routing::{get, post};
 
     axum::Router::new()
         .route("/api/files/raw/{*path}", get(files::raw_file))
-        .route("/api/files/", get(files::get_root))
+        .route(
+            "/api/files/",
+            get(files::get_root)
+                .head(files::head_root)
+                .post(files::post_root),
+        )
         .route(
             "/api/files/{*path}",
-            get(files::get_file).put(files::put_file),
+            get(files::get_file)
+                .head(files::head_file)
+                .put(files::put_file)
+                .patch(files::patch_file)
+                .post(files::post_file)
+                .delete(files::delete_file),
+        )
+        .route("/api/grep", get(search::grep))
+        .route("/api/mounts", get(mounts::list_mounts))
+        .route("/api/openapi.json", get(openapi::openapi_json))
+        .route("/api/zip/", get(export::zip_root))
+        .route("/api/zip/{*path}", get(export::zip_directory))
+        .route("/api/search", get(search::search))
+        .route("/api/size/", get(size::get_root_size))
+        .route("/api/size/{*path}", get(size::get_size))
+        .route("/api/stat/", get(stat::get_root_stat))
+        .route("/api/stat/{*path}", get(stat::get_stat))
+        .route("/api/stats/", get(stats::get_root_stats))
+        .route("/api/stats/{*path}", get(stats::get_stats))
+        .route("/api/thumb/{*path}", get(thumb::thumbnail))
+        .route("/api/upload/init", post(upload::begin_upload))
+        .route(
+            "/api/upload/{id}",
+            get(upload::upload_status).put(upload::put_chunk),
+        )
+        .route("/api/upload/{id}/finish", post(upload::finish_upload))
+        .route("/api/watch", get(watch::watch))
+        .route("/api/ws", get(ws::ws))
+        .route("/metrics", get(metrics::metrics))
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .layer(middleware::from_fn(error::normalize_errors))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit::log_traversals,
+        ))
+        // Innermost check, so requests refused anyway don't spend tokens
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::write_limit_check,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            read_only_check,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), csrf_check))
+        .layer(middleware::from_fn_with_state(state.clone(), auth_check))
+        // Probes are added after the auth layer so orchestrators need no token
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        // Outside auth, so refused requests are counted too
+        .layer(middleware::from_fn(metrics::track_requests))
+        .layer(CompressionLayer::new().compress_when(compression_predicate()))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_request(())
+                .on_response(
+                    DefaultOnResponse::new()
+                        .level(Level::INFO)
+                        .latency_unit(LatencyUnit::Millis),
+                ),
         )
-        .layer(middleware::from_fn(csrf_check))
         .with_state(state)
 }
+
+/// Span wrapping each request, logged with its status and latency. The
+/// `projection` field is filled in by handlers once they pick one, so slow
+/// projections show up by name.
+fn request_span(request: &Request) -> tracing::Span {
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = request.uri().path(),
+        projection = tracing::field::Empty,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Method;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (k, v) in pairs {
+            headers.insert(*k, v.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn csrf_safe_methods_always_pass() {
+        let cross = headers(&[("origin", "http://evil.example")]);
+        assert!(csrf_allowed(&Method::GET, &cross, true));
+        assert!(csrf_allowed(&Method::HEAD, &cross, true));
+    }
+
+    #[test]
+    fn csrf_missing_origin() {
+        let none = HeaderMap::new();
+        assert!(csrf_allowed(&Method::PUT, &none, false));
+        assert!(!csrf_allowed(&Method::PUT, &none, true));
+        assert!(!csrf_allowed(&Method::DELETE, &none, true));
+    }
+
+    #[test]
+    fn csrf_cross_origin_rejected() {
+        let cross = headers(&[("origin", "http://evil.example")]);
+        assert!(!csrf_allowed(&Method::POST, &cross, false));
+        assert!(!csrf_allowed(&Method::POST, &cross, true));
+        let cross_site = headers(&[("sec-fetch-site", "cross-site")]);
+        assert!(!csrf_allowed(&Method::POST, &cross_site, true));
+
+        // Hosts that only start like a loopback name are someone else's
+        for origin in [
+            "http://localhost.evil.com",
+            "http://127.0.0.1.nip.io",
+            "http://[::1].evil.com",
+            "http://localhost@evil.com",
+            "https://evil.com/http://localhost",
+            "null",
+        ] {
+            let foreign = headers(&[("origin", origin)]);
+            assert!(!csrf_allowed(&Method::POST, &foreign, false), "{origin}");
+        }
+    }
+
+    #[test]
+    fn csrf_same_origin_allowed() {
+        for origin in [
+            "http://localhost:3000",
+            "http://localhost",
+            "http://127.0.0.1:8080",
+            "http://[::1]:3000",
+        ] {
+            let local = headers(&[("origin", origin)]);
+            assert!(csrf_allowed(&Method::PUT, &local, true), "{origin}");
+        }
+        let fetch_meta = headers(&[("sec-fetch-site", "same-origin")]);
+        assert!(csrf_allowed(&Method::PUT, &fetch_meta, true));
+    }
+
+    #[test]
+    fn token_from_bearer_header_or_cookie() {
+        let token = "s3cret";
+        assert!(!token_presented(&HeaderMap::new(), token));
+        assert!(token_presented(
+            &headers(&[("authorization", "Bearer s3cret")]),
+            token
+        ));
+        assert!(!token_presented(
+            &headers(&[("authorization", "Bearer s3cre")]),
+            token
+        ));
+        assert!(!token_presented(
+            &headers(&[("authorization", "Basic s3cret")]),
+            token
+        ));
+        assert!(token_presented(
+            &headers(&[("cookie", "theme=dark; deskspace_token=s3cret")]),
+            token
+        ));
+        assert!(!token_presented(
+            &headers(&[("cookie", "other_token=s3cret")]),
+            token
+        ));
+    }
+
+    #[tokio::test]
+    async fn read_only_refuses_writes_over_http() {
+        use crate::projections::text_raw::TextRaw;
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "kept").unwrap();
+        let mut registry = ProjectionRegistry::new();
+        registry.register(Arc::new(TextRaw));
+        let mut state = AppState::for_tests(dir.path(), registry);
+        state.read_only = true;
+        let app = router(Arc::new(state));
+        let send = |method: Method| {
+            let app = app.clone();
+            async move {
+                let request = Request::builder()
+                    .method(method)
+                    .uri("/api/files/a.txt")
+                    .header(axum::http::header::CONTENT_TYPE, "application/json")
+                    .body(axum::body::Body::from("{}"))
+                    .unwrap();
+                app.oneshot(request).await.unwrap()
+            }
+        };
+
+        for method in [Method::PUT, Method::POST, Method::DELETE, Method::PATCH] {
+            let response = send(method.clone()).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{method}");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], "read_only", "{method}");
+        }
+        assert_eq!(send(Method::GET).await.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "kept"
+        );
+    }
+
+    #[tokio::test]
+    async fn only_probes_skip_the_access_token() {
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(dir.path(), ProjectionRegistry::new());
+        state.auth_token = Some("s3cret".to_string());
+        let app = router(Arc::new(state));
+        let get = |uri: &'static str, token: bool| {
+            let app = app.clone();
+            async move {
+                let mut request = Request::get(uri);
+                if token {
+                    request = request.header("authorization", "Bearer s3cret");
+                }
+                let request = request.body(axum::body::Body::empty()).unwrap();
+                app.oneshot(request).await.unwrap().status()
+            }
+        };
+
+        assert_eq!(get("/healthz", false).await, StatusCode::OK);
+        assert_eq!(get("/metrics", false).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(get("/api/files/", false).await, StatusCode::UNAUTHORIZED);
+        // Past the token, metrics are only missing because none are recorded
+        assert_eq!(get("/metrics", true).await, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn projection_size_limits() {
+        use crate::projections::{image_preview::ImagePreview, text_raw::TextRaw};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(dir.path(), ProjectionRegistry::new());
+        state.max_projection_bytes = 100;
+        assert_eq!(state.projection_size_limit(&TextRaw), Some(100));
+        // Streams the file, so unlimited unless configured
+        assert_eq!(state.projection_size_limit(&ImagePreview), None);
+
+        state.projection_size_limits = HashMap::from([
+            ("text.raw".to_string(), 0),
+            ("image.preview".to_string(), 500),
+        ]);
+        assert_eq!(state.projection_size_limit(&TextRaw), None);
+        assert_eq!(state.projection_size_limit(&ImagePreview), Some(500));
+
+        state.max_projection_bytes = 0;
+        state.projection_size_limits.clear();
+        assert_eq!(state.projection_size_limit(&TextRaw), None);
+    }
+
+    #[test]
+    fn compression_skips_precompressed_types() {
+        let response = |content_type: &str| {
+            Response::builder()
+                .header(axum::http::header::CONTENT_TYPE, content_type)
+                .body(axum::body::Body::from(vec![b'x'; 4096]))
+                .unwrap()
+        };
+        let predicate = compression_predicate();
+        assert!(predicate.should_compress(&response("application/json")));
+        assert!(predicate.should_compress(&response("text/plain")));
+        assert!(!predicate.should_compress(&response("video/mp4")));
+        assert!(!predicate.should_compress(&response("application/zip")));
+        assert!(!predicate.should_compress(&response("image/png")));
+        assert!(!predicate.should_compress(&response("text/event-stream")));
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::Json;
+use serde::Serialize;
+
+use crate::api::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct MountInfo {
+    /// Top-level path component the mount is served under.
+    pub name: String,
+}
+
+/// List the named mounts. Host paths are not reported.
+pub async fn list_mounts(State(state): State<Arc<AppState>>) -> Json<Vec<MountInfo>> {
+    Json(
+        state
+            .workspace
+            .mounts()
+            .map(|(name, _)| MountInfo {
+                name: name.to_owned(),
+            })
+            .collect(),
+    )
+}
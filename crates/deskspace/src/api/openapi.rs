@@ -0,0 +1,79 @@
+use axum::response::Json;
+use utoipa::openapi::schema::{Discriminator, Schema};
+use utoipa::openapi::RefOr;
+use utoipa::{Modify, OpenApi};
+
+use crate::api::error::ErrorBody;
+use crate::api::files;
+
+/// OpenAPI document for the file API, generated from the handlers' and
+/// types' derives so it can't drift from them.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "deskspace", description = "Unified file workspace server"),
+    paths(
+        files::get_root,
+        files::get_file,
+        files::raw_file,
+        files::put_file,
+        files::patch_file,
+        files::delete_file,
+    ),
+    components(schemas(ErrorBody)),
+    modifiers(&TaggedUnions),
+)]
+pub struct ApiDoc;
+
+/// Marks the internally tagged enums as discriminated on their tag. The
+/// derive only emits a discriminator for enums of newtype variants.
+struct TaggedUnions;
+
+impl Modify for TaggedUnions {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let Some(components) = openapi.components.as_mut() else {
+            return;
+        };
+        for (name, tag) in [("ProjectionOutput", "type"), ("NotebookOutput", "kind")] {
+            if let Some(RefOr::T(Schema::OneOf(one_of))) = components.schemas.get_mut(name) {
+                one_of.discriminator = Some(Discriminator::new(tag));
+            }
+        }
+    }
+}
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_describes_files_and_tagged_outputs() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let paths = &spec["paths"];
+        for path in ["/api/files/", "/api/files/{path}", "/api/files/raw/{path}"] {
+            assert!(paths[path]["get"].is_object(), "{path} missing");
+        }
+        let params: Vec<&str> = paths["/api/files/{path}"]["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(params, ["path", "projection"]);
+
+        let schemas = &spec["components"]["schemas"];
+        for name in ["FileResponse", "ProjectionInfo", "ErrorBody"] {
+            assert!(schemas[name].is_object(), "{name} missing");
+        }
+        let output = &schemas["ProjectionOutput"];
+        assert_eq!(output["discriminator"]["propertyName"], "type");
+        let variants = output["oneOf"].as_array().unwrap();
+        let listing = serde_json::json!(["DirectoryList"]);
+        assert!(variants
+            .iter()
+            .any(|v| v["properties"]["type"]["enum"] == listing));
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::api::{ApiError, AppState};
+
+/// Token bucket shared by every client: holds up to `burst` tokens,
+/// refilled at `per_second`, and each request takes one.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    /// Tokens left and when they were counted.
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Panics unless `per_second` is positive.
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        assert!(per_second > 0.0, "rate must be positive");
+        let burst = f64::from(burst.max(1));
+        Self {
+            per_second,
+            burst,
+            bucket: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Take a token, or say how long until one is available.
+    pub fn acquire(&self) -> Result<(), Duration> {
+        self.acquire_at(Instant::now())
+    }
+
+    fn acquire_at(&self, now: Instant) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let (tokens, counted) = *bucket;
+        let elapsed = now.saturating_duration_since(counted).as_secs_f64();
+        let tokens = (tokens + elapsed * self.per_second).min(self.burst);
+        if tokens >= 1.0 {
+            *bucket = (tokens - 1.0, now);
+            Ok(())
+        } else {
+            *bucket = (tokens, now);
+            Err(Duration::from_secs_f64((1.0 - tokens) / self.per_second))
+        }
+    }
+}
+
+/// Rate limit middleware: mutating requests draw from `write_limit` and get
+/// 429 once it runs dry. Reads are never limited.
+pub async fn write_limit_check(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let method = request.method();
+    let is_safe = method == Method::GET || method == Method::HEAD || method == Method::OPTIONS;
+    if let (Some(limiter), false) = (&state.write_limit, is_safe) {
+        limiter.acquire().map_err(ApiError::TooManyRequests)?;
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.acquire_at(start).is_ok());
+        }
+        let wait = limiter.acquire_at(start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+
+        // Half a second refills one token, not more
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.acquire_at(later).is_ok());
+        assert!(limiter.acquire_at(later).is_err());
+
+        // A long pause refills no more than the burst
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.acquire_at(much_later).is_ok());
+        }
+        assert!(limiter.acquire_at(much_later).is_err());
+    }
+}
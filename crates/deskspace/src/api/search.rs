@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Json, Response};
+use serde::Deserialize;
+
+use crate::api::{ApiError, AppState};
+use crate::workspace::GrepOptions;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Files larger than this are not searched by `/api/grep`.
+const GREP_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+pub async fn search(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Response, ApiError> {
+    if query.q.is_empty() {
+        return Err(ApiError::BadRequest("empty query".to_string()));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    if let Some(results) = state
+        .search_index
+        .as_ref()
+        .and_then(|i| i.search(&query.q, limit))
+    {
+        return Ok(Json(results).into_response());
+    }
+    let workspace = state.workspace.clone();
+    let results = tokio::task::spawn_blocking(move || workspace.search(&query.q, limit)).await??;
+    Ok(Json(results).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct GrepQuery {
+    pub q: String,
+    /// Only search files whose workspace-relative path matches, e.g. `*.rs`.
+    pub glob: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Content search: lines containing `q` in text files under the root.
+pub async fn grep(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<GrepQuery>,
+) -> Result<Response, ApiError> {
+    if query.q.is_empty() {
+        return Err(ApiError::BadRequest("empty query".to_string()));
+    }
+    let glob = query
+        .glob
+        .filter(|g| !g.is_empty())
+        .map(|g| globset::Glob::new(&g).map(|g| g.compile_matcher()))
+        .transpose()
+        .map_err(|e| ApiError::BadRequest(format!("invalid glob: {e}")))?;
+    let options = GrepOptions {
+        glob,
+        limit: query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT),
+        max_file_size: GREP_MAX_FILE_SIZE,
+    };
+    let workspace = state.workspace.clone();
+    let results = tokio::task::spawn_blocking(move || workspace.grep(&query.q, &options)).await??;
+    Ok(Json(results).into_response())
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Json, Response};
+use serde::Deserialize;
+
+use crate::api::{ApiError, AppState};
+use crate::workspace::WalkOptions;
+
+#[derive(Deserialize)]
+pub struct SizeQuery {
+    /// Stop descending below this depth; capped at the walk default.
+    pub max_depth: Option<usize>,
+}
+
+/// Sets the flag when dropped, which happens when the client disconnects
+/// and axum drops the handler future mid-walk.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+async fn size_response(
+    state: Arc<AppState>,
+    path: String,
+    query: SizeQuery,
+) -> Result<Response, ApiError> {
+    let defaults = WalkOptions::default();
+    let options = WalkOptions {
+        max_depth: query
+            .max_depth
+            .map_or(defaults.max_depth, |d| d.min(defaults.max_depth)),
+        ..defaults
+    };
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _guard = CancelOnDrop(cancelled.clone());
+
+    let workspace = state.workspace.clone();
+    let size = tokio::task::spawn_blocking(move || {
+        workspace.directory_size(Path::new(&path), &options, &cancelled)
+    })
+    .await??;
+    Ok(Json(size).into_response())
+}
+
+pub async fn get_root_size(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SizeQuery>,
+) -> Result<Response, ApiError> {
+    size_response(state, String::new(), query).await
+}
+
+pub async fn get_size(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    Query(query): Query<SizeQuery>,
+) -> Result<Response, ApiError> {
+    size_response(state, path, query).await
+}
@@ -0,0 +1,99 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Json, Response};
+use serde::Serialize;
+
+use crate::api::files::{mime_type, sniffed_resource};
+use crate::api::{ApiError, AppState};
+use crate::workspace::{changed_secs, created_secs, file_mode, modified_secs, WorkspaceError};
+
+#[derive(Debug, Serialize)]
+pub struct FileStat {
+    pub path: String,
+    pub name: String,
+    /// Describes the link's target when the path is a symlink that
+    /// resolves inside the workspace.
+    pub is_dir: bool,
+    pub size: u64,
+    /// Unix seconds.
+    pub modified: Option<u64>,
+    /// Birth time in unix seconds, where the filesystem records it.
+    pub created: Option<u64>,
+    /// Last status change (`ctime`) in unix seconds, on Unix.
+    pub changed: Option<u64>,
+    /// Unix permission bits of the path itself.
+    pub mode: Option<u32>,
+    pub is_symlink: bool,
+    /// Workspace-relative target of a symlink that stays inside the root.
+    pub symlink_target: Option<String>,
+    /// Symlink pointing outside the root; it is described but not followed.
+    pub outside_root: bool,
+    pub mime_type: Option<String>,
+    /// Ids of the projections that can show the path, best first.
+    pub projections: Vec<String>,
+}
+
+/// Metadata-only counterpart of `get_file`: stats the path without
+/// following a final symlink and reports what could show it, reading at
+/// most the few bytes needed to sniff its type.
+async fn stat_response(state: &AppState, path: &str) -> Result<Response, ApiError> {
+    let workspace = &state.workspace;
+    let link_meta = workspace.symlink_metadata(Path::new(path)).await?;
+    let is_symlink = link_meta.file_type().is_symlink();
+    let (meta, symlink_target, outside_root) = if is_symlink {
+        match workspace.resolve(path) {
+            Ok(target) => match tokio::fs::metadata(&target).await {
+                Ok(meta) => (meta, Some(workspace.relative(&target)), false),
+                // Dangling link
+                Err(_) => (link_meta.clone(), None, false),
+            },
+            Err(WorkspaceError::PathTraversal(_)) => (link_meta.clone(), None, true),
+            Err(_) => (link_meta.clone(), None, false),
+        }
+    } else {
+        (link_meta.clone(), None, false)
+    };
+
+    let resource = sniffed_resource(state, path, &crate::store::FileStat::from(&meta)).await;
+    // Nothing can open a link that leaves the workspace
+    let projections = if outside_root {
+        Vec::new()
+    } else {
+        state
+            .registry
+            .available_for(&resource)
+            .into_iter()
+            .map(|p| p.id)
+            .collect()
+    };
+    let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    Ok(Json(FileStat {
+        path: path.to_string(),
+        name: name.to_string(),
+        is_dir: meta.is_dir(),
+        size: meta.len(),
+        modified: modified_secs(&meta),
+        created: created_secs(&meta),
+        changed: changed_secs(&meta),
+        mode: file_mode(&link_meta),
+        is_symlink,
+        symlink_target,
+        outside_root,
+        mime_type: mime_type(&resource),
+        projections,
+    })
+    .into_response())
+}
+
+pub async fn get_root_stat(State(state): State<Arc<AppState>>) -> Result<Response, ApiError> {
+    stat_response(&state, "").await
+}
+
+pub async fn get_stat(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+) -> Result<Response, ApiError> {
+    stat_response(&state, &path).await
+}
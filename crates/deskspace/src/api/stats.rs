@@ -0,0 +1,160 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::{IntoResponse, Json, Response};
+use serde::Serialize;
+
+use crate::api::{ApiError, AppState};
+use crate::workspace::{WalkOptions, Workspace};
+
+/// Maximum number of extensions reported in the breakdown.
+const MAX_EXTENSIONS: usize = 20;
+/// Maximum number of entries in the largest-files list.
+const MAX_LARGEST: usize = 20;
+
+#[derive(Debug, Serialize)]
+pub struct TreeStats {
+    pub path: String,
+    pub total_files: u64,
+    pub total_dirs: u64,
+    pub total_bytes: u64,
+    /// Per-extension counts, largest total size first.
+    pub extensions: Vec<ExtensionStats>,
+    /// Largest files under the path, largest first.
+    pub largest_files: Vec<FileSize>,
+    /// Whether the walk hit its depth or entry cap.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExtensionStats {
+    /// Lowercase extension without the dot; `None` for extensionless files.
+    pub extension: Option<String>,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileSize {
+    pub size: u64,
+    pub path: String,
+}
+
+/// Walk `path` and aggregate file counts, sizes, and the extension histogram.
+pub fn compute_stats(workspace: &Workspace, path: &str) -> crate::workspace::Result<TreeStats> {
+    let mut total_files = 0;
+    let mut total_dirs = 0;
+    let mut total_bytes = 0;
+    let mut by_extension: HashMap<Option<String>, (u64, u64)> = HashMap::new();
+    // Min-heap of the largest files seen so far, bounded to MAX_LARGEST
+    let mut largest: BinaryHeap<Reverse<FileSize>> = BinaryHeap::new();
+
+    let summary = workspace.walk(Path::new(path), &WalkOptions::default(), |entry| {
+        if entry.is_dir {
+            total_dirs += 1;
+            return ControlFlow::Continue(());
+        }
+        if entry.is_symlink {
+            return ControlFlow::Continue(());
+        }
+        total_files += 1;
+        total_bytes += entry.size;
+
+        let extension = Path::new(&entry.path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        let bucket = by_extension.entry(extension).or_default();
+        bucket.0 += 1;
+        bucket.1 += entry.size;
+
+        if largest.len() < MAX_LARGEST {
+            largest.push(Reverse(FileSize {
+                size: entry.size,
+                path: entry.path.clone(),
+            }));
+        } else if largest
+            .peek()
+            .is_some_and(|Reverse(min)| entry.size > min.size)
+        {
+            largest.pop();
+            largest.push(Reverse(FileSize {
+                size: entry.size,
+                path: entry.path.clone(),
+            }));
+        }
+        ControlFlow::Continue(())
+    })?;
+
+    let mut extensions: Vec<_> = by_extension
+        .into_iter()
+        .map(|(extension, (files, bytes))| ExtensionStats {
+            extension,
+            files,
+            bytes,
+        })
+        .collect();
+    extensions.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| b.files.cmp(&a.files)));
+    extensions.truncate(MAX_EXTENSIONS);
+
+    let largest_files = largest
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(f)| f)
+        .collect();
+
+    Ok(TreeStats {
+        path: path.to_string(),
+        total_files,
+        total_dirs,
+        total_bytes,
+        extensions,
+        largest_files,
+        truncated: summary.truncated,
+    })
+}
+
+async fn stats_response(state: Arc<AppState>, path: String) -> Result<Response, ApiError> {
+    let workspace = state.workspace.clone();
+    let stats = tokio::task::spawn_blocking(move || compute_stats(&workspace, &path)).await??;
+    Ok(Json(stats).into_response())
+}
+
+pub async fn get_root_stats(State(state): State<Arc<AppState>>) -> Result<Response, ApiError> {
+    stats_response(state, String::new()).await
+}
+
+pub async fn get_stats(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+) -> Result<Response, ApiError> {
+    stats_response(state, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn compute_stats_aggregates_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("README"), "hello").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let stats = compute_stats(&ws, "").unwrap();
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.total_dirs, 1);
+        assert_eq!(stats.total_bytes, 17);
+        assert_eq!(stats.extensions[0].extension.as_deref(), Some("rs"));
+        assert_eq!(stats.extensions[0].files, 2);
+        assert_eq!(stats.largest_files[0].path, "src/main.rs");
+        assert_eq!(stats.largest_files[1].path, "README");
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Response;
+use serde::Deserialize;
+
+use crate::api::files::not_modified;
+use crate::api::{ApiError, AppState};
+use crate::thumbnail::{self, ThumbnailCache};
+
+const DEFAULT_WIDTH: u32 = 256;
+
+#[derive(Deserialize)]
+pub struct ThumbQuery {
+    /// Target width in pixels, clamped to `thumbnail::MAX_WIDTH`.
+    pub w: Option<u32>,
+}
+
+/// Downscaled JPEG of an image, for grids where the full file would be too
+/// slow. Thumbnails are generated once per file version and width, then
+/// served from the disk cache.
+pub async fn thumbnail(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(path): axum::extract::Path<String>,
+    Query(query): Query<ThumbQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let width = query
+        .w
+        .unwrap_or(DEFAULT_WIDTH)
+        .clamp(1, thumbnail::MAX_WIDTH);
+    let meta = state.workspace.metadata(Path::new(&path)).await?;
+    if meta.is_dir() {
+        return Err(ApiError::IsDirectory);
+    }
+    let resolved = state.workspace.resolve(&path)?;
+    let key = ThumbnailCache::key(&resolved, &meta, width);
+    let etag = format!("\"{key}\"");
+    if not_modified(&headers, Some(&etag), None) {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    let cache = state.thumbnails.clone();
+    let jpeg = tokio::task::spawn_blocking(move || {
+        if let Some(hit) = cache.get(&key) {
+            return Ok(hit);
+        }
+        let jpeg = thumbnail::generate(&resolved, width)?;
+        if let Err(e) = cache.put(&key, &jpeg) {
+            tracing::debug!("thumbnail cache write failed: {e}");
+        }
+        Ok::<_, ApiError>(jpeg)
+    })
+    .await??;
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "image/jpeg")
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from(jpeg))
+        .unwrap())
+}
@@ -0,0 +1,91 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use serde::Deserialize;
+
+use crate::api::files::etag;
+use crate::api::{ApiError, AppState};
+
+#[derive(Deserialize)]
+pub struct BeginRequest {
+    /// Destination, relative to the workspace root.
+    pub path: String,
+    /// Total size, if known; finishing short of it is refused.
+    pub size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ChunkQuery {
+    /// Where this chunk starts: the upload's current offset.
+    pub offset: u64,
+}
+
+/// Begin a chunked upload. The file is staged outside the workspace until
+/// finished; the response has its id and offset.
+pub async fn begin_upload(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BeginRequest>,
+) -> Result<Response, ApiError> {
+    let resolved = state.workspace.resolve(&request.path)?;
+    if resolved == state.workspace.root() {
+        return Err(ApiError::WorkspaceRoot);
+    }
+    if tokio::fs::metadata(&resolved)
+        .await
+        .is_ok_and(|meta| meta.is_dir())
+    {
+        return Err(ApiError::IsDirectory);
+    }
+    let status = state.uploads.begin(&request.path, request.size).await?;
+    Ok((StatusCode::CREATED, Json(status)).into_response())
+}
+
+/// Where an upload stands, so a client can resume after losing its
+/// connection.
+pub async fn upload_status(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Response, ApiError> {
+    Ok(Json(state.uploads.status(&id).await?).into_response())
+}
+
+/// Append a chunk. `offset` must equal the bytes received so far; anything
+/// else is 409 with the real offset in `Upload-Offset`.
+pub async fn put_chunk(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Query(query): Query<ChunkQuery>,
+    body: axum::body::Bytes,
+) -> Result<Response, ApiError> {
+    let status = state.uploads.append(&id, query.offset, &body).await?;
+    Ok(Json(status).into_response())
+}
+
+/// Move the finished upload to its destination atomically, replacing any
+/// file there. The new ETag is returned.
+pub async fn finish_upload(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Response, ApiError> {
+    let workspace = &state.workspace;
+    let path = state
+        .uploads
+        .finish(&id, |path, staged| async move {
+            workspace.install(Path::new(&path), &staged).await
+        })
+        .await?;
+    let mut builder = Response::builder().status(StatusCode::NO_CONTENT);
+    if let Some(etag) = workspace
+        .metadata(Path::new(&path))
+        .await
+        .ok()
+        .and_then(|meta| etag(&meta))
+    {
+        builder = builder.header(header::ETAG, etag);
+    }
+    Ok(builder.body(Body::empty()).unwrap())
+}
@@ -0,0 +1,39 @@
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+
+use crate::api::{ApiError, AppState};
+
+#[derive(Deserialize)]
+pub struct WatchQuery {
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+/// Stream change events under a path as Server-Sent Events. The watcher
+/// lives inside the stream, so it is torn down when the client disconnects.
+pub async fn watch(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WatchQuery>,
+) -> Result<Response, ApiError> {
+    let watch = crate::watch::watch(&state.workspace, Path::new(&query.path), query.recursive)?;
+
+    let stream = futures::stream::unfold(watch, |mut watch| async move {
+        let change = watch.next().await?;
+        let event = Event::default()
+            .json_data(&change)
+            .unwrap_or_else(|_| Event::default());
+        Some((Ok::<_, Infallible>(event), watch))
+    });
+
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::{Extensions, HeaderMap, Method, Uri};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::api::audit::Requester;
+use crate::api::{csrf_allowed, ApiError, AppState};
+use crate::watch::ChangeEvent;
+use crate::workspace::WorkspaceError;
+
+/// Outgoing messages buffered per connection, mostly change events.
+const OUTBOX_CAPACITY: usize = 256;
+
+/// A client request. `id` is echoed back on the reply so clients can match
+/// them up.
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    op: Op,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Op {
+    Read {
+        path: String,
+    },
+    Write {
+        path: String,
+        content: String,
+    },
+    Watch {
+        path: String,
+        #[serde(default)]
+        recursive: bool,
+    },
+    Unwatch {
+        path: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Reply {
+    Content {
+        id: Option<serde_json::Value>,
+        path: String,
+        content: String,
+    },
+    Ack {
+        id: Option<serde_json::Value>,
+        path: String,
+    },
+    /// A change under a path the client is watching.
+    Change {
+        watch: String,
+        #[serde(flatten)]
+        event: ChangeEvent,
+    },
+    Error {
+        id: Option<serde_json::Value>,
+        error: String,
+    },
+}
+
+/// State of one WebSocket connection. Watch tasks are aborted when the
+/// session is dropped, so nothing outlives the socket.
+struct Session {
+    state: Arc<AppState>,
+    /// The upgrade request, named in audit warnings for refused paths.
+    requester: Requester,
+    outbox: mpsc::Sender<Reply>,
+    watches: HashMap<String, JoinHandle<()>>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        for task in self.watches.values() {
+            task.abort();
+        }
+    }
+}
+
+impl Session {
+    /// Answer one text frame.
+    async fn handle(&mut self, text: &str) -> Reply {
+        let request: Request = match serde_json::from_str(text) {
+            Ok(request) => request,
+            Err(e) => {
+                return Reply::Error {
+                    id: None,
+                    error: format!("invalid message: {e}"),
+                }
+            }
+        };
+        let id = request.id;
+        let result = match request.op {
+            Op::Read { path } => self
+                .state
+                .workspace
+                .read_to_string(Path::new(&path))
+                .await
+                .map(|content| Reply::Content {
+                    id: id.clone(),
+                    path,
+                    content,
+                }),
+            Op::Write { path, content } => {
+                if self.state.read_only {
+                    return Reply::Error {
+                        id,
+                        error: "server is read-only".to_string(),
+                    };
+                }
+                // Drawn from the same bucket as HTTP writes, which only see
+                // this socket's upgrade
+                if let Some(Err(wait)) = self.state.write_limit.as_ref().map(|l| l.acquire()) {
+                    return Reply::Error {
+                        id,
+                        error: format!("too many writes, retry in {:.1}s", wait.as_secs_f64()),
+                    };
+                }
+                self.state
+                    .workspace
+                    .write(Path::new(&path), content.as_bytes())
+                    .await
+                    .map(|()| Reply::Ack {
+                        id: id.clone(),
+                        path,
+                    })
+            }
+            Op::Watch { path, recursive } => self.watch(&path, recursive).map(|()| Reply::Ack {
+                id: id.clone(),
+                path,
+            }),
+            Op::Unwatch { path } => {
+                if let Some(task) = self.watches.remove(&path) {
+                    task.abort();
+                }
+                Ok(Reply::Ack {
+                    id: id.clone(),
+                    path,
+                })
+            }
+        };
+        result.unwrap_or_else(|e| {
+            if let WorkspaceError::PathTraversal(path) = &e {
+                self.requester.traversal_rejected(&self.state, path);
+            }
+            Reply::Error {
+                id,
+                error: e.to_string(),
+            }
+        })
+    }
+
+    /// Forward changes under `path` to the client until unwatched or
+    /// disconnected. Watching a path again replaces the earlier watch.
+    fn watch(&mut self, path: &str, recursive: bool) -> crate::workspace::Result<()> {
+        let mut watch = crate::watch::watch(&self.state.workspace, Path::new(path), recursive)?;
+        let outbox = self.outbox.clone();
+        let name = path.to_string();
+        let task = tokio::spawn(async move {
+            while let Some(event) = watch.next().await {
+                let change = Reply::Change {
+                    watch: name.clone(),
+                    event,
+                };
+                if outbox.send(change).await.is_err() {
+                    break;
+                }
+            }
+        });
+        if let Some(previous) = self.watches.insert(path.to_string(), task) {
+            previous.abort();
+        }
+        Ok(())
+    }
+}
+
+async fn send(socket: &mut WebSocket, reply: &Reply) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(reply).unwrap_or_default();
+    socket.send(Message::Text(text.into())).await
+}
+
+async fn serve(mut socket: WebSocket, state: Arc<AppState>, requester: Requester) {
+    let (outbox, mut events) = mpsc::channel(OUTBOX_CAPACITY);
+    let mut session = Session {
+        state,
+        requester,
+        outbox,
+        watches: HashMap::new(),
+    };
+    loop {
+        let reply = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => session.handle(&text).await,
+                Some(Ok(Message::Binary(_))) => Reply::Error {
+                    id: None,
+                    error: "expected a text message".to_string(),
+                },
+                Some(Ok(_)) => continue,
+                Some(Err(_)) | None => break,
+            },
+            Some(change) = events.recv() => change,
+        };
+        if send(&mut socket, &reply).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Persistent channel for reading, writing and watching files. Browsers
+/// can open WebSockets cross-site, and writes go through this socket, so
+/// the upgrade is held to the same origin rules as other mutations.
+pub async fn ws(
+    State(state): State<Arc<AppState>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    extensions: Extensions,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if !csrf_allowed(&Method::POST, &headers, state.strict_csrf) {
+        return ApiError::Forbidden("cross-origin WebSocket rejected".to_string()).into_response();
+    }
+    let requester = Requester::new(&method, &uri, &headers, &extensions);
+    upgrade
+        .on_upgrade(move |socket| serve(socket, state, requester))
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::api::RateLimiter;
+    use crate::registry::ProjectionRegistry;
+
+    fn session(dir: &Path, read_only: bool) -> (Session, mpsc::Receiver<Reply>) {
+        let mut state = AppState::for_tests(dir, ProjectionRegistry::new());
+        state.read_only = read_only;
+        session_over(state)
+    }
+
+    fn session_over(state: AppState) -> (Session, mpsc::Receiver<Reply>) {
+        let (outbox, events) = mpsc::channel(OUTBOX_CAPACITY);
+        let session = Session {
+            state: Arc::new(state),
+            requester: Requester::new(
+                &Method::GET,
+                &Uri::from_static("/api/ws"),
+                &HeaderMap::new(),
+                &Extensions::new(),
+            ),
+            outbox,
+            watches: HashMap::new(),
+        };
+        (session, events)
+    }
+
+    #[tokio::test]
+    async fn read_write_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut session, _events) = session(dir.path(), false);
+
+        let reply = session
+            .handle(r#"{"op":"write","id":1,"path":"a/b.txt","content":"hi"}"#)
+            .await;
+        assert!(matches!(reply, Reply::Ack { id: Some(_), .. }), "{reply:?}");
+        let reply = session.handle(r#"{"op":"read","path":"a/b.txt"}"#).await;
+        assert!(matches!(reply, Reply::Content { ref content, .. } if content == "hi"));
+
+        let reply = session
+            .handle(r#"{"op":"read","id":"x","path":"../escape"}"#)
+            .await;
+        assert!(
+            matches!(reply, Reply::Error { id: Some(_), .. }),
+            "{reply:?}"
+        );
+        // Refused like an HTTP request for the same path would be
+        assert_eq!(
+            session.state.traversal_rejections.load(Ordering::Relaxed),
+            1
+        );
+        let reply = session.handle(r#"{"op":"delete","path":"a"}"#).await;
+        assert!(matches!(reply, Reply::Error { id: None, .. }), "{reply:?}");
+    }
+
+    #[tokio::test]
+    async fn read_only_rejects_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut session, _events) = session(dir.path(), true);
+        let reply = session
+            .handle(r#"{"op":"write","path":"a.txt","content":"x"}"#)
+            .await;
+        assert!(matches!(reply, Reply::Error { .. }));
+        assert!(!dir.path().join("a.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn writes_draw_from_the_write_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::for_tests(dir.path(), ProjectionRegistry::new());
+        state.write_limit = Some(RateLimiter::new(0.01, 2));
+        let (mut session, _events) = session_over(state);
+        let write = |name: &str| format!(r#"{{"op":"write","path":"{name}","content":"x"}}"#);
+
+        for name in ["a.txt", "b.txt"] {
+            let reply = session.handle(&write(name)).await;
+            assert!(matches!(reply, Reply::Ack { .. }), "{reply:?}");
+        }
+        let reply = session.handle(&write("c.txt")).await;
+        assert!(
+            matches!(reply, Reply::Error { ref error, .. } if error.starts_with("too many writes")),
+            "{reply:?}"
+        );
+        assert!(!dir.path().join("c.txt").exists());
+        // Reads aren't limited
+        let reply = session.handle(r#"{"op":"read","path":"a.txt"}"#).await;
+        assert!(matches!(reply, Reply::Content { .. }), "{reply:?}");
+    }
+
+    #[tokio::test]
+    async fn watch_forwards_changes_until_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut session, mut events) = session(dir.path(), false);
+        let reply = session.handle(r#"{"op":"watch","path":""}"#).await;
+        assert!(matches!(reply, Reply::Ack { .. }), "{reply:?}");
+
+        std::fs::write(dir.path().join("new.txt"), "x").unwrap();
+        let change = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(change, Reply::Change { ref event, .. } if event.path == "new.txt"));
+
+        // Dropping the session aborts the forwarder, closing the channel
+        drop(session);
+        assert!(tokio::time::timeout(Duration::from_secs(5), async {
+            while events.recv().await.is_some() {}
+        })
+        .await
+        .is_ok());
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::{Component, Path};
+
+use flate2::read::GzDecoder;
+
+use crate::projection::{ArchiveEntry, Resource};
+
+/// Archive formats that can be listed without extracting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Detect the archive format from a file name.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_lowercase();
+        let ext = Path::new(&lower).extension()?.to_str()?;
+        match ext {
+            "zip" => Some(Self::Zip),
+            "tar" => Some(Self::Tar),
+            "tgz" | "gz" => Some(Self::TarGz),
+            _ => None,
+        }
+    }
+
+    /// Detect the archive format from the name, else from the sniffed
+    /// content type, so extensionless archives are still recognised.
+    pub fn for_resource(resource: &Resource) -> Option<Self> {
+        Self::from_path(&resource.path).or_else(|| match resource.mime_type.as_deref()? {
+            "application/zip" => Some(Self::Zip),
+            "application/x-tar" => Some(Self::Tar),
+            "application/gzip" => Some(Self::TarGz),
+            _ => None,
+        })
+    }
+}
+
+fn zip_error(e: zip::result::ZipError) -> std::io::Error {
+    match e {
+        zip::result::ZipError::Io(io) => io,
+        zip::result::ZipError::FileNotFound => std::io::ErrorKind::NotFound.into(),
+        other => std::io::Error::new(std::io::ErrorKind::InvalidData, other),
+    }
+}
+
+fn tar_reader(file: File, kind: ArchiveKind) -> tar::Archive<Box<dyn Read>> {
+    let reader: Box<dyn Read> = match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(BufReader::new(file))),
+        _ => Box::new(BufReader::new(file)),
+    };
+    tar::Archive::new(reader)
+}
+
+/// List up to `max_entries` entries of the archive at `path`. Zip archives
+/// are read from the central directory; tar archives are streamed header by
+/// header, skipping over file contents. Returns whether the list was cut short.
+///
+/// Reads the file synchronously; a compressed tar is decompressed up to the
+/// last entry listed.
+pub fn list(
+    path: &Path,
+    kind: ArchiveKind,
+    max_entries: usize,
+) -> std::io::Result<(Vec<ArchiveEntry>, bool)> {
+    let file = File::open(path)?;
+    let mut entries = Vec::new();
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(zip_error)?;
+            for i in 0..archive.len() {
+                if entries.len() >= max_entries {
+                    return Ok((entries, true));
+                }
+                let entry = archive.by_index_raw(i).map_err(zip_error)?;
+                entries.push(ArchiveEntry {
+                    path: entry.name().map_err(zip_error)?.into_owned(),
+                    size: entry.size(),
+                    compressed_size: Some(entry.compressed_size()),
+                    is_dir: entry.is_dir(),
+                });
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let mut archive = tar_reader(file, kind);
+            for entry in archive.entries()? {
+                if entries.len() >= max_entries {
+                    return Ok((entries, true));
+                }
+                let entry = entry?;
+                let header = entry.header();
+                entries.push(ArchiveEntry {
+                    path: entry.path()?.to_string_lossy().into_owned(),
+                    size: header.size()?,
+                    compressed_size: None,
+                    is_dir: header.entry_type().is_dir(),
+                });
+            }
+        }
+    }
+    Ok((entries, false))
+}
+
+/// Normalize a requested entry path to the `a/b/c` form stored in archives.
+/// Returns `None` for empty, absolute, or parent-relative paths, so a lookup
+/// can never match an entry like `../../escape`.
+pub fn normalize_entry(entry: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(entry).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// Find `entry` in the archive at `path` and copy its contents into the
+/// writer returned by `open`, which is called with the entry's uncompressed
+/// size once the entry has been located. Fails with `NotFound` if there is
+/// no such entry and `InvalidInput` if it names a directory; `open` is not
+/// called in either case.
+///
+/// Entries whose stored names are absolute or contain `..` never match.
+/// Returns only once the whole entry has been written to `open`'s writer.
+pub fn extract<W: Write>(
+    path: &Path,
+    kind: ArchiveKind,
+    entry: &str,
+    open: impl FnOnce(u64) -> W,
+) -> std::io::Result<u64> {
+    let entry = normalize_entry(entry).ok_or(std::io::ErrorKind::NotFound)?;
+    let file = File::open(path)?;
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(zip_error)?;
+            let index = (0..archive.len())
+                .find(|&i| {
+                    archive.by_index_raw(i).is_ok_and(|f| {
+                        f.enclosed_name()
+                            .and_then(|p| normalize_entry(&p.to_string_lossy()))
+                            .is_some_and(|p| p == entry)
+                    })
+                })
+                .ok_or(std::io::ErrorKind::NotFound)?;
+            let mut file = archive.by_index(index).map_err(zip_error)?;
+            if file.is_dir() {
+                return Err(std::io::ErrorKind::InvalidInput.into());
+            }
+            let mut writer = open(file.size());
+            let copied = std::io::copy(&mut file, &mut writer)?;
+            writer.flush()?;
+            Ok(copied)
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let mut archive = tar_reader(file, kind);
+            for item in archive.entries()? {
+                let mut item = item?;
+                let matches = item
+                    .path()
+                    .ok()
+                    .and_then(|p| normalize_entry(&p.to_string_lossy()))
+                    .is_some_and(|p| p == entry);
+                if !matches {
+                    continue;
+                }
+                if item.header().entry_type().is_dir() {
+                    return Err(std::io::ErrorKind::InvalidInput.into());
+                }
+                let mut writer = open(item.header().size()?);
+                let copied = std::io::copy(&mut item, &mut writer)?;
+                writer.flush()?;
+                return Ok(copied);
+            }
+            Err(std::io::ErrorKind::NotFound.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn list_zip_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.add_directory("docs/", options).unwrap();
+        writer.start_file("docs/a.txt", options).unwrap();
+        writer.write_all(b"hello zip").unwrap();
+        writer.finish().unwrap();
+
+        let (entries, truncated) = list(&path, ArchiveKind::Zip, 100).unwrap();
+        assert!(!truncated);
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[1].path, "docs/a.txt");
+        assert_eq!(entries[1].size, 9);
+        assert!(entries[1].compressed_size.is_some());
+    }
+
+    #[test]
+    fn normalize_entry_rejects_escapes() {
+        assert_eq!(
+            normalize_entry("./docs//a.txt").as_deref(),
+            Some("docs/a.txt")
+        );
+        assert_eq!(normalize_entry("../../escape"), None);
+        assert_eq!(normalize_entry("/etc/passwd"), None);
+        assert_eq!(normalize_entry(""), None);
+    }
+
+    #[test]
+    fn extract_zip_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.add_directory("docs/", options).unwrap();
+        writer.start_file("docs/a.txt", options).unwrap();
+        writer.write_all(b"hello zip").unwrap();
+        writer.finish().unwrap();
+
+        let mut out = Vec::new();
+        let mut size = None;
+        extract(&path, ArchiveKind::Zip, "docs/a.txt", |s| {
+            size = Some(s);
+            &mut out
+        })
+        .unwrap();
+        assert_eq!(size, Some(9));
+        assert_eq!(out, b"hello zip");
+
+        let missing = extract(&path, ArchiveKind::Zip, "nope.txt", |_| Vec::new());
+        assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        let dir_entry = extract(&path, ArchiveKind::Zip, "docs/", |_| Vec::new());
+        assert_eq!(
+            dir_entry.unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn list_tar_gz_entries_with_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.tar.gz");
+        let gz = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(gz);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, &b"data"[..])
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let sniffed =
+            Resource::new("backup".into(), false).with_mime_type(Some("application/gzip".into()));
+        assert_eq!(
+            ArchiveKind::for_resource(&sniffed),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(
+            ArchiveKind::from_path("test.tar.gz"),
+            Some(ArchiveKind::TarGz)
+        );
+        let (entries, truncated) = list(&path, ArchiveKind::TarGz, 2).unwrap();
+        assert!(truncated);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].path, "b.txt");
+        assert_eq!(entries[1].size, 4);
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use lru::LruCache;
+
+use crate::projection::ProjectionMeta;
+
+/// Identifies one projection of one version of a file. Modification time
+/// and size are part of the key, so edits make old entries unreachable and
+/// they age out of the LRU instead of needing explicit invalidation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub path: String,
+    pub projection: String,
+    pub modified_nanos: u128,
+    pub size: u64,
+    /// Projection options, sorted so equivalent queries share an entry.
+    pub options: BTreeMap<String, String>,
+}
+
+/// A serialized projection output with the response details it asked for.
+#[derive(Debug, Clone)]
+pub struct CachedOutput {
+    pub value: Arc<serde_json::Value>,
+    pub meta: ProjectionMeta,
+}
+
+/// Bounded in-memory cache of serialized projection outputs.
+pub struct ProjectionCache {
+    /// `None` when caching is disabled (capacity 0).
+    entries: Option<Mutex<LruCache<CacheKey, CachedOutput>>>,
+}
+
+impl ProjectionCache {
+    /// Create a cache holding up to `capacity` outputs; 0 disables caching.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: NonZeroUsize::new(capacity).map(|cap| Mutex::new(LruCache::new(cap))),
+        }
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CachedOutput> {
+        self.entries.as_ref()?.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: CacheKey, value: CachedOutput) {
+        if let Some(entries) = &self.entries {
+            entries.lock().unwrap().put(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str, size: u64) -> CacheKey {
+        CacheKey {
+            path: path.to_string(),
+            projection: "text.raw".to_string(),
+            modified_nanos: 1,
+            size,
+            options: BTreeMap::new(),
+        }
+    }
+
+    fn output(text: &str) -> CachedOutput {
+        CachedOutput {
+            value: Arc::new(serde_json::json!(text)),
+            meta: ProjectionMeta::default(),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ProjectionCache::new(2);
+        cache.insert(key("a", 1), output("a"));
+        cache.insert(key("b", 1), output("b"));
+        assert!(cache.get(&key("a", 1)).is_some());
+        cache.insert(key("c", 1), output("c"));
+        assert!(cache.get(&key("b", 1)).is_none());
+        assert!(cache.get(&key("a", 1)).is_some());
+        // A changed size is a different key
+        assert!(cache.get(&key("a", 2)).is_none());
+    }
+
+    #[test]
+    fn zero_capacity_disables() {
+        let cache = ProjectionCache::new(0);
+        cache.insert(key("a", 1), output("a"));
+        assert!(cache.get(&key("a", 1)).is_none());
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::projection::ResourceCategory;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("cannot read config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid config: {0}")]
+    Parse(#[from] toml::de::Error),
+}
+
+/// Server configuration loaded from a TOML file. Every section is optional;
+/// an empty file behaves like no config at all.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub projections: ProjectionsConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ProjectionsConfig {
+    /// Projection ids to register. `None` keeps every built-in.
+    pub enabled: Option<Vec<String>>,
+    /// Per-extension confidence overrides, e.g. open `.txt` as Markdown.
+    #[serde(default)]
+    pub overrides: Vec<ConfidenceOverride>,
+    /// Largest file, in bytes, each listed projection may open, e.g. a
+    /// higher ceiling for `text.raw` than the server-wide default. 0 lifts
+    /// the limit.
+    #[serde(default)]
+    pub max_bytes: HashMap<String, u64>,
+    /// Projections that run an external program and show its output.
+    #[serde(default)]
+    pub commands: Vec<CommandConfig>,
+    /// Projections to try, in order, when the best one fails, keyed by
+    /// extension or `*` for every file. Without a chain the other matching
+    /// projections are tried by descending confidence.
+    #[serde(default)]
+    pub fallbacks: HashMap<String, Vec<String>>,
+    /// Projection to open by default for each kind of resource (`dir`,
+    /// `text`, `image` or `binary`), when it can show the resource at all.
+    /// Otherwise the most confident projection is used.
+    #[serde(default)]
+    pub defaults: HashMap<ResourceCategory, String>,
+}
+
+/// An external program registered as a projection. The command is run
+/// directly, never through a shell, so the file path reaches it as a single
+/// argument whatever characters it contains.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandConfig {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Icon identifier for the UI, as returned by `Projection::icon`.
+    pub icon: Option<String>,
+    /// Extensions (lowercase, without dot) the command is offered for.
+    pub extensions: Vec<String>,
+    /// Program and arguments; `{path}` in any argument is replaced by the
+    /// file's absolute path.
+    pub command: Vec<String>,
+    /// Seconds before the program is killed.
+    #[serde(default = "default_command_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_command_confidence")]
+    pub confidence: f32,
+}
+
+fn default_command_timeout() -> u64 {
+    10
+}
+
+fn default_command_confidence() -> f32 {
+    0.5
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfidenceOverride {
+    pub extension: String,
+    pub projection: String,
+    pub confidence: f32,
+    /// Apply even to files the projection doesn't claim on its own.
+    #[serde(default)]
+    pub force: bool,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_enabled_projections() {
+        let config: Config = toml::from_str(
+            r#"
+            [projections]
+            enabled = ["dir.list", "text.raw"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.projections.enabled.as_deref(),
+            Some(&["dir.list".to_string(), "text.raw".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn parse_overrides() {
+        let config: Config = toml::from_str(
+            r#"
+            [[projections.overrides]]
+            extension = "txt"
+            projection = "text.markdown"
+            confidence = 1.0
+            force = true
+            "#,
+        )
+        .unwrap();
+        let o = &config.projections.overrides[0];
+        assert_eq!(
+            (o.extension.as_str(), o.projection.as_str()),
+            ("txt", "text.markdown")
+        );
+        assert!(o.force);
+    }
+
+    #[test]
+    fn parse_max_bytes() {
+        let config: Config = toml::from_str(
+            r#"
+            [projections.max_bytes]
+            "text.raw" = 1048576
+            "image.preview" = 0
+            "#,
+        )
+        .unwrap();
+        let limits = &config.projections.max_bytes;
+        assert_eq!(limits["text.raw"], 1_048_576);
+        assert_eq!(limits["image.preview"], 0);
+    }
+
+    #[test]
+    fn parse_commands() {
+        let config: Config = toml::from_str(
+            r#"
+            [[projections.commands]]
+            id = "cmd.objdump"
+            name = "Disassembly"
+            extensions = ["o", "so"]
+            command = ["objdump", "-d", "{path}"]
+            "#,
+        )
+        .unwrap();
+        let c = &config.projections.commands[0];
+        assert_eq!(c.command, ["objdump", "-d", "{path}"]);
+        assert_eq!((c.timeout_secs, c.confidence), (10, 0.5));
+    }
+
+    #[test]
+    fn parse_fallbacks() {
+        let config: Config = toml::from_str(
+            r#"
+            [projections.fallbacks]
+            "*" = ["text.raw", "hex.view"]
+            dat = ["hex.view"]
+            "#,
+        )
+        .unwrap();
+        let fallbacks = &config.projections.fallbacks;
+        assert_eq!(fallbacks["*"], ["text.raw", "hex.view"]);
+        assert_eq!(fallbacks["dat"], ["hex.view"]);
+    }
+
+    #[test]
+    fn empty_config_enables_everything() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.projections.enabled.is_none());
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use ignore::WalkState;
+
+use crate::watch::{ChangeEvent, ChangeKind};
+use crate::workspace::{SearchHit, SearchResults, Workspace};
+
+/// Directory names never indexed: version control internals and trash.
+const SKIPPED_DIRS: &[&str] = &[".git", ".hg", ".svn", ".Trash", ".Trashes", "$RECYCLE.BIN"];
+
+/// Paths indexed before the build gives up and marks the index truncated.
+pub const DEFAULT_MAX_ENTRIES: usize = 200_000;
+
+#[derive(Debug, Clone)]
+struct Indexed {
+    /// Lowercased path, what queries are matched against.
+    folded: String,
+    is_dir: bool,
+    size: u64,
+}
+
+/// In-memory index of every path in the workspace, for filename search
+/// without walking the tree. Built in the background on startup, skipping
+/// what git ignores and `SKIPPED_DIRS`, then kept current from filesystem
+/// events. Until the first build finishes, searches fall back to a walk.
+pub struct PathIndex {
+    paths: RwLock<BTreeMap<String, Indexed>>,
+    max_entries: usize,
+    ready: AtomicBool,
+    /// The build stopped at `max_entries`, so some paths are missing.
+    truncated: AtomicBool,
+}
+
+impl PathIndex {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            paths: RwLock::default(),
+            max_entries,
+            ready: AtomicBool::new(false),
+            truncated: AtomicBool::new(false),
+        }
+    }
+
+    /// Start indexing `workspace` in the background and follow its changes
+    /// for as long as the runtime lives. Changes are watched from before
+    /// the build starts, so none made during it are lost.
+    pub fn spawn(workspace: Workspace, max_entries: usize) -> crate::workspace::Result<Arc<Self>> {
+        let index = Arc::new(Self::new(max_entries));
+        let mut watch = crate::watch::watch(&workspace, Path::new(""), true)?;
+        tokio::spawn({
+            let index = index.clone();
+            async move {
+                let started = std::time::Instant::now();
+                let build = tokio::task::spawn_blocking({
+                    let (index, workspace) = (index.clone(), workspace.clone());
+                    move || index.build(&workspace)
+                });
+                if let Err(e) = build.await {
+                    tracing::warn!("index build failed: {e}");
+                    return;
+                }
+                tracing::info!(
+                    "indexed {} paths in {:?}{}",
+                    index.len(),
+                    started.elapsed(),
+                    if index.truncated.load(Ordering::Relaxed) {
+                        " (truncated)"
+                    } else {
+                        ""
+                    }
+                );
+                while let Some(change) = watch.next().await {
+                    let (index, workspace) = (index.clone(), workspace.clone());
+                    let _ =
+                        tokio::task::spawn_blocking(move || index.apply(&workspace, &change)).await;
+                }
+            }
+        });
+        Ok(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// Index the whole workspace, replacing what was there, and mark the
+    /// index ready. Blocks; the walk runs on several threads.
+    pub fn build(&self, workspace: &Workspace) {
+        let mut roots = vec![workspace.root().to_path_buf()];
+        roots.extend(workspace.mounts().map(|(_, root)| root.to_path_buf()));
+        let (found, truncated) = self.walk(workspace, &roots, self.max_entries);
+        *self.paths.write().unwrap() = found.into_iter().collect();
+        self.truncated.store(truncated, Ordering::Relaxed);
+        self.ready.store(true, Ordering::Release);
+    }
+
+    /// Paths under `roots` (not the roots themselves) that aren't ignored,
+    /// up to `limit` of them, and whether the limit cut the walk short.
+    fn walk(
+        &self,
+        workspace: &Workspace,
+        roots: &[PathBuf],
+        limit: usize,
+    ) -> (Vec<(String, Indexed)>, bool) {
+        let Some((first, rest)) = roots.split_first() else {
+            return (Vec::new(), false);
+        };
+        let mut builder = ignore::WalkBuilder::new(first);
+        for root in rest {
+            builder.add(root);
+        }
+        let root = workspace.root().to_path_buf();
+        let mount_names: Vec<String> = workspace.mounts().map(|(n, _)| n.to_string()).collect();
+        builder
+            .hidden(false)
+            .ignore(false)
+            .git_global(false)
+            .require_git(true)
+            .filter_entry(move |entry| {
+                let name = entry.file_name().to_string_lossy();
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                // Mounts hide real entries of the same name at the top
+                let shadowed = entry.path().parent() == Some(root.as_path())
+                    && mount_names.iter().any(|m| *m == name);
+                !(shadowed || is_dir && SKIPPED_DIRS.contains(&name.as_ref()))
+            });
+
+        let found = Mutex::new(Vec::new());
+        let count = AtomicUsize::new(0);
+        let truncated = AtomicBool::new(false);
+        builder.build_parallel().run(|| {
+            Box::new(|entry| {
+                let entry = match entry {
+                    Ok(entry) if entry.depth() > 0 => entry,
+                    Ok(_) => return WalkState::Continue,
+                    Err(e) => {
+                        tracing::debug!("index: skipping: {e}");
+                        return WalkState::Continue;
+                    }
+                };
+                if count.fetch_add(1, Ordering::Relaxed) >= limit {
+                    truncated.store(true, Ordering::Relaxed);
+                    return WalkState::Quit;
+                }
+                if let Some(indexed) = Self::entry(workspace, entry.path()) {
+                    found.lock().unwrap().push(indexed);
+                }
+                WalkState::Continue
+            })
+        });
+        (found.into_inner().unwrap(), truncated.into_inner())
+    }
+
+    /// Index entry for the file at `abs`. `None` if it's gone or is a link
+    /// leading out of the workspace.
+    fn entry(workspace: &Workspace, abs: &Path) -> Option<(String, Indexed)> {
+        let meta = std::fs::symlink_metadata(abs).ok()?;
+        let path = workspace.relative(abs);
+        if meta.file_type().is_symlink() && workspace.resolve(&path).is_err() {
+            return None;
+        }
+        let indexed = Indexed {
+            folded: path.to_lowercase(),
+            is_dir: meta.is_dir(),
+            size: meta.len(),
+        };
+        Some((path, indexed))
+    }
+
+    /// Update the index for one filesystem change.
+    pub fn apply(&self, workspace: &Workspace, change: &ChangeEvent) {
+        let path = change.path.as_str();
+        if path.is_empty() || path.split('/').any(|c| SKIPPED_DIRS.contains(&c)) {
+            return;
+        }
+        let abs = workspace.resolve(path).ok().filter(|abs| abs.exists());
+        let Some(abs) = abs else {
+            self.remove(path);
+            return;
+        };
+        if change.kind == ChangeKind::Modified {
+            if let Some((path, indexed)) = Self::entry(workspace, &abs) {
+                let mut paths = self.paths.write().unwrap();
+                if let Some(existing) = paths.get_mut(&path) {
+                    *existing = indexed;
+                }
+            }
+            return;
+        }
+        // New here: index it, and what's below a directory, if its parent
+        // is indexed and doesn't ignore it
+        let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+        if !parent.is_empty() && !self.paths.read().unwrap().contains_key(parent) {
+            return;
+        }
+        let Some(parent_abs) = abs.parent() else {
+            return;
+        };
+        let siblings = self.walk_one_level(workspace, parent_abs);
+        if !siblings.iter().any(|(p, _)| p == path) {
+            return;
+        }
+        let mut added: Vec<(String, Indexed)> =
+            siblings.into_iter().filter(|(p, _)| p == path).collect();
+        if abs.is_dir() {
+            let room = self.max_entries.saturating_sub(self.len());
+            let (below, truncated) = self.walk(workspace, &[abs], room);
+            added.extend(below);
+            if truncated {
+                self.truncated.store(true, Ordering::Relaxed);
+            }
+        }
+        let mut paths = self.paths.write().unwrap();
+        paths.extend(added);
+    }
+
+    /// Unignored direct children of `dir`.
+    fn walk_one_level(&self, workspace: &Workspace, dir: &Path) -> Vec<(String, Indexed)> {
+        ignore::WalkBuilder::new(dir)
+            .max_depth(Some(1))
+            .hidden(false)
+            .ignore(false)
+            .git_global(false)
+            .require_git(true)
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.depth() == 1)
+            .filter_map(|entry| Self::entry(workspace, entry.path()))
+            .collect()
+    }
+
+    /// Drop `path` and everything below it.
+    fn remove(&self, path: &str) {
+        let mut paths = self.paths.write().unwrap();
+        paths.remove(path);
+        let prefix = format!("{path}/");
+        let below: Vec<String> = paths
+            .range(prefix.clone()..)
+            .take_while(|(p, _)| p.starts_with(&prefix))
+            .map(|(p, _)| p.clone())
+            .collect();
+        for p in below {
+            paths.remove(&p);
+        }
+    }
+
+    /// Paths containing `query`, ignoring case, in path order. `None` until
+    /// the index is built, when the caller should walk instead.
+    pub fn search(&self, query: &str, limit: usize) -> Option<SearchResults> {
+        if !self.is_ready() {
+            return None;
+        }
+        let needle = query.to_lowercase();
+        let paths = self.paths.read().unwrap();
+        let mut matches = paths.iter().filter(|(_, e)| e.folded.contains(&needle));
+        let hits: Vec<SearchHit> = matches
+            .by_ref()
+            .take(limit)
+            .map(|(path, e)| SearchHit {
+                path: path.clone(),
+                is_dir: e.is_dir,
+                size: e.size,
+            })
+            .collect();
+        let more = matches.next().is_some();
+        Some(SearchResults {
+            hits,
+            truncated: more || self.truncated.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(results: SearchResults) -> Vec<String> {
+        results.hits.into_iter().map(|h| h.path).collect()
+    }
+
+    fn setup() -> (tempfile::TempDir, Workspace) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/deep")).unwrap();
+        std::fs::create_dir_all(root.join(".git/objects")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::create_dir_all(root.join(".Trash")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/Main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("src/deep/main_test.rs"), "").unwrap();
+        std::fs::write(root.join(".git/objects/main"), "").unwrap();
+        std::fs::write(root.join("target/main.o"), "").unwrap();
+        std::fs::write(root.join(".Trash/main.rs"), "").unwrap();
+        let ws = Workspace::new(root).unwrap();
+        (dir, ws)
+    }
+
+    #[test]
+    fn build_skips_ignored_and_trash() {
+        let (_dir, ws) = setup();
+        let index = PathIndex::new(DEFAULT_MAX_ENTRIES);
+        assert!(index.search("main", 10).is_none());
+        index.build(&ws);
+
+        let results = index.search("MAIN", 10).unwrap();
+        assert!(!results.truncated);
+        assert_eq!(paths(results), ["src/Main.rs", "src/deep/main_test.rs"]);
+        assert_eq!(paths(index.search("main", 1).unwrap()), ["src/Main.rs"]);
+        assert!(index.search("main", 1).unwrap().truncated);
+        assert_eq!(index.search("Main.rs", 10).unwrap().hits[0].size, 12);
+    }
+
+    #[test]
+    fn bounded_builds_are_truncated() {
+        let (_dir, ws) = setup();
+        let index = PathIndex::new(2);
+        index.build(&ws);
+        assert!(index.len() <= 2);
+        assert!(index.search("zzz", 10).unwrap().truncated);
+    }
+
+    #[test]
+    fn changes_update_the_index() {
+        let (dir, ws) = setup();
+        let root = dir.path();
+        let index = PathIndex::new(DEFAULT_MAX_ENTRIES);
+        index.build(&ws);
+        let change = |kind, path: &str| ChangeEvent {
+            kind,
+            path: path.to_string(),
+        };
+
+        std::fs::create_dir_all(root.join("docs/guide")).unwrap();
+        std::fs::write(root.join("docs/guide/main.md"), "").unwrap();
+        index.apply(&ws, &change(ChangeKind::Created, "docs"));
+        std::fs::write(root.join("target/main2.o"), "").unwrap();
+        index.apply(&ws, &change(ChangeKind::Created, "target/main2.o"));
+        assert_eq!(
+            paths(index.search("main", 10).unwrap()),
+            ["docs/guide/main.md", "src/Main.rs", "src/deep/main_test.rs"]
+        );
+
+        std::fs::write(root.join("src/Main.rs"), "fn main() { loop {} }").unwrap();
+        index.apply(&ws, &change(ChangeKind::Modified, "src/Main.rs"));
+        assert_eq!(index.search("Main.rs", 1).unwrap().hits[0].size, 21);
+
+        std::fs::remove_dir_all(root.join("src")).unwrap();
+        index.apply(&ws, &change(ChangeKind::Removed, "src"));
+        assert_eq!(
+            paths(index.search("main", 10).unwrap()),
+            ["docs/guide/main.md"]
+        );
+    }
+}
@@ -1,5 +1,13 @@
 pub mod api;
+pub mod archive;
+pub mod cache;
+pub mod config;
+pub mod index;
 pub mod projection;
 pub mod projections;
 pub mod registry;
+pub mod store;
+pub mod thumbnail;
+pub mod upload;
+pub mod watch;
 pub mod workspace;
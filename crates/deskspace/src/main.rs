@@ -1,34 +1,270 @@
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+use clap::Parser;
 use tower_http::services::ServeDir;
 use tracing_subscriber::EnvFilter;
 
-use deskspace::api::{self, AppState};
-use deskspace::projections::{dir_list, image_preview, text_markdown, text_raw};
+use deskspace::api::{self, AppState, RateLimiter};
+use deskspace::cache::ProjectionCache;
+use deskspace::config::Config;
+use deskspace::index::{self, PathIndex};
+use deskspace::projections::{
+    archive_list, audio_preview, command, config_view, csv_table, diff_view, dir_list, dir_tree,
+    env_view, font_preview, hex_view, html_preview, image_preview, json_view, notebook_view,
+    pdf_preview, sql_dump, sqlite_browse, svg_preview, text_markdown, text_raw, video_preview,
+};
 use deskspace::registry::ProjectionRegistry;
+use deskspace::thumbnail::ThumbnailCache;
+use deskspace::upload::{self, UploadStaging};
 use deskspace::workspace::Workspace;
 
+/// Default port when neither --addr nor --port is given.
+const DEFAULT_PORT: u16 = 3000;
+
+/// How long in-flight requests get to finish after a shutdown signal.
+/// Watches and WebSockets never finish on their own, so they are cut off
+/// when this runs out.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Parser)]
+#[command(version, about = "Unified file workspace server")]
+struct Args {
+    /// Workspace root directory.
+    #[arg(default_value = ".")]
+    root: PathBuf,
+
+    /// Mount another directory under NAME at the top of the workspace,
+    /// e.g. --mount docs=/srv/docs. Repeatable; NAME must not already be
+    /// taken by an entry in the root.
+    #[arg(long = "mount", value_name = "NAME=PATH", value_parser = parse_mount)]
+    mounts: Vec<(String, PathBuf)>,
+
+    /// Address to bind, e.g. 127.0.0.1:3000 or 0.0.0.0:8080.
+    #[arg(long, env = "DESKSPACE_ADDR", conflicts_with = "port")]
+    addr: Option<SocketAddr>,
+
+    /// Port to bind on 127.0.0.1.
+    #[arg(long, env = "DESKSPACE_PORT")]
+    port: Option<u16>,
+
+    /// TOML config file (enabled projections, confidence overrides).
+    #[arg(long, env = "DESKSPACE_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Number of file projection outputs kept in memory (0 disables).
+    #[arg(long, env = "DESKSPACE_CACHE_SIZE", default_value_t = 256)]
+    cache_size: usize,
+
+    /// Reject all mutating requests (writes, uploads, deletes, moves).
+    #[arg(long, env = "DESKSPACE_READ_ONLY")]
+    read_only: bool,
+
+    /// Reject mutating requests that carry no Origin header unless the
+    /// browser marks them same-origin via Sec-Fetch-Site.
+    #[arg(long, env = "DESKSPACE_STRICT_CSRF")]
+    strict_csrf: bool,
+
+    /// Largest file, in bytes, opened by projections that load it whole
+    /// (0 disables the limit).
+    #[arg(long, env = "DESKSPACE_MAX_PROJECTION_BYTES", default_value_t = 16 * 1024 * 1024)]
+    max_projection_bytes: u64,
+
+    /// Seconds a projection may run before it is abandoned (0 waits
+    /// indefinitely).
+    #[arg(long, env = "DESKSPACE_PROJECTION_TIMEOUT", default_value_t = 30)]
+    projection_timeout: u64,
+
+    /// Require this token on every API request and on `/metrics`, as
+    /// `Authorization: Bearer` or the UI's cookie. The UI's static files and
+    /// the health probes stay public.
+    #[arg(long, env = "DESKSPACE_TOKEN", hide_env_values = true)]
+    auth_token: Option<String>,
+
+    /// Mutating requests allowed per second, across all clients (0
+    /// disables the limit). Excess requests get 429.
+    #[arg(long, env = "DESKSPACE_WRITE_RATE", default_value_t = 0.0)]
+    write_rate: f64,
+
+    /// Mutating requests allowed in a burst above --write-rate.
+    #[arg(long, env = "DESKSPACE_WRITE_BURST", default_value_t = 20)]
+    write_burst: u32,
+
+    /// Index workspace paths in the background for instant filename search.
+    #[arg(long, env = "DESKSPACE_INDEX")]
+    index: bool,
+
+    /// Directory chunked uploads are staged in [default: <temp dir>/deskspace-uploads].
+    #[arg(long, env = "DESKSPACE_UPLOAD_DIR")]
+    upload_dir: Option<PathBuf>,
+
+    /// Directory for generated thumbnails [default: <temp dir>/deskspace-thumbnails].
+    #[arg(long, env = "DESKSPACE_THUMBNAIL_DIR")]
+    thumbnail_dir: Option<PathBuf>,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
         .init();
 
-    let root = std::env::args().nth(1).unwrap_or_else(|| ".".to_string());
+    let args = Args::parse();
+    let root = args.root;
+
+    let config = match &args.config {
+        Some(path) => {
+            tracing::info!("loading config: {}", path.display());
+            Config::load(path)?
+        }
+        None => Config::default(),
+    };
 
-    let workspace = Workspace::new(&root)?;
+    let mut workspace = Workspace::new(&root)?;
     tracing::info!("serving workspace: {}", workspace.root().display());
+    for (name, path) in args.mounts {
+        workspace = workspace
+            .with_mount(&name, &path)
+            .map_err(|e| anyhow::anyhow!("cannot mount {}: {e}", path.display()))?;
+        tracing::info!("mounted {} as {name}/", path.display());
+    }
 
     let mut registry = ProjectionRegistry::new();
     registry.register(Arc::new(dir_list::DirList));
+    registry.register(Arc::new(dir_tree::DirTree));
     registry.register(Arc::new(text_raw::TextRaw));
     registry.register(Arc::new(text_markdown::TextMarkdown));
+    registry.register(Arc::new(html_preview::HtmlPreview));
     registry.register(Arc::new(image_preview::ImagePreview));
+    registry.register(Arc::new(svg_preview::SvgPreview));
+    registry.register(Arc::new(sql_dump::SqlDump));
+    registry.register(Arc::new(json_view::JsonView));
+    registry.register(Arc::new(config_view::ConfigView));
+    registry.register(Arc::new(env_view::EnvView));
+    registry.register(Arc::new(csv_table::CsvTable));
+    registry.register(Arc::new(hex_view::HexView));
+    registry.register(Arc::new(archive_list::ArchiveList));
+    registry.register(Arc::new(pdf_preview::PdfPreview));
+    registry.register(Arc::new(audio_preview::AudioPreview));
+    registry.register(Arc::new(video_preview::VideoPreview));
+    registry.register(Arc::new(sqlite_browse::SqliteBrowse));
+    registry.register(Arc::new(notebook_view::NotebookView));
+    registry.register(Arc::new(font_preview::FontPreview));
+    registry.register(Arc::new(diff_view::DiffView));
+    #[cfg(feature = "highlight")]
+    registry.register(Arc::new(
+        deskspace::projections::syntax_highlight::SyntaxHighlight,
+    ));
+
+    for command in &config.projections.commands {
+        if registry.get(&command.id).is_some() {
+            tracing::warn!(
+                "command projection {:?} clashes with a built-in, ignoring",
+                command.id
+            );
+            continue;
+        }
+        match command::CommandProjection::new(command.clone()) {
+            Ok(projection) => {
+                tracing::info!("registered command projection {:?}", command.id);
+                registry.register(Arc::new(projection));
+            }
+            Err(e) => tracing::warn!("{e}, ignoring"),
+        }
+    }
+
+    if let Some(enabled) = &config.projections.enabled {
+        for id in enabled {
+            if registry.get(id).is_none() {
+                tracing::warn!("config enables unknown projection {id:?}, ignoring");
+            }
+        }
+        registry.retain(|id| enabled.iter().any(|e| e == id));
+    }
+    for o in &config.projections.overrides {
+        if registry.get(&o.projection).is_none() {
+            tracing::warn!(
+                "override for .{} names unavailable projection {:?}, ignoring",
+                o.extension,
+                o.projection
+            );
+            continue;
+        }
+        registry.register_override(&o.extension, &o.projection, o.confidence, o.force);
+    }
+
+    for (extension, chain) in &config.projections.fallbacks {
+        for id in chain {
+            if registry.get(id).is_none() {
+                tracing::warn!(
+                    "fallback chain for {extension:?} names unavailable projection {id:?}, ignoring"
+                );
+            }
+        }
+        registry.register_fallbacks(extension, chain.clone());
+    }
+
+    registry.register_defaults(&config.projections.defaults);
+
+    for id in config.projections.max_bytes.keys() {
+        if registry.get(id).is_none() {
+            tracing::warn!("size limit names unavailable projection {id:?}, ignoring");
+        }
+    }
+
+    let auth_token = args.auth_token.filter(|t| !t.is_empty());
+    if auth_token.is_some() {
+        tracing::info!("API requests must present the access token");
+    }
+    if args.read_only {
+        tracing::info!("read-only mode: mutating requests will be rejected");
+    }
+    let write_limit = (args.write_rate > 0.0).then(|| {
+        tracing::info!(
+            "limiting writes to {}/s, bursts of {}",
+            args.write_rate,
+            args.write_burst
+        );
+        RateLimiter::new(args.write_rate, args.write_burst)
+    });
+    let search_index = if args.index {
+        Some(PathIndex::spawn(
+            workspace.clone(),
+            index::DEFAULT_MAX_ENTRIES,
+        )?)
+    } else {
+        None
+    };
+    let uploads = Arc::new(UploadStaging::new(
+        args.upload_dir
+            .unwrap_or_else(|| std::env::temp_dir().join("deskspace-uploads")),
+        upload::DEFAULT_IDLE_TIMEOUT,
+    ));
+    uploads.collect_every(upload::COLLECT_INTERVAL);
 
     let state = Arc::new(AppState {
+        store: Arc::new(workspace.clone()),
         workspace,
         registry,
+        cache: ProjectionCache::new(args.cache_size),
+        read_only: args.read_only,
+        strict_csrf: args.strict_csrf,
+        max_projection_bytes: args.max_projection_bytes,
+        projection_size_limits: config.projections.max_bytes,
+        thumbnails: ThumbnailCache::new(
+            args.thumbnail_dir
+                .unwrap_or_else(|| std::env::temp_dir().join("deskspace-thumbnails")),
+        ),
+        projection_timeout: (args.projection_timeout > 0)
+            .then(|| Duration::from_secs(args.projection_timeout)),
+        auth_token,
+        write_limit,
+        search_index,
+        uploads,
+        traversal_rejections: Default::default(),
+        metrics: Some(api::metrics::install()?),
     });
 
     // UI is served from ui/ directory relative to the binary's working directory
@@ -49,11 +285,84 @@ async fn main() -> anyhow::Result<()> {
         )
         .fallback_service(ServeDir::new(&ui_dir));
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    let addr = args
+        .addr
+        .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], args.port.unwrap_or(DEFAULT_PORT))));
+    if !addr.ip().is_loopback() {
+        tracing::warn!(
+            "binding to non-loopback address {addr}: the workspace is reachable from the network"
+        );
+        if !args.strict_csrf {
+            tracing::warn!(
+                "mutating requests without an Origin header are accepted; \
+                 consider --strict-csrf"
+            );
+        }
+    }
     tracing::info!("listening on http://{addr}");
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    let stopping = Arc::new(tokio::sync::Notify::new());
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let stopping = stopping.clone();
+        async move {
+            shutdown_signal().await;
+            tracing::info!("shutting down, waiting for in-flight requests");
+            stopping.notify_one();
+        }
+    });
+    tokio::select! {
+        result = server => result?,
+        () = async {
+            stopping.notified().await;
+            tokio::time::sleep(SHUTDOWN_GRACE).await;
+        } => {
+            tracing::warn!(
+                "requests still open after {}s, closing them",
+                SHUTDOWN_GRACE.as_secs()
+            );
+        }
+    }
+    tracing::info!("shut down");
 
     Ok(())
 }
+
+fn parse_mount(arg: &str) -> Result<(String, PathBuf), String> {
+    match arg.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((name.to_owned(), PathBuf::from(path)))
+        }
+        _ => Err(format!("expected NAME=PATH, got {arg:?}")),
+    }
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix (what container runtimes send).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("cannot listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("cannot listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
@@ -1,7 +1,13 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::time::Duration;
+
 use async_trait::async_trait;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::workspace::{Workspace, WorkspaceError};
+use crate::store::FileStore;
+use crate::workspace::WorkspaceError;
 
 /// A resource that a projection operates on.
 #[derive(Debug, Clone)]
@@ -12,6 +18,10 @@ pub struct Resource {
     pub is_dir: bool,
     /// File extension (lowercase, without dot), if any.
     pub extension: Option<String>,
+    /// Content type sniffed from the file's leading bytes, when they match a
+    /// known signature. Lets projections claim extensionless or misnamed
+    /// files.
+    pub mime_type: Option<String>,
 }
 
 impl Resource {
@@ -27,8 +37,94 @@ impl Resource {
             path,
             is_dir,
             extension,
+            mime_type: None,
+        }
+    }
+
+    /// Last component of the path, e.g. `Makefile` for `src/Makefile`.
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+
+    pub fn with_mime_type(mut self, mime_type: Option<String>) -> Self {
+        self.mime_type = mime_type;
+        self
+    }
+
+    /// Whether sniffing matched a signature that isn't a `text/` type.
+    pub fn sniffed_binary(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|m| !m.starts_with("text/"))
+    }
+
+    /// Whether the sniffed content type is one of `types`.
+    pub fn mime_is(&self, types: &[&str]) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|m| types.contains(&m))
+    }
+
+    /// Broad kind of resource, for server-wide default projections. Images
+    /// are recognised by sniffed content or extension; anything else that
+    /// sniffs as binary is `Binary`, and the rest is taken for text.
+    pub fn category(&self) -> ResourceCategory {
+        let is_image = |m: &str| m.starts_with("image/");
+        if self.is_dir {
+            ResourceCategory::Dir
+        } else if self.mime_type.as_deref().is_some_and(is_image)
+            || mime_guess::from_path(&self.path)
+                .first_raw()
+                .is_some_and(is_image)
+        {
+            ResourceCategory::Image
+        } else if self.sniffed_binary() {
+            ResourceCategory::Binary
+        } else {
+            ResourceCategory::Text
+        }
+    }
+}
+
+/// Broad kind of resource, see [`Resource::category`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceCategory {
+    Dir,
+    Text,
+    Image,
+    Binary,
+}
+
+/// URL of `path` on the raw endpoint, carrying the file's current version
+/// as `?v=` so the browser can cache it indefinitely. Falls back to the bare
+/// URL when the file can't be stat'ed.
+pub async fn raw_url(workspace: &dyn FileStore, path: &str) -> String {
+    let version = match workspace.stat(std::path::Path::new(path)).await {
+        Ok(stat) => stat.version(),
+        Err(_) => None,
+    };
+    versioned_raw_url(path, version.as_deref())
+}
+
+/// URL of `path` on the raw endpoint with `?v=version` if given. Each
+/// segment is percent-encoded, so names with `?`, `#`, `%` or spaces still
+/// point at the file.
+pub fn versioned_raw_url(path: &str, version: Option<&str>) -> String {
+    let mut url = String::from("/api/files/raw/");
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{byte:02X}")),
         }
     }
+    if let Some(version) = version {
+        url.push_str("?v=");
+        url.push_str(version);
+    }
+    url
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -37,12 +133,117 @@ pub enum ProjectionError {
     Workspace(#[from] WorkspaceError),
     #[error("unsupported resource")]
     Unsupported,
+    #[error("invalid option: {0}")]
+    InvalidOption(String),
     #[error("{0}")]
     Other(String),
 }
 
 pub type Result<T> = std::result::Result<T, ProjectionError>;
 
+/// Request-scoped options passed to [`Projection::project`], taken from the
+/// query string. Projections read the keys they understand and ignore the rest.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectionRequest {
+    options: HashMap<String, String>,
+}
+
+impl ProjectionRequest {
+    pub fn new(options: HashMap<String, String>) -> Self {
+        Self { options }
+    }
+
+    /// Raw value of an option, if present.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// Parse an option, failing with `InvalidOption` if it is present but
+    /// malformed.
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<Option<T>> {
+        self.get(key)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| ProjectionError::InvalidOption(format!("{key}={v}")))
+            })
+            .transpose()
+    }
+
+    /// Parse a boolean option, accepting `true/false`, `1/0`, and `yes/no`.
+    pub fn flag(&self, key: &str) -> Result<Option<bool>> {
+        self.get(key)
+            .map(|v| match v {
+                "true" | "1" | "yes" => Ok(true),
+                "false" | "0" | "no" => Ok(false),
+                _ => Err(ProjectionError::InvalidOption(format!("{key}={v}"))),
+            })
+            .transpose()
+    }
+
+    /// Index of the first item to return, for paginated outputs.
+    pub fn offset(&self) -> Result<Option<usize>> {
+        self.parse("offset")
+    }
+
+    /// Maximum number of items to return, for paginated outputs.
+    pub fn limit(&self) -> Result<Option<usize>> {
+        self.parse("limit")
+    }
+
+    /// Rendered form the client wants instead of the JSON envelope.
+    pub fn format(&self) -> Result<Option<RenderFormat>> {
+        self.parse("format")
+    }
+}
+
+/// A form a projection's output can be served in directly, instead of the
+/// JSON envelope, for embedding in other tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderFormat {
+    /// An HTML fragment, e.g. rendered Markdown or highlighted code.
+    Html,
+}
+
+impl RenderFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    /// This form of a projection output, if the output has one. Outputs
+    /// are matched by `type`, as they're cached serialized.
+    pub fn render(self, output: &serde_json::Value) -> Option<&str> {
+        let field = match (self, output["type"].as_str()?) {
+            (Self::Html, "Markdown" | "HighlightedText") => "html",
+            (Self::Html, "Html") => "safe_html",
+            (Self::Html, "Svg") => "safe_markup",
+            _ => return None,
+        };
+        output[field].as_str()
+    }
+}
+
+impl std::fmt::Display for RenderFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Html => "html",
+        })
+    }
+}
+
+impl FromStr for RenderFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "html" => Ok(Self::Html),
+            _ => Err(()),
+        }
+    }
+}
+
 #[async_trait]
 pub trait Projection: Send + Sync {
     /// Unique identifier for this projection (e.g. "dir.list").
@@ -51,46 +252,560 @@ pub trait Projection: Send + Sync {
     /// Human-readable name.
     fn name(&self) -> &str;
 
+    /// One-line summary shown in the projection picker.
+    fn description(&self) -> Option<&str> {
+        None
+    }
+
+    /// Icon identifier (`folder`, `text`, `image`, ...) the UI maps to an
+    /// icon of its choosing.
+    fn icon(&self) -> Option<&str> {
+        None
+    }
+
     /// How well this projection handles the given resource. 0.0 = not at all, 1.0 = perfect.
     fn confidence(&self, resource: &Resource) -> f32;
 
+    /// Forms the output can be served in besides JSON, via `?format=` or
+    /// `Accept`. A projection declaring one produces it whenever the
+    /// `format` option asks for it.
+    fn formats(&self) -> &[RenderFormat] {
+        &[]
+    }
+
+    /// Whether `project` loads the entire file into memory. Such
+    /// projections are refused for files over the server's size limit;
+    /// ones that stream or read a bounded window should return false.
+    fn reads_whole_file(&self) -> bool {
+        true
+    }
+
     /// Produce the projection output for the given resource.
-    async fn project(&self, resource: &Resource, workspace: &Workspace)
-        -> Result<ProjectionOutput>;
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput>;
+
+    /// `project`, plus headers and caching advice for the HTTP response.
+    /// Projections that need to set them override this; the default adds
+    /// nothing.
+    async fn project_with_meta(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
+    ) -> Result<(ProjectionOutput, ProjectionMeta)> {
+        let output = self.project(resource, workspace, request).await?;
+        Ok((output, ProjectionMeta::default()))
+    }
+}
+
+/// Response details a projection asks for alongside its output. They
+/// apply to the JSON response of a single-file GET and are cached with the
+/// output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectionMeta {
+    /// Headers set on the response, e.g. `Content-Security-Policy`.
+    pub headers: Vec<(String, String)>,
+    /// How long the client may reuse the response, sent as
+    /// `Cache-Control: private, max-age=<secs>`.
+    pub cache_ttl: Option<Duration>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(tag = "type")]
 pub enum ProjectionOutput {
     DirectoryList {
         entries: Vec<DirectoryEntry>,
+        /// Number of entries in the directory, before pagination.
+        total: usize,
+        /// Dotfiles left out because of `hidden=false`; not part of `total`.
+        hidden: usize,
+        /// Entries left out because of `respect_gitignore=true`; not part of
+        /// `total`.
+        ignored: usize,
+        /// Entries left out for not matching `glob`; not part of `total`.
+        filtered: usize,
+        /// A recursive `glob` walk hit its depth or entry cap, so matches
+        /// further down may be missing.
+        truncated: bool,
+        /// The directory's README, on the first page unless `readme=false`.
+        readme: Option<Readme>,
     },
     Text {
         content: String,
         language: Option<String>,
+        /// Total lines in the file, even when `content` is a window.
+        line_count: usize,
+        /// Window of lines in `content`, when a range was requested.
+        range: Option<LineRange>,
+        /// Source encoding the content was decoded from, when known.
+        encoding: Option<String>,
+        /// Whether undecodable bytes were replaced with U+FFFD.
+        lossy: bool,
+        /// Line break style, judged from the start of the file; `None`
+        /// when there are no line breaks or it wasn't examined.
+        line_ending: Option<LineEnding>,
+        /// Indentation style, judged from the start of the file; `None`
+        /// when no line is indented or it wasn't examined.
+        indent: Option<Indent>,
+    },
+    HighlightedText {
+        /// Highlighted source as HTML with inline styles.
+        html: String,
+        language: Option<String>,
         line_count: usize,
     },
     Markdown {
         raw: String,
         toc: Vec<TocEntry>,
+        /// Sanitized HTML rendering, present when requested with `render=html`.
+        html: Option<String>,
+    },
+    Html {
+        /// The page's source, for editing.
+        raw: String,
+        /// The page with scripts, frames, styles and event handlers removed.
+        safe_html: String,
+    },
+    Svg {
+        /// The drawing with scripts, event handlers and external
+        /// references removed, for inlining.
+        safe_markup: String,
+        /// Size of the drawing in user units, from its `width` and `height`
+        /// or else its `viewBox`.
+        width: Option<f64>,
+        height: Option<f64>,
     },
     Image {
         mime_type: String,
         url: String,
+        /// EXIF readout, when the file carries one.
+        metadata: Option<ImageMeta>,
+    },
+    Json {
+        /// Parsed document, or `None` when the source is malformed.
+        value: Option<serde_json::Value>,
+        /// Re-indented source; the raw text when parsing failed.
+        pretty: String,
+        /// Parse error message with line and column, if any.
+        error: Option<String>,
+    },
+    Hex {
+        /// Byte offset of the first byte shown.
+        offset: u64,
+        /// Lowercase hex encoding of the bytes shown.
+        bytes: String,
+        /// The same bytes as printable ASCII, `.` for anything else.
+        ascii: String,
+        /// Size of the whole file.
+        total_size: u64,
+        /// Whether the file extends past the bytes shown.
+        truncated: bool,
+    },
+    Pdf {
+        /// `None` when the document could not be parsed.
+        page_count: Option<usize>,
+        /// Leading text extracted from the first pages.
+        text_excerpt: Option<String>,
+        /// Why the document could not be fully read (encrypted, malformed).
+        error: Option<String>,
+    },
+    Audio {
+        /// Playback URL on the raw endpoint, which supports Range seeking.
+        raw_url: String,
+        duration_secs: Option<f64>,
+        /// Kilobits per second.
+        bitrate: Option<u32>,
+        sample_rate: Option<u32>,
+        channels: Option<u8>,
+        /// Common tags (title, artist, album, ...) that are present.
+        tags: BTreeMap<String, String>,
+    },
+    Video {
+        /// Playback URL on the raw endpoint, which supports Range seeking.
+        raw_url: String,
+        mime_type: String,
+        duration_secs: Option<f64>,
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+    Font {
+        family: Option<String>,
+        style: Option<String>,
+        glyph_count: Option<u16>,
+        /// Font file URL on the raw endpoint, for loading via `@font-face`.
+        raw_url: String,
+    },
+    Notebook {
+        /// Kernel language, for highlighting code cells.
+        language: Option<String>,
+        cells: Vec<NotebookCell>,
+    },
+    /// The projection could not handle this file; the client can pick
+    /// another from the available list.
+    Error {
+        message: String,
+    },
+    /// The file is over the size limit for this projection and wasn't
+    /// read; the client can download it or pick a lighter projection.
+    TooLarge {
+        size: u64,
+        limit: u64,
+        raw_url: String,
+    },
+    /// The projection didn't finish within the server's time limit and
+    /// was abandoned; another view or lighter options may still work.
+    Timeout {
+        seconds: u64,
+    },
+    Diff {
+        files: Vec<DiffFile>,
+    },
+    ArchiveList {
+        entries: Vec<ArchiveEntry>,
+        /// Whether the listing stopped at the entry cap.
+        truncated: bool,
+    },
+    /// A YAML or TOML document parsed into a tree.
+    Config {
+        /// `yaml` or `toml`.
+        format: String,
+        /// Parsed document, or `None` when the source is malformed. Several
+        /// YAML documents in one file come back as an array.
+        value: Option<serde_json::Value>,
+        source: String,
+        error: Option<SyntaxError>,
+    },
+    /// Variables from a dotenv file, values masked unless revealed.
+    Env {
+        entries: Vec<EnvEntry>,
+    },
+    /// Nested listing of a directory, walked to a bounded depth.
+    Tree {
+        /// Path of the directory the tree is rooted at.
+        root: String,
+        /// Levels walked below `root`, after clamping.
+        depth: usize,
+        children: Vec<TreeNode>,
+        /// Whether anything was left out anywhere in the tree.
+        truncated: bool,
+    },
+    Table {
+        /// Source table name, when the data came from a named table.
+        table: Option<String>,
+        headers: Vec<String>,
+        /// Whether `headers` was guessed from the first row rather than
+        /// declared by the source.
+        headers_inferred: bool,
+        rows: Vec<Vec<String>>,
+        /// Whether rows were dropped to stay under the row cap.
+        truncated: bool,
     },
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct DirectoryEntry {
     pub name: String,
     pub is_dir: bool,
     pub size: u64,
     pub extension: Option<String>,
+    /// Last modification time in unix seconds.
+    pub modified: Option<u64>,
+    /// Unix permission bits, if available.
+    pub mode: Option<u32>,
+    pub is_symlink: bool,
+    /// Workspace-relative target of a symlink that stays inside the root.
+    pub symlink_target: Option<String>,
+    /// Symlink pointing outside the root; it is listed but not followed.
+    pub outside_root: bool,
+    /// Short git status (`M`, `A`, `??`, ...) with `git_status=true`; `None`
+    /// for unchanged entries and outside a repository.
+    pub git_status: Option<String>,
+}
+
+/// A README shown with its directory's listing.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Readme {
+    /// Path relative to the workspace root.
+    pub path: String,
+    /// Sanitized HTML rendering, for a Markdown README.
+    pub html: Option<String>,
+    /// The file's text, for any other README.
+    pub raw: Option<String>,
+}
+
+impl From<crate::workspace::DirEntry> for DirectoryEntry {
+    fn from(e: crate::workspace::DirEntry) -> Self {
+        Self {
+            extension: if e.is_dir {
+                None
+            } else {
+                std::path::Path::new(&e.name)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+            },
+            name: e.name,
+            is_dir: e.is_dir,
+            size: e.size,
+            modified: e.modified,
+            mode: e.mode,
+            is_symlink: e.is_symlink,
+            symlink_target: e.symlink_target,
+            outside_root: e.outside_root,
+            git_status: None,
+        }
+    }
+}
+
+/// Where and why a document failed to parse.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SyntaxError {
+    pub message: String,
+    /// One-based position, when the parser reports one.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TreeNode {
+    pub name: String,
+    /// Workspace-relative path.
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub is_symlink: bool,
+    /// Listed contents of a directory; `None` for files and for directories
+    /// that weren't descended into.
+    #[schema(no_recursion)]
+    pub children: Option<Vec<TreeNode>>,
+    /// Entries were dropped from `children`, or the directory was past the
+    /// depth limit and not listed at all.
+    pub truncated: bool,
+}
+
+/// Zero-based, half-open window of lines (`start..end`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Both styles occur.
+    Mixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct Indent {
+    pub kind: IndentKind,
+    /// Columns per level for spaces; 1 for tabs.
+    pub width: u8,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentKind {
+    Spaces,
+    Tabs,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImageMeta {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// EXIF orientation tag (1-8).
+    pub orientation: Option<u16>,
+    pub gps: Option<GpsPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GpsPosition {
+    /// Decimal degrees, negative south of the equator.
+    pub latitude: f64,
+    /// Decimal degrees, negative west of Greenwich.
+    pub longitude: f64,
+    /// Metres, negative below sea level.
+    pub altitude: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NotebookCell {
+    /// `markdown`, `code`, or `raw`.
+    pub cell_type: String,
+    pub source: String,
+    /// Sanitized HTML for markdown cells.
+    pub html: Option<String>,
+    pub execution_count: Option<u64>,
+    pub outputs: Vec<NotebookOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NotebookOutput {
+    Text {
+        text: String,
+    },
+    Image {
+        mime_type: String,
+        /// Inline `data:` URI usable as an image source.
+        data_uri: String,
+    },
+    Error {
+        name: String,
+        value: String,
+        traceback: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ArchiveEntry {
+    /// Path of the entry inside the archive.
+    pub path: String,
+    /// Uncompressed size in bytes.
+    pub size: u64,
+    /// Stored size, for formats that compress per entry.
+    pub compressed_size: Option<u64>,
+    pub is_dir: bool,
+}
+
+/// One file's section of a unified diff.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct DiffFile {
+    /// Path before the change; `None` for added files.
+    pub old_path: Option<String>,
+    /// Path after the change; `None` for deleted files.
+    pub new_path: Option<String>,
+    /// Extended header lines (`index`, `new file mode`, `rename from`, ...).
+    pub headers: Vec<String>,
+    /// The file changed but the patch carries no textual hunks.
+    pub binary: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiffHunk {
+    /// The full `@@ -a,b +c,d @@ section` line.
+    pub header: String,
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    /// Line text without the leading marker.
+    pub content: String,
+    /// One-based line number in the old file, for context and removals.
+    pub old_line: Option<usize>,
+    /// One-based line number in the new file, for context and additions.
+    pub new_line: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+    Context,
+    Add,
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EnvEntry {
+    pub key: String,
+    /// The value with quotes and escapes resolved, or `****` when masked.
+    pub value: String,
+    /// The value was hidden; request it with `reveal=KEY` or `reveal=all`.
+    pub masked: bool,
+    /// Declared with an `export ` prefix.
+    pub exported: bool,
+    /// One-based line the assignment starts on.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct TocEntry {
     pub level: u8,
     pub text: String,
     pub slug: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn raw_urls_encode_awkward_names() {
+        let store = crate::store::MemoryStore::new().with_file("notes/a b?#%.txt", "x");
+        let url = raw_url(&store, "notes/a b?#%.txt").await;
+        let (path, version) = url.split_once("?v=").unwrap();
+        assert_eq!(path, "/api/files/raw/notes/a%20b%3F%23%25.txt");
+        assert!(!version.is_empty() && !version.contains(['?', '#']));
+        assert_eq!(
+            versioned_raw_url("résumé.pdf", None),
+            "/api/files/raw/r%C3%A9sum%C3%A9.pdf"
+        );
+    }
+
+    #[test]
+    fn resources_fall_into_categories() {
+        let category = |path: &str, is_dir: bool, mime: Option<&str>| {
+            Resource::new(path.into(), is_dir)
+                .with_mime_type(mime.map(str::to_string))
+                .category()
+        };
+        assert_eq!(category("src", true, None), ResourceCategory::Dir);
+        assert_eq!(category("a.png", false, None), ResourceCategory::Image);
+        assert_eq!(
+            category("scan", false, Some("image/jpeg")),
+            ResourceCategory::Image
+        );
+        assert_eq!(
+            category("a.out", false, Some("application/x-executable")),
+            ResourceCategory::Binary
+        );
+        assert_eq!(category("Makefile", false, None), ResourceCategory::Text);
+        assert_eq!(category("a.rs", false, None), ResourceCategory::Text);
+    }
+
+    fn request(pairs: &[(&str, &str)]) -> ProjectionRequest {
+        ProjectionRequest::new(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn request_parses_typed_options() {
+        let req = request(&[("offset", "10"), ("hidden", "false")]);
+        assert_eq!(req.offset().unwrap(), Some(10));
+        assert_eq!(req.limit().unwrap(), None);
+        assert_eq!(req.flag("hidden").unwrap(), Some(false));
+    }
+
+    #[test]
+    fn request_rejects_malformed_options() {
+        let req = request(&[("limit", "lots"), ("hidden", "maybe")]);
+        assert!(matches!(
+            req.limit(),
+            Err(ProjectionError::InvalidOption(_))
+        ));
+        assert!(matches!(
+            req.flag("hidden"),
+            Err(ProjectionError::InvalidOption(_))
+        ));
+    }
+}
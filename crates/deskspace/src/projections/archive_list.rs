@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+
+use crate::archive::{self, ArchiveKind};
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+/// Maximum number of entries listed.
+const MAX_ENTRIES: usize = 10_000;
+
+pub struct ArchiveList;
+
+#[async_trait]
+impl Projection for ArchiveList {
+    fn id(&self) -> &str {
+        "archive.list"
+    }
+
+    fn name(&self) -> &str {
+        "Archive Contents"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Entries inside a zip or tar archive")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("archive")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match ArchiveKind::for_resource(resource) {
+            Some(_) => 0.9,
+            None => 0.0,
+        }
+    }
+
+    fn reads_whole_file(&self) -> bool {
+        false
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let kind = ArchiveKind::for_resource(resource).ok_or(ProjectionError::Unsupported)?;
+        let path = workspace.local_path(&resource.path)?;
+        let (entries, truncated) =
+            tokio::task::spawn_blocking(move || archive::list(&path, kind, MAX_ENTRIES))
+                .await
+                .map_err(|e| ProjectionError::Other(e.to_string()))?
+                .map_err(|e| ProjectionError::Other(format!("unreadable archive: {e}")))?;
+        Ok(ProjectionOutput::ArchiveList { entries, truncated })
+    }
+}
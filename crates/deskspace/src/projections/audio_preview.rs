@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use async_trait::async_trait;
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::probe::Probe;
+use lofty::tag::Accessor;
+
+use crate::projection::{
+    raw_url, Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "wav", "ogg", "opus", "m4a", "aac", "aiff"];
+
+pub struct AudioPreview;
+
+impl AudioPreview {
+    /// Read stream properties and tags from the container headers; the audio
+    /// itself is never decoded.
+    fn read_properties(path: &Path, raw_url: String) -> Result<ProjectionOutput> {
+        let unreadable =
+            |e: &dyn std::fmt::Display| ProjectionError::Other(format!("unreadable audio: {e}"));
+        let file = Probe::open(path)
+            .map_err(|e| unreadable(&e))?
+            .guess_file_type()
+            .map_err(|e| unreadable(&e))?
+            .read()
+            .map_err(|e| unreadable(&e))?;
+
+        let properties = file.properties();
+        let duration = properties.duration();
+        let mut tags = BTreeMap::new();
+        if let Some(tag) = file.primary_tag().or_else(|| file.first_tag()) {
+            let text = [
+                ("title", tag.title()),
+                ("artist", tag.artist()),
+                ("album", tag.album()),
+                ("genre", tag.genre()),
+                ("comment", tag.comment()),
+            ];
+            for (key, value) in text {
+                if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+                    tags.insert(key.to_string(), value.into_owned());
+                }
+            }
+            if let Some(track) = tag.track() {
+                tags.insert("track".to_string(), track.to_string());
+            }
+            if let Some(date) = tag.date() {
+                tags.insert("date".to_string(), date.to_string());
+            }
+        }
+
+        Ok(ProjectionOutput::Audio {
+            raw_url,
+            duration_secs: (!duration.is_zero()).then_some(duration.as_secs_f64()),
+            bitrate: properties
+                .audio_bitrate()
+                .or_else(|| properties.overall_bitrate()),
+            sample_rate: properties.sample_rate(),
+            channels: properties.channels(),
+            tags,
+        })
+    }
+}
+
+#[async_trait]
+impl Projection for AudioPreview {
+    fn id(&self) -> &str {
+        "audio.preview"
+    }
+
+    fn name(&self) -> &str {
+        "Audio"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Audio player with duration and tags")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("audio")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match &resource.extension {
+            Some(ext) if AUDIO_EXTENSIONS.contains(&ext.as_str()) => 0.9,
+            // lofty probes the content, so any sniffed audio type will do
+            _ if resource
+                .mime_type
+                .as_deref()
+                .is_some_and(|m| m.starts_with("audio/")) =>
+            {
+                0.8
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn reads_whole_file(&self) -> bool {
+        false
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let path = workspace.local_path(&resource.path)?;
+        let raw_url = raw_url(workspace, &resource.path).await;
+        tokio::task::spawn_blocking(move || Self::read_properties(&path, raw_url))
+            .await
+            .map_err(|e| ProjectionError::Other(e.to_string()))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal PCM WAV: mono, 8 kHz, 8-bit, `secs` seconds of silence.
+    fn wav(secs: u32) -> Vec<u8> {
+        let data_len = 8000 * secs;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // channels
+        out.extend_from_slice(&8000u32.to_le_bytes()); // sample rate
+        out.extend_from_slice(&8000u32.to_le_bytes()); // byte rate
+        out.extend_from_slice(&1u16.to_le_bytes()); // block align
+        out.extend_from_slice(&8u16.to_le_bytes()); // bits per sample
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        out.resize(out.len() + data_len as usize, 0x80);
+        out
+    }
+
+    #[test]
+    fn untagged_wav_reports_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.wav");
+        std::fs::write(&path, wav(2)).unwrap();
+        let ProjectionOutput::Audio {
+            duration_secs,
+            sample_rate,
+            channels,
+            tags,
+            ..
+        } = AudioPreview::read_properties(&path, String::new()).unwrap()
+        else {
+            panic!("expected Audio output");
+        };
+        assert_eq!(duration_secs, Some(2.0));
+        assert_eq!(sample_rate, Some(8000));
+        assert_eq!(channels, Some(1));
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fake.mp3");
+        std::fs::write(&path, b"definitely not audio").unwrap();
+        assert!(AudioPreview::read_properties(&path, String::new()).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::AsyncReadExt;
+
+use crate::config::CommandConfig;
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+/// Stdout kept from a command; anything past this is dropped.
+const MAX_OUTPUT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Stderr quoted back when a command fails.
+const MAX_ERROR_CHARS: usize = 2000;
+
+/// Runs an external program on the file and shows its stdout as text.
+/// Defined in config rather than code, see [`CommandConfig`].
+pub struct CommandProjection {
+    config: CommandConfig,
+}
+
+impl CommandProjection {
+    /// Fails when the config has no program to run.
+    pub fn new(mut config: CommandConfig) -> std::result::Result<Self, String> {
+        if config.command.is_empty() {
+            return Err(format!("command projection {:?} has no command", config.id));
+        }
+        for ext in &mut config.extensions {
+            *ext = ext.trim_start_matches('.').to_lowercase();
+        }
+        Ok(Self { config })
+    }
+
+    async fn run(&self, path: &str) -> Result<String> {
+        let args: Vec<String> = self
+            .config
+            .command
+            .iter()
+            .map(|arg| arg.replace("{path}", path))
+            .collect();
+        let mut child = tokio::process::Command::new(&args[0])
+            .args(&args[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ProjectionError::Other(format!("cannot run {}: {e}", args[0])))?;
+
+        let mut stdout = child
+            .stdout
+            .take()
+            .expect("stdout is piped")
+            .take(MAX_OUTPUT_BYTES);
+        let mut stderr = child
+            .stderr
+            .take()
+            .expect("stderr is piped")
+            .take(MAX_OUTPUT_BYTES);
+        let collect = async {
+            let mut out = Vec::new();
+            let mut err = Vec::new();
+            let (read_out, read_err) =
+                tokio::join!(stdout.read_to_end(&mut out), stderr.read_to_end(&mut err));
+            read_out?;
+            read_err?;
+            // Close our ends so a chatty program sees a broken pipe rather
+            // than blocking on output nobody reads
+            drop(stdout);
+            drop(stderr);
+            let status = child.wait().await?;
+            Ok::<_, std::io::Error>((status, out, err))
+        };
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let (status, out, err) = tokio::time::timeout(timeout, collect)
+            .await
+            .map_err(|_| {
+                ProjectionError::Other(format!(
+                    "{} timed out after {}s",
+                    args[0], self.config.timeout_secs
+                ))
+            })?
+            .map_err(|e| ProjectionError::Other(e.to_string()))?;
+        if !status.success() {
+            let stderr: String = String::from_utf8_lossy(&err)
+                .chars()
+                .take(MAX_ERROR_CHARS)
+                .collect();
+            return Err(ProjectionError::Other(format!(
+                "{} failed ({status}): {}",
+                args[0],
+                stderr.trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
+}
+
+#[async_trait]
+impl Projection for CommandProjection {
+    fn id(&self) -> &str {
+        &self.config.id
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.config.description.as_deref()
+    }
+
+    fn icon(&self) -> Option<&str> {
+        self.config.icon.as_deref().or(Some("terminal"))
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        match &resource.extension {
+            Some(ext) if self.config.extensions.contains(ext) => self.config.confidence,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let path = workspace.local_path(&resource.path)?;
+        let content = self.run(&path.to_string_lossy()).await?;
+        let lossy = content.contains('\u{FFFD}');
+        Ok(ProjectionOutput::Text {
+            line_count: content.lines().count(),
+            content,
+            language: None,
+            range: None,
+            encoding: None,
+            lossy,
+            line_ending: None,
+            indent: None,
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::workspace::Workspace;
+
+    fn projection(command: &[&str], timeout_secs: u64) -> CommandProjection {
+        CommandProjection::new(CommandConfig {
+            id: "cmd.test".to_string(),
+            name: "Test".to_string(),
+            description: None,
+            icon: None,
+            extensions: vec![".TXT".to_string()],
+            command: command.iter().map(|s| s.to_string()).collect(),
+            timeout_secs,
+            confidence: 0.5,
+        })
+        .unwrap()
+    }
+
+    async fn project(p: &CommandProjection, ws: &Workspace, path: &str) -> Result<String> {
+        let resource = Resource::new(path.to_string(), false);
+        match p
+            .project(&resource, ws, &ProjectionRequest::default())
+            .await?
+        {
+            ProjectionOutput::Text { content, .. } => Ok(content),
+            other => panic!("expected Text, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_the_path_as_one_argument() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = "a b; echo pwned $(id).txt";
+        std::fs::write(dir.path().join(name), "hello\n").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let cat = projection(&["cat", "{path}"], 10);
+        assert_eq!(cat.confidence(&Resource::new(name.to_string(), false)), 0.5);
+        assert_eq!(project(&cat, &ws, name).await.unwrap(), "hello\n");
+    }
+
+    #[tokio::test]
+    async fn reports_failures_and_timeouts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let err = project(&projection(&["ls", "{path}.missing"], 10), &ws, "a.txt")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("failed"), "{err}");
+
+        let err = project(&projection(&["sleep", "5"], 0), &ws, "a.txt")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+
+        assert!(CommandProjection::new(CommandConfig {
+            command: Vec::new(),
+            ..projection(&["true"], 1).config
+        })
+        .is_err());
+    }
+}
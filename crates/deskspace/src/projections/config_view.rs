@@ -0,0 +1,209 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::projection::{
+    Projection, ProjectionOutput, ProjectionRequest, Resource, Result, SyntaxError,
+};
+use crate::store::FileStore;
+
+pub struct ConfigView;
+
+impl ConfigView {
+    fn format(extension: &str) -> Option<&'static str> {
+        match extension {
+            "yaml" | "yml" => Some("yaml"),
+            "toml" => Some("toml"),
+            _ => None,
+        }
+    }
+
+    /// Parse config source into a tree. Malformed input still projects,
+    /// with no value and the error's position set.
+    fn parse(format: &str, source: String) -> ProjectionOutput {
+        let parsed = match format {
+            "toml" => Self::parse_toml(&source),
+            _ => Self::parse_yaml(&source),
+        };
+        let (value, error) = match parsed {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+        ProjectionOutput::Config {
+            format: format.to_string(),
+            value,
+            source,
+            error,
+        }
+    }
+
+    /// A stream of several documents becomes an array of them.
+    fn parse_yaml(source: &str) -> std::result::Result<serde_json::Value, SyntaxError> {
+        let mut docs = Vec::new();
+        for doc in serde_yaml::Deserializer::from_str(source) {
+            let value = serde_yaml::Value::deserialize(doc).map_err(|e| {
+                let location = e.location();
+                SyntaxError {
+                    message: e.to_string(),
+                    line: location.as_ref().map(|l| l.line()),
+                    column: location.as_ref().map(|l| l.column()),
+                }
+            })?;
+            let value = serde_json::to_value(value).map_err(|e| SyntaxError {
+                message: format!("cannot represent document: {e}"),
+                line: None,
+                column: None,
+            })?;
+            docs.push(value);
+        }
+        Ok(match docs.len() {
+            0 => serde_json::Value::Null,
+            1 => docs.remove(0),
+            _ => serde_json::Value::Array(docs),
+        })
+    }
+
+    fn parse_toml(source: &str) -> std::result::Result<serde_json::Value, SyntaxError> {
+        match toml::from_str::<toml::Table>(source) {
+            Ok(table) => Ok(toml_to_json(toml::Value::Table(table))),
+            Err(e) => {
+                let (line, column) = match e.span() {
+                    Some(span) => {
+                        let (line, column) = line_column(source, span.start);
+                        (Some(line), Some(column))
+                    }
+                    None => (None, None),
+                };
+                Err(SyntaxError {
+                    message: e.message().to_string(),
+                    line,
+                    column,
+                })
+            }
+        }
+    }
+}
+
+/// Datetimes become their TOML string form rather than serde's private
+/// wrapper struct.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+    match value {
+        toml::Value::String(s) => Json::String(s),
+        toml::Value::Integer(i) => Json::from(i),
+        toml::Value::Float(f) => Json::from(f),
+        toml::Value::Boolean(b) => Json::Bool(b),
+        toml::Value::Datetime(d) => Json::String(d.to_string()),
+        toml::Value::Array(items) => Json::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Json::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// One-based line and column (in characters) of byte `offset` in `source`.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..source.floor_char_boundary(offset.min(source.len()))];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[async_trait]
+impl Projection for ConfigView {
+    fn id(&self) -> &str {
+        "config.view"
+    }
+
+    fn name(&self) -> &str {
+        "Config"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("YAML or TOML parsed into a tree, with syntax errors")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("json")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        // Above text.raw and text.highlight, which stay available for editing
+        match resource.extension.as_deref().and_then(Self::format) {
+            Some(_) => 0.9,
+            None => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let format = resource
+            .extension
+            .as_deref()
+            .and_then(Self::format)
+            .unwrap_or("yaml");
+        let source = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        Ok(Self::parse(format, source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(format: &str, source: &str) -> (Option<serde_json::Value>, Option<SyntaxError>) {
+        let ProjectionOutput::Config { value, error, .. } =
+            ConfigView::parse(format, source.to_string())
+        else {
+            panic!("expected Config output");
+        };
+        (value, error)
+    }
+
+    #[test]
+    fn parses_yaml_and_toml_into_trees() {
+        let (value, error) = parse("yaml", "name: deskspace\nports: [80, 443]\n1: one\n");
+        assert!(error.is_none());
+        let value = value.unwrap();
+        assert_eq!(value["ports"][1], 443);
+        assert_eq!(value["1"], "one");
+
+        let (value, _) = parse("yaml", "a: 1\n---\na: 2\n");
+        assert_eq!(value.unwrap()[1]["a"], 2);
+
+        let (value, error) = parse(
+            "toml",
+            "[server]\nport = 3000\nstarted = 1979-05-27T07:32:00Z\n",
+        );
+        assert!(error.is_none());
+        let value = value.unwrap();
+        assert_eq!(value["server"]["port"], 3000);
+        assert_eq!(value["server"]["started"], "1979-05-27T07:32:00Z");
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let (value, error) = parse("yaml", "a: 1\nb: [unclosed\n");
+        assert!(value.is_none());
+        let error = error.unwrap();
+        assert_eq!(error.line, Some(3));
+
+        let (value, error) = parse("toml", "a = 1\nb = = 2\n");
+        assert!(value.is_none());
+        let error = error.unwrap();
+        assert_eq!((error.line, error.column), (Some(2), Some(5)));
+    }
+}
@@ -0,0 +1,138 @@
+use async_trait::async_trait;
+
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+/// Maximum number of data rows returned.
+const MAX_ROWS: usize = 1000;
+
+pub struct CsvTable;
+
+impl CsvTable {
+    /// Parse delimited text into a table, treating the first row as headers.
+    fn parse(raw: &str, delimiter: u8) -> Result<ProjectionOutput> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(raw.as_bytes());
+
+        let mut records = reader.records();
+        let headers = match records.next() {
+            Some(record) => record
+                .map_err(|e| ProjectionError::Other(e.to_string()))?
+                .iter()
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        };
+        let headers_inferred = !headers.is_empty();
+
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        for record in records {
+            let record = record.map_err(|e| ProjectionError::Other(e.to_string()))?;
+            if rows.len() >= MAX_ROWS {
+                truncated = true;
+                break;
+            }
+            rows.push(record.iter().map(String::from).collect());
+        }
+
+        Ok(ProjectionOutput::Table {
+            table: None,
+            headers,
+            headers_inferred,
+            rows,
+            truncated,
+        })
+    }
+}
+
+#[async_trait]
+impl Projection for CsvTable {
+    fn id(&self) -> &str {
+        "csv.table"
+    }
+
+    fn name(&self) -> &str {
+        "Table"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Delimited data as a table")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("table")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("csv" | "tsv") => 0.9,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let raw = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        let delimiter = match resource.extension.as_deref() {
+            Some("tsv") => b'\t',
+            _ => b',',
+        };
+        Self::parse(&raw, delimiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_quoted_fields() {
+        let raw = "name,notes\nAlice,\"likes, commas\"\nBob,\"multi\nline\"\n";
+        let ProjectionOutput::Table {
+            headers,
+            headers_inferred,
+            rows,
+            truncated,
+            ..
+        } = CsvTable::parse(raw, b',').unwrap()
+        else {
+            panic!("expected Table output");
+        };
+        assert_eq!(headers, vec!["name", "notes"]);
+        assert!(headers_inferred);
+        assert_eq!(rows[0], vec!["Alice", "likes, commas"]);
+        assert_eq!(rows[1], vec!["Bob", "multi\nline"]);
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn parse_truncates_long_files() {
+        let mut raw = String::from("n\n");
+        for i in 0..MAX_ROWS + 5 {
+            raw.push_str(&format!("{i}\n"));
+        }
+        let ProjectionOutput::Table {
+            rows, truncated, ..
+        } = CsvTable::parse(&raw, b',').unwrap()
+        else {
+            panic!("expected Table output");
+        };
+        assert_eq!(rows.len(), MAX_ROWS);
+        assert!(truncated);
+    }
+}
@@ -0,0 +1,352 @@
+use async_trait::async_trait;
+
+use crate::projection::{
+    DiffFile, DiffHunk, DiffLine, DiffLineKind, Projection, ProjectionOutput, ProjectionRequest,
+    Resource, Result,
+};
+use crate::store::FileStore;
+
+pub struct DiffView;
+
+/// Hunk being filled, with the lines it still expects on each side.
+struct OpenHunk {
+    hunk: DiffHunk,
+    old_left: usize,
+    new_left: usize,
+}
+
+impl DiffView {
+    /// Parse a unified diff (optionally with git extended headers) into
+    /// files and hunks. Text outside any file section, such as a
+    /// `format-patch` mail header, is skipped. Returns an empty list when
+    /// nothing diff-like was found.
+    fn parse(text: &str) -> Vec<DiffFile> {
+        let mut files: Vec<DiffFile> = Vec::new();
+        let mut open: Option<OpenHunk> = None;
+
+        let close = |files: &mut Vec<DiffFile>, open: &mut Option<OpenHunk>| {
+            if let (Some(o), Some(file)) = (open.take(), files.last_mut()) {
+                file.hunks.push(o.hunk);
+            }
+        };
+
+        for line in text.lines() {
+            if let Some(o) = open.as_mut() {
+                if o.old_left > 0 || o.new_left > 0 {
+                    if Self::push_hunk_line(o, line) {
+                        continue;
+                    }
+                } else if line.starts_with('\\') {
+                    // "\ No newline at end of file" after the last line
+                    continue;
+                }
+                close(&mut files, &mut open);
+            }
+
+            if let Some(rest) = line.strip_prefix("diff --git ") {
+                let (old, new) = Self::git_paths(rest);
+                files.push(DiffFile {
+                    old_path: old,
+                    new_path: new,
+                    ..DiffFile::default()
+                });
+            } else if let Some(path) = line.strip_prefix("--- ") {
+                // A plain unified diff starts each file with the --- line;
+                // after `diff --git` it continues that file's header
+                if files.last().is_none_or(|f| !f.hunks.is_empty() || f.binary) {
+                    files.push(DiffFile::default());
+                }
+                if let Some(file) = files.last_mut() {
+                    file.old_path = Self::marker_path(path);
+                }
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                if let Some(file) = files.last_mut() {
+                    file.new_path = Self::marker_path(path);
+                }
+            } else if line.starts_with("@@") {
+                match Self::hunk_header(line) {
+                    Some(hunk) if !files.is_empty() => {
+                        open = Some(OpenHunk {
+                            old_left: hunk.old_lines,
+                            new_left: hunk.new_lines,
+                            hunk,
+                        });
+                    }
+                    _ => {}
+                }
+            } else if let Some(file) = files.last_mut() {
+                if !file.hunks.is_empty() || line.is_empty() {
+                    continue;
+                }
+                if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                    file.binary = true;
+                }
+                if let Some(path) = line.strip_prefix("rename from ") {
+                    file.old_path = Some(path.to_string());
+                } else if let Some(path) = line.strip_prefix("rename to ") {
+                    file.new_path = Some(path.to_string());
+                } else if line.starts_with("new file mode") {
+                    file.old_path = None;
+                } else if line.starts_with("deleted file mode") {
+                    file.new_path = None;
+                }
+                file.headers.push(line.to_string());
+            }
+        }
+        close(&mut files, &mut open);
+        files
+    }
+
+    /// Classify one line inside an open hunk. Returns false if the line
+    /// doesn't belong to the hunk.
+    fn push_hunk_line(open: &mut OpenHunk, line: &str) -> bool {
+        let hunk = &mut open.hunk;
+        let old_no = hunk.old_start + hunk.old_lines - open.old_left;
+        let new_no = hunk.new_start + hunk.new_lines - open.new_left;
+        let (kind, content) = match line.as_bytes().first() {
+            Some(b'+') if open.new_left > 0 => (DiffLineKind::Add, &line[1..]),
+            Some(b'-') if open.old_left > 0 => (DiffLineKind::Remove, &line[1..]),
+            Some(b' ') if open.old_left > 0 && open.new_left > 0 => {
+                (DiffLineKind::Context, &line[1..])
+            }
+            // Some tools strip the space from empty context lines
+            None if open.old_left > 0 && open.new_left > 0 => (DiffLineKind::Context, ""),
+            Some(b'\\') => return true,
+            _ => return false,
+        };
+        let (old_line, new_line) = match kind {
+            DiffLineKind::Add => {
+                open.new_left -= 1;
+                (None, Some(new_no))
+            }
+            DiffLineKind::Remove => {
+                open.old_left -= 1;
+                (Some(old_no), None)
+            }
+            DiffLineKind::Context => {
+                open.old_left -= 1;
+                open.new_left -= 1;
+                (Some(old_no), Some(new_no))
+            }
+        };
+        hunk.lines.push(DiffLine {
+            kind,
+            content: content.to_string(),
+            old_line,
+            new_line,
+        });
+        true
+    }
+
+    /// Parse `@@ -a[,b] +c[,d] @@ ...`; an omitted count means one line.
+    fn hunk_header(line: &str) -> Option<DiffHunk> {
+        let rest = line.strip_prefix("@@ ")?;
+        let (ranges, _) = rest.split_once(" @@")?;
+        let (old, new) = ranges.split_once(' ')?;
+        let range = |r: &str| -> Option<(usize, usize)> {
+            match r.split_once(',') {
+                Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+                None => Some((r.parse().ok()?, 1)),
+            }
+        };
+        let (old_start, old_lines) = range(old.strip_prefix('-')?)?;
+        let (new_start, new_lines) = range(new.strip_prefix('+')?)?;
+        Some(DiffHunk {
+            header: line.to_string(),
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+            lines: Vec::new(),
+        })
+    }
+
+    /// Path from a `---`/`+++` line, without the `a/`/`b/` prefix or a
+    /// trailing timestamp; `/dev/null` means the side doesn't exist.
+    fn marker_path(raw: &str) -> Option<String> {
+        let path = raw.split('\t').next().unwrap_or(raw).trim_end();
+        if path == "/dev/null" {
+            return None;
+        }
+        let path = path
+            .strip_prefix("a/")
+            .or_else(|| path.strip_prefix("b/"))
+            .unwrap_or(path);
+        Some(path.to_string())
+    }
+
+    /// Paths from `diff --git a/old b/new`. Ambiguous when names contain
+    /// " b/"; the ---/+++ or rename lines that follow take precedence.
+    fn git_paths(rest: &str) -> (Option<String>, Option<String>) {
+        match rest.rfind(" b/") {
+            Some(split) => {
+                let old = &rest[..split];
+                let new = &rest[split + 1..];
+                (
+                    Some(old.strip_prefix("a/").unwrap_or(old).to_string()),
+                    Some(new.strip_prefix("b/").unwrap_or(new).to_string()),
+                )
+            }
+            None => (None, None),
+        }
+    }
+}
+
+#[async_trait]
+impl Projection for DiffView {
+    fn id(&self) -> &str {
+        "diff.view"
+    }
+
+    fn name(&self) -> &str {
+        "Diff"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Unified diff split into files and hunks")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("diff")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("diff" | "patch") => 0.9,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let content = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        let files = Self::parse(&content);
+        if files.is_empty() {
+            // Not recognisably a diff: show it as a single raw blob
+            let line_count = content.lines().count();
+            return Ok(ProjectionOutput::Text {
+                content,
+                language: Some("diff".to_string()),
+                line_count,
+                range: None,
+                encoding: None,
+                lossy: false,
+                line_ending: None,
+                indent: None,
+            });
+        }
+        Ok(ProjectionOutput::Diff { files })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_unified_diff() {
+        let diff = "--- a/src/lib.rs\t2024-01-01 00:00:00\n\
+                    +++ b/src/lib.rs\t2024-01-02 00:00:00\n\
+                    @@ -1,3 +1,3 @@ fn main()\n \
+                    one\n\
+                    -two\n\
+                    +TWO\n \
+                    three\n";
+        let files = DiffView::parse(diff);
+        assert_eq!(files.len(), 1);
+        let file = &files[0];
+        assert_eq!(file.old_path.as_deref(), Some("src/lib.rs"));
+        assert_eq!(file.new_path.as_deref(), Some("src/lib.rs"));
+        let lines = &file.hunks[0].lines;
+        let kinds: Vec<_> = lines.iter().map(|l| l.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                DiffLineKind::Context,
+                DiffLineKind::Remove,
+                DiffLineKind::Add,
+                DiffLineKind::Context
+            ]
+        );
+        assert_eq!((lines[1].old_line, lines[1].new_line), (Some(2), None));
+        assert_eq!((lines[2].old_line, lines[2].new_line), (None, Some(2)));
+        assert_eq!((lines[3].old_line, lines[3].new_line), (Some(3), Some(3)));
+    }
+
+    #[test]
+    fn parses_git_multi_file_patch_with_extended_headers() {
+        let diff = "From abc Mon Sep 17 00:00:00 2001\n\
+                    Subject: [PATCH] tidy\n\
+                    \n\
+                    diff --git a/old.txt b/new.txt\n\
+                    similarity index 90%\n\
+                    rename from old.txt\n\
+                    rename to new.txt\n\
+                    index 1111111..2222222 100644\n\
+                    --- a/old.txt\n\
+                    +++ b/new.txt\n\
+                    @@ -1 +1 @@\n\
+                    --- not a header\n\
+                    +++ nor this\n\
+                    diff --git a/gone.bin b/gone.bin\n\
+                    deleted file mode 100644\n\
+                    Binary files a/gone.bin and /dev/null differ\n\
+                    diff --git a/added.rs b/added.rs\n\
+                    new file mode 100644\n\
+                    --- /dev/null\n\
+                    +++ b/added.rs\n\
+                    @@ -0,0 +1,2 @@\n\
+                    +fn a() {}\n\
+                    +fn b() {}\n\
+                    \\ No newline at end of file\n\
+                    -- \n\
+                    2.40.0\n";
+        let files = DiffView::parse(diff);
+        assert_eq!(files.len(), 3);
+
+        let renamed = &files[0];
+        assert_eq!(renamed.old_path.as_deref(), Some("old.txt"));
+        assert_eq!(renamed.new_path.as_deref(), Some("new.txt"));
+        assert!(renamed.headers.contains(&"rename from old.txt".to_string()));
+        let lines = &renamed.hunks[0].lines;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].kind, DiffLineKind::Remove);
+        assert_eq!(lines[0].content, "-- not a header");
+        assert_eq!(lines[1].kind, DiffLineKind::Add);
+
+        let deleted = &files[1];
+        assert!(deleted.binary);
+        assert_eq!(deleted.new_path, None);
+        assert!(deleted.hunks.is_empty());
+
+        let added = &files[2];
+        assert_eq!(added.old_path, None);
+        assert_eq!(added.new_path.as_deref(), Some("added.rs"));
+        let hunk = &added.hunks[0];
+        assert_eq!(hunk.lines.len(), 2);
+        assert_eq!(hunk.lines[1].new_line, Some(2));
+    }
+
+    #[test]
+    fn multiple_plain_files() {
+        let diff = "--- a\n+++ a\n@@ -1 +1 @@\n-x\n+y\n--- b\n+++ b\n@@ -2,0 +3 @@\n+z\n";
+        let files = DiffView::parse(diff);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].old_path.as_deref(), Some("b"));
+        assert_eq!(files[1].hunks[0].lines[0].new_line, Some(3));
+    }
+
+    #[test]
+    fn non_diff_text_yields_nothing() {
+        assert!(DiffView::parse("just some notes\nabout a patch\n").is_empty());
+    }
+}
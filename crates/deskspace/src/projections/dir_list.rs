@@ -1,10 +1,265 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::path::Path;
+
 use async_trait::async_trait;
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::projection::{
+    DirectoryEntry, Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Readme,
+    Resource, Result,
+};
+use crate::projections::text_markdown::TextMarkdown;
+use crate::store::FileStore;
+use crate::workspace::{DirEntry, WalkOptions};
 
-use crate::projection::{DirectoryEntry, Projection, ProjectionOutput, Resource, Result};
-use crate::workspace::Workspace;
+/// Levels walked for a recursive `glob` when the request doesn't say.
+const DEFAULT_GLOB_DEPTH: usize = 8;
+/// Deepest recursive `glob` walk allowed; larger `depth` values are clamped.
+const MAX_GLOB_DEPTH: usize = 16;
+/// Entries visited by a recursive `glob` before the walk stops.
+const MAX_GLOB_VISITS: usize = 50_000;
+/// Names taken for a directory's README, in order of preference, ignoring
+/// case.
+const README_NAMES: &[&str] = &["readme.md", "readme.markdown", "readme", "readme.txt"];
+/// Largest README shown with a listing; bigger ones are left out.
+const MAX_README_BYTES: u64 = 512 * 1024;
 
 pub struct DirList;
 
+/// The compiled `glob` option.
+struct GlobFilter {
+    matcher: GlobMatcher,
+    /// The pattern spans directories, so the listing walks below the
+    /// requested one.
+    recursive: bool,
+}
+
+impl DirList {
+    /// Parse `glob`. Patterns containing `/` or `**` match paths relative
+    /// to the listed directory and make the listing recursive; others match
+    /// entry names. `*` never crosses a `/`.
+    fn glob(request: &ProjectionRequest) -> Result<Option<GlobFilter>> {
+        let Some(pattern) = request.get("glob").filter(|p| !p.is_empty()) else {
+            return Ok(None);
+        };
+        let matcher = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| ProjectionError::InvalidOption(format!("glob: {e}")))?
+            .compile_matcher();
+        Ok(Some(GlobFilter {
+            matcher,
+            recursive: pattern.contains('/') || pattern.contains("**"),
+        }))
+    }
+
+    /// Everything below `dir` down to `depth` levels, named by path relative
+    /// to `dir` and sorted by it. Unless `show_hidden`, the contents of
+    /// dot-directories are skipped; the directories themselves are kept for
+    /// the hidden count. Also returns whether the walk was cut short.
+    async fn walk_entries(
+        workspace: &dyn FileStore,
+        dir: &str,
+        depth: usize,
+        show_hidden: bool,
+    ) -> Result<(Vec<DirEntry>, bool)> {
+        let workspace = workspace
+            .local()
+            .ok_or_else(|| {
+                ProjectionError::Other("recursive globs need a workspace on disk".to_string())
+            })?
+            .clone();
+        let dir = dir.trim_matches('/').to_string();
+        let options = WalkOptions {
+            max_depth: depth,
+            max_entries: MAX_GLOB_VISITS,
+            follow_symlinks: false,
+        };
+        tokio::task::spawn_blocking(move || {
+            let mut entries = Vec::new();
+            let summary = workspace.walk(Path::new(&dir), &options, |entry| {
+                let rel = match dir.as_str() {
+                    "" => Some(entry.path.as_str()),
+                    dir => entry
+                        .path
+                        .strip_prefix(dir)
+                        .and_then(|p| p.strip_prefix('/')),
+                }
+                .unwrap_or(&entry.path);
+                let in_hidden_dir = rel
+                    .rsplit_once('/')
+                    .is_some_and(|(parent, _)| parent.split('/').any(|c| c.starts_with('.')));
+                if show_hidden || !in_hidden_dir {
+                    entries.push(DirEntry {
+                        name: rel.to_string(),
+                        is_dir: entry.is_dir,
+                        size: entry.size,
+                        modified: entry.modified,
+                        mode: entry.mode,
+                        is_symlink: entry.is_symlink,
+                        symlink_target: None,
+                        outside_root: false,
+                    });
+                }
+                ControlFlow::Continue(())
+            })?;
+            entries.sort_by(|a, b| {
+                a.name
+                    .to_lowercase()
+                    .cmp(&b.name.to_lowercase())
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+            Ok((entries, summary.truncated))
+        })
+        .await
+        .map_err(|e| ProjectionError::Other(e.to_string()))?
+    }
+
+    /// The README among `entries` of `dir`, rendered when it's Markdown.
+    /// `None` when there isn't one or it can't be read as text.
+    async fn readme(workspace: &dyn FileStore, dir: &str, entries: &[DirEntry]) -> Option<Readme> {
+        let entry = README_NAMES.iter().find_map(|wanted| {
+            entries
+                .iter()
+                .find(|e| !e.is_dir && e.name.eq_ignore_ascii_case(wanted))
+        })?;
+        if entry.size > MAX_README_BYTES {
+            return None;
+        }
+        let path = match dir.trim_matches('/') {
+            "" => entry.name.clone(),
+            dir => format!("{dir}/{}", entry.name),
+        };
+        let raw = match workspace.read_to_string(Path::new(&path)).await {
+            Ok(raw) => raw,
+            Err(e) => {
+                tracing::debug!("dir.list: skipping README {path}: {e}");
+                return None;
+            }
+        };
+        let name = entry.name.to_lowercase();
+        if name.ends_with(".md") || name.ends_with(".markdown") {
+            Some(Readme {
+                path,
+                html: Some(TextMarkdown::render_html(&raw)),
+                raw: None,
+            })
+        } else {
+            Some(Readme {
+                path,
+                html: None,
+                raw: Some(raw),
+            })
+        }
+    }
+
+    /// Paths below `dir`, down to `depth` levels and relative to it, that
+    /// git would not ignore, honouring `.gitignore` in `dir` and its parents
+    /// plus `.git/info/exclude`. Outside a git repository nothing is ignored.
+    fn unignored_paths(dir: &Path, depth: usize) -> HashSet<String> {
+        ignore::WalkBuilder::new(dir)
+            .max_depth(Some(depth))
+            .hidden(false)
+            .ignore(false)
+            .git_global(false)
+            .require_git(true)
+            .build()
+            .filter_map(|entry| match entry {
+                Ok(entry) if entry.depth() > 0 => {
+                    let rel = entry.path().strip_prefix(dir).ok()?;
+                    Some(
+                        rel.components()
+                            .map(|c| c.as_os_str().to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/"),
+                    )
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    tracing::debug!("dir.list: gitignore walk error: {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Short git status (`M`, `A`, `??`, ...) of each child of `dir` that
+    /// differs from `HEAD` or the index, keyed by name. Only paths under
+    /// `dir` are examined and untracked directories aren't descended into.
+    /// A child directory takes the status of its changed contents, or `M`
+    /// when those disagree. `None` outside a repository.
+    fn git_statuses(dir: &Path) -> Option<HashMap<String, String>> {
+        let repo = git2::Repository::discover(dir).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let prefix = dir.strip_prefix(&workdir).ok()?;
+
+        let mut options = git2::StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(false)
+            .exclude_submodules(true);
+        if !prefix.as_os_str().is_empty() {
+            options.pathspec(prefix).disable_pathspec_match(true);
+        }
+        let statuses = match repo.statuses(Some(&mut options)) {
+            Ok(statuses) => statuses,
+            Err(e) => {
+                tracing::debug!("dir.list: git status failed: {e}");
+                return None;
+            }
+        };
+
+        let mut by_name = HashMap::new();
+        for entry in statuses.iter() {
+            let Some(code) = Self::status_code(entry.status()) else {
+                continue;
+            };
+            let Ok(path) = entry.path() else {
+                continue;
+            };
+            let Ok(rel) = Path::new(path).strip_prefix(prefix) else {
+                continue;
+            };
+            let Some(name) = rel.components().next() else {
+                continue;
+            };
+            let name = name.as_os_str().to_string_lossy().into_owned();
+            by_name
+                .entry(name)
+                .and_modify(|existing: &mut String| {
+                    if existing != code {
+                        *existing = "M".to_string();
+                    }
+                })
+                .or_insert_with(|| code.to_string());
+        }
+        Some(by_name)
+    }
+
+    fn status_code(status: git2::Status) -> Option<&'static str> {
+        use git2::Status;
+        let code = if status.is_conflicted() {
+            "U"
+        } else if status.is_wt_new() {
+            "??"
+        } else if status.is_index_new() {
+            "A"
+        } else if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+            "R"
+        } else if status.intersects(Status::INDEX_MODIFIED | Status::WT_MODIFIED) {
+            "M"
+        } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+            "D"
+        } else if status.intersects(Status::INDEX_TYPECHANGE | Status::WT_TYPECHANGE) {
+            "T"
+        } else {
+            return None;
+        };
+        Some(code)
+    }
+}
+
 #[async_trait]
 impl Projection for DirList {
     fn id(&self) -> &str {
@@ -15,6 +270,14 @@ impl Projection for DirList {
         "Directory Listing"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("Files and folders in this directory")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("folder")
+    }
+
     fn confidence(&self, resource: &Resource) -> f32 {
         if resource.is_dir {
             1.0
@@ -26,26 +289,345 @@ impl Projection for DirList {
     async fn project(
         &self,
         resource: &Resource,
-        workspace: &Workspace,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
     ) -> Result<ProjectionOutput> {
-        let entries = workspace
-            .read_dir(std::path::Path::new(&resource.path))
-            .await?;
+        let show_hidden = request.flag("hidden")?.unwrap_or(true);
+        let glob = Self::glob(request)?;
+        let depth = match &glob {
+            Some(glob) if glob.recursive => request
+                .parse::<usize>("depth")?
+                .unwrap_or(DEFAULT_GLOB_DEPTH)
+                .clamp(1, MAX_GLOB_DEPTH),
+            _ => 1,
+        };
+        let (mut entries, truncated) = if depth > 1 {
+            Self::walk_entries(workspace, &resource.path, depth, show_hidden).await?
+        } else {
+            let entries = workspace
+                .read_dir(std::path::Path::new(&resource.path))
+                .await?;
+            (entries, false)
+        };
+        let offset = request.offset()?.unwrap_or(0);
+        let readme = if offset == 0 && request.flag("readme")?.unwrap_or(true) {
+            Self::readme(workspace, &resource.path, &entries).await
+        } else {
+            None
+        };
+        let before = entries.len();
+        if !show_hidden {
+            entries.retain(|e| !e.name.rsplit('/').next().unwrap_or("").starts_with('.'));
+        }
+        let hidden = before - entries.len();
+        let before = entries.len();
+        if request.flag("respect_gitignore")?.unwrap_or(false) {
+            let dir = workspace.local_path(&resource.path)?;
+            let keep = tokio::task::spawn_blocking(move || Self::unignored_paths(&dir, depth))
+                .await
+                .map_err(|e| ProjectionError::Other(e.to_string()))?;
+            entries.retain(|e| keep.contains(&e.name));
+        }
+        let ignored = before - entries.len();
+        let before = entries.len();
+        if let Some(glob) = &glob {
+            entries.retain(|e| glob.matcher.is_match(&e.name));
+        }
+        let filtered = before - entries.len();
+        let mut git = None;
+        if request.flag("git_status")?.unwrap_or(false) {
+            let dir = workspace.local_path(&resource.path)?;
+            git = tokio::task::spawn_blocking(move || Self::git_statuses(&dir))
+                .await
+                .map_err(|e| ProjectionError::Other(e.to_string()))?;
+        }
+        let total = entries.len();
         let entries = entries
             .into_iter()
+            .skip(offset)
+            .take(request.limit()?.unwrap_or(usize::MAX))
             .map(|e| DirectoryEntry {
-                extension: if e.is_dir {
-                    None
-                } else {
-                    std::path::Path::new(&e.name)
-                        .extension()
-                        .map(|ext| ext.to_string_lossy().to_lowercase())
-                },
-                name: e.name,
-                is_dir: e.is_dir,
-                size: e.size,
+                git_status: git.as_ref().and_then(|g| g.get(&e.name).cloned()),
+                ..e.into()
             })
             .collect();
-        Ok(ProjectionOutput::DirectoryList { entries })
+        Ok(ProjectionOutput::DirectoryList {
+            entries,
+            total,
+            hidden,
+            ignored,
+            filtered,
+            truncated,
+            readme,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::workspace::Workspace;
+
+    #[tokio::test]
+    async fn hidden_false_filters_dotfiles() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".env"), "").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let resource = Resource::new(String::new(), true);
+
+        let request =
+            ProjectionRequest::new(HashMap::from([("hidden".to_string(), "false".to_string())]));
+        let ProjectionOutput::DirectoryList {
+            entries,
+            total,
+            hidden,
+            ..
+        } = DirList.project(&resource, &ws, &request).await.unwrap()
+        else {
+            panic!("expected DirectoryList");
+        };
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "main.rs");
+        assert_eq!((total, hidden), (1, 2));
+
+        let request = ProjectionRequest::default();
+        let ProjectionOutput::DirectoryList { total, hidden, .. } =
+            DirList.project(&resource, &ws, &request).await.unwrap()
+        else {
+            panic!("expected DirectoryList");
+        };
+        assert_eq!((total, hidden), (3, 0));
+    }
+
+    #[tokio::test]
+    async fn glob_filters_names_or_walks_below() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/api")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".cache")).unwrap();
+        for file in [
+            "logo.png",
+            "notes.txt",
+            "src/lib.rs",
+            "src/api/mod.rs",
+            "src/api/icon.png",
+            ".cache/old.rs",
+        ] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+        let ws = Workspace::new(dir.path()).unwrap();
+        let list = |path: &str, options: &[(&str, &str)]| {
+            let resource = Resource::new(path.to_string(), true);
+            let request = ProjectionRequest::new(
+                options
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            );
+            let ws = ws.clone();
+            async move { DirList.project(&resource, &ws, &request).await }
+        };
+        let names = |output: ProjectionOutput| {
+            let ProjectionOutput::DirectoryList {
+                entries,
+                total,
+                filtered,
+                ..
+            } = output
+            else {
+                panic!("expected DirectoryList");
+            };
+            let names: Vec<_> = entries.into_iter().map(|e| e.name).collect();
+            (names, total, filtered)
+        };
+
+        // Plain patterns match names of direct children only
+        assert_eq!(
+            names(list("", &[("glob", "*.png")]).await.unwrap()),
+            (vec!["logo.png".to_string()], 1, 3)
+        );
+        assert_eq!(
+            names(list("src", &[("glob", "**/*.rs")]).await.unwrap()).0,
+            ["api/mod.rs", "lib.rs"]
+        );
+        assert_eq!(
+            names(list("", &[("glob", "src/**/*.png")]).await.unwrap()).0,
+            ["src/api/icon.png"]
+        );
+        assert_eq!(
+            names(
+                list("", &[("glob", "**/*.rs"), ("hidden", "false")])
+                    .await
+                    .unwrap()
+            )
+            .0,
+            ["src/api/mod.rs", "src/lib.rs"]
+        );
+        assert_eq!(
+            names(
+                list("", &[("glob", "**/*.rs"), ("depth", "2")])
+                    .await
+                    .unwrap()
+            )
+            .0,
+            [".cache/old.rs", "src/lib.rs"]
+        );
+
+        assert!(matches!(
+            list("", &[("glob", "src/[a-")]).await,
+            Err(ProjectionError::InvalidOption(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn respect_gitignore_filters_ignored_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src/gen")).unwrap();
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(dir.path().join("src/.gitignore"), "gen/\n").unwrap();
+        std::fs::write(dir.path().join("build.log"), "").unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.path().join("src/debug.log"), "").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let request = ProjectionRequest::new(HashMap::from([(
+            "respect_gitignore".to_string(),
+            "true".to_string(),
+        )]));
+
+        let list = |path: &str| {
+            let resource = Resource::new(path.to_string(), true);
+            let ws = ws.clone();
+            let request = request.clone();
+            async move {
+                let ProjectionOutput::DirectoryList {
+                    entries, ignored, ..
+                } = DirList.project(&resource, &ws, &request).await.unwrap()
+                else {
+                    panic!("expected DirectoryList");
+                };
+                let names: Vec<_> = entries.into_iter().map(|e| e.name).collect();
+                (names, ignored)
+            }
+        };
+        assert_eq!(
+            list("").await,
+            (vec![".git".into(), "src".into(), ".gitignore".into()], 2)
+        );
+        // Root rules compose with the nested .gitignore
+        assert_eq!(
+            list("src").await,
+            (vec![".gitignore".into(), "lib.rs".into()], 2)
+        );
+
+        // Without a repository the option is a no-op
+        std::fs::remove_dir(dir.path().join(".git")).unwrap();
+        assert_eq!(list("src").await.1, 0);
+    }
+
+    #[tokio::test]
+    async fn git_status_marks_changed_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        std::fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        std::fs::write(dir.path().join("src/clean.rs"), "a").unwrap();
+        std::fs::write(dir.path().join("src/edited.rs"), "a").unwrap();
+        std::fs::write(dir.path().join("src/nested/deep.rs"), "a").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("t", "t@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+
+        std::fs::write(dir.path().join("src/edited.rs"), "b").unwrap();
+        std::fs::write(dir.path().join("src/nested/deep.rs"), "b").unwrap();
+        std::fs::write(dir.path().join("src/new.rs"), "").unwrap();
+        std::fs::create_dir(dir.path().join("src/fresh")).unwrap();
+        std::fs::write(dir.path().join("src/fresh/x.rs"), "").unwrap();
+        std::fs::write(dir.path().join("src/staged.rs"), "").unwrap();
+        index.add_path(Path::new("src/staged.rs")).unwrap();
+        index.write().unwrap();
+        std::fs::write(dir.path().join("top.txt"), "").unwrap();
+
+        let ws = Workspace::new(dir.path()).unwrap();
+        let request = ProjectionRequest::new(HashMap::from([(
+            "git_status".to_string(),
+            "true".to_string(),
+        )]));
+        let resource = Resource::new("src".to_string(), true);
+        let ProjectionOutput::DirectoryList { entries, .. } =
+            DirList.project(&resource, &ws, &request).await.unwrap()
+        else {
+            panic!("expected DirectoryList");
+        };
+        let status: HashMap<_, _> = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.git_status.as_deref()))
+            .collect();
+        assert_eq!(status["clean.rs"], None);
+        assert_eq!(status["edited.rs"], Some("M"));
+        assert_eq!(status["nested"], Some("M"));
+        assert_eq!(status["new.rs"], Some("??"));
+        assert_eq!(status["fresh"], Some("??"));
+        assert_eq!(status["staged.rs"], Some("A"));
+        assert!(!status.contains_key("top.txt"));
+    }
+
+    #[tokio::test]
+    async fn git_status_is_none_outside_a_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "").unwrap();
+        assert!(DirList::git_statuses(&dir.path().canonicalize().unwrap()).is_none());
+    }
+
+    #[tokio::test]
+    async fn readme_is_shown_with_the_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("docs")).unwrap();
+        std::fs::create_dir_all(dir.path().join("empty")).unwrap();
+        std::fs::write(dir.path().join("Readme.md"), "# Hi\n<script>x()</script>").unwrap();
+        std::fs::write(dir.path().join("README.txt"), "shadowed").unwrap();
+        std::fs::write(dir.path().join("docs/README"), "plain").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let readme = |path: &str, options: &[(&str, &str)]| {
+            let resource = Resource::new(path.to_string(), true);
+            let request = ProjectionRequest::new(
+                options
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            );
+            let ws = ws.clone();
+            async move {
+                let ProjectionOutput::DirectoryList { readme, .. } =
+                    DirList.project(&resource, &ws, &request).await.unwrap()
+                else {
+                    panic!("expected DirectoryList");
+                };
+                readme
+            }
+        };
+
+        let root = readme("", &[]).await.unwrap();
+        assert_eq!(root.path, "Readme.md");
+        let html = root.html.unwrap();
+        assert!(html.contains("<h1") && !html.contains("<script"), "{html}");
+        assert!(root.raw.is_none());
+
+        let docs = readme("docs", &[]).await.unwrap();
+        assert_eq!(docs.path, "docs/README");
+        assert_eq!((docs.html, docs.raw.as_deref()), (None, Some("plain")));
+
+        assert!(readme("empty", &[]).await.is_none());
+        assert!(readme("", &[("readme", "false")]).await.is_none());
+        assert!(readme("", &[("offset", "1")]).await.is_none());
     }
 }
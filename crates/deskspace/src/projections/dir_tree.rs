@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+use crate::projection::{
+    Projection, ProjectionOutput, ProjectionRequest, Resource, Result, TreeNode,
+};
+use crate::store::FileStore;
+
+/// Levels walked when the request doesn't say.
+const DEFAULT_DEPTH: usize = 3;
+/// Deepest walk allowed; larger `depth` values are clamped.
+const MAX_DEPTH: usize = 8;
+/// Entries kept per directory when the request doesn't say.
+const DEFAULT_PER_DIR: usize = 200;
+/// Largest `per_dir` allowed; larger values are clamped.
+const MAX_PER_DIR: usize = 1000;
+/// Nodes in the whole tree. Once spent, remaining directories are marked
+/// truncated instead of listed, so wide trees can't blow up the response.
+const MAX_NODES: usize = 10_000;
+
+pub struct DirTree;
+
+/// Limits and bookkeeping shared across one walk.
+struct Walk<'a> {
+    workspace: &'a dyn FileStore,
+    max_depth: usize,
+    per_dir: usize,
+    hidden: bool,
+    budget: usize,
+    /// Canonical paths of directories already listed. Symlinks are followed
+    /// only to directories not in here, so link cycles end after one lap.
+    seen: HashSet<PathBuf>,
+}
+
+impl Walk<'_> {
+    /// Children of `path`, descending while `depth < max_depth`. Returns the
+    /// nodes and whether any entries were dropped.
+    fn children<'s>(
+        &'s mut self,
+        path: &'s str,
+        depth: usize,
+    ) -> BoxFuture<'s, Result<(Vec<TreeNode>, bool)>> {
+        Box::pin(async move {
+            let mut entries = self.workspace.read_dir(Path::new(path)).await?;
+            if !self.hidden {
+                entries.retain(|e| !e.name.starts_with('.'));
+            }
+            let keep = entries.len().min(self.per_dir).min(self.budget);
+            let mut truncated = keep < entries.len();
+            entries.truncate(keep);
+            self.budget -= keep;
+
+            let mut nodes = Vec::with_capacity(entries.len());
+            for e in entries {
+                let child = if path.is_empty() {
+                    e.name.clone()
+                } else {
+                    format!("{path}/{}", e.name)
+                };
+                let mut node = TreeNode {
+                    name: e.name,
+                    path: child,
+                    is_dir: e.is_dir,
+                    size: e.size,
+                    is_symlink: e.is_symlink,
+                    children: None,
+                    truncated: false,
+                };
+                if e.is_dir && !e.outside_root {
+                    if depth + 1 >= self.max_depth || self.budget == 0 {
+                        node.truncated = true;
+                    } else if self.first_visit(&node.path) {
+                        let (children, cut) = self.children(&node.path, depth + 1).await?;
+                        node.children = Some(children);
+                        node.truncated = cut;
+                    }
+                }
+                truncated |= node.truncated;
+                nodes.push(node);
+            }
+            Ok((nodes, truncated))
+        })
+    }
+
+    fn first_visit(&mut self, path: &str) -> bool {
+        match canonical(self.workspace, path) {
+            Ok(canonical) => self.seen.insert(canonical),
+            Err(e) => {
+                tracing::debug!("dir.tree: not descending into {path}: {e}");
+                false
+            }
+        }
+    }
+}
+
+/// Key telling directories apart in `Walk::seen`: the canonical location on
+/// disk, or just the path in a store without symlinks.
+fn canonical(workspace: &dyn FileStore, path: &str) -> crate::workspace::Result<PathBuf> {
+    match workspace.local() {
+        Some(local) => local.resolve(path),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+#[async_trait]
+impl Projection for DirTree {
+    fn id(&self) -> &str {
+        "dir.tree"
+    }
+
+    fn name(&self) -> &str {
+        "Directory Tree"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Nested view of the directory a few levels deep")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("tree")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            0.5
+        } else {
+            0.0
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let depth = request
+            .parse::<usize>("depth")?
+            .unwrap_or(DEFAULT_DEPTH)
+            .clamp(1, MAX_DEPTH);
+        let mut walk = Walk {
+            workspace,
+            max_depth: depth,
+            per_dir: request
+                .parse::<usize>("per_dir")?
+                .unwrap_or(DEFAULT_PER_DIR)
+                .clamp(1, MAX_PER_DIR),
+            hidden: request.flag("hidden")?.unwrap_or(true),
+            budget: MAX_NODES,
+            seen: HashSet::from([canonical(workspace, &resource.path)?]),
+        };
+        let (children, truncated) = walk.children(&resource.path, 0).await?;
+        Ok(ProjectionOutput::Tree {
+            root: resource.path.clone(),
+            depth,
+            children,
+            truncated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::workspace::Workspace;
+
+    async fn tree(ws: &Workspace, options: &[(&str, &str)]) -> (Vec<TreeNode>, bool) {
+        let request = ProjectionRequest::new(
+            options
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        );
+        let resource = Resource::new(String::new(), true);
+        let ProjectionOutput::Tree {
+            children,
+            truncated,
+            ..
+        } = DirTree.project(&resource, ws, &request).await.unwrap()
+        else {
+            panic!("expected Tree");
+        };
+        (children, truncated)
+    }
+
+    #[tokio::test]
+    async fn walks_to_the_requested_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        std::fs::write(dir.path().join("a/b/c/deep.txt"), "").unwrap();
+        std::fs::write(dir.path().join("a/one.txt"), "").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let (children, truncated) = tree(&ws, &[("depth", "2")]).await;
+        assert!(truncated);
+        let a = &children[0];
+        assert_eq!(a.path, "a");
+        let inner = a.children.as_ref().unwrap();
+        let b = inner.iter().find(|n| n.name == "b").unwrap();
+        assert_eq!(b.path, "a/b");
+        assert!(b.children.is_none() && b.truncated);
+
+        let (children, truncated) = tree(&ws, &[("depth", "4")]).await;
+        assert!(!truncated);
+        let b = &children[0].children.as_ref().unwrap()[0];
+        let c = &b.children.as_ref().unwrap()[0];
+        assert_eq!(c.children.as_ref().unwrap()[0].path, "a/b/c/deep.txt");
+    }
+
+    #[tokio::test]
+    async fn limits_entries_per_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..5 {
+            std::fs::write(dir.path().join(format!("{i}.txt")), "").unwrap();
+        }
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let (children, truncated) = tree(&ws, &[("per_dir", "3")]).await;
+        assert_eq!((children.len(), truncated), (3, true));
+        let (children, truncated) = tree(&ws, &[("hidden", "false")]).await;
+        assert_eq!((children.len(), truncated), (5, false));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlink_cycles_are_not_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("a")).unwrap();
+        std::os::unix::fs::symlink("..", dir.path().join("a/up")).unwrap();
+        std::os::unix::fs::symlink("../a", dir.path().join("a/self")).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let (children, _) = tree(&ws, &[("depth", "8")]).await;
+        let a = children[0].children.as_ref().unwrap();
+        assert_eq!(a.len(), 2);
+        for link in a {
+            assert!(link.is_symlink && link.is_dir);
+            assert!(link.children.is_none(), "{} was followed", link.name);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use async_trait::async_trait;
+
+use crate::projection::{
+    EnvEntry, Projection, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+/// Shown in place of a value that wasn't revealed. Fixed, so the mask
+/// doesn't give away the value's length.
+const MASK: &str = "****";
+
+pub struct EnvView;
+
+impl EnvView {
+    /// Whether `name` is a dotenv file: `.env`, `.env.local`, `prod.env`, ...
+    fn is_env_name(name: &str) -> bool {
+        name == ".env" || name.starts_with(".env.") || name.ends_with(".env")
+    }
+
+    fn valid_key(key: &str) -> bool {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+    }
+
+    /// Byte offset of the quote closing a value that opened with `quote`.
+    /// Backslash escapes are honoured inside double quotes only.
+    fn closing_quote(body: &str, quote: char) -> Option<usize> {
+        let mut escaped = false;
+        for (i, c) in body.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quote == '"' => escaped = true,
+                c if c == quote => return Some(i),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn unescape(body: &str) -> String {
+        let mut out = String::with_capacity(body.len());
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some('t') => out.push('\t'),
+                Some(c @ ('"' | '\\' | '$')) => out.push(c),
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            }
+        }
+        out
+    }
+
+    /// Parse `KEY=VALUE` lines, skipping blanks, `#` comments and lines that
+    /// aren't assignments. Handles an `export ` prefix, single- and
+    /// double-quoted values (which may span lines) and trailing comments
+    /// after unquoted values. Values come back unmasked.
+    fn parse(source: &str) -> Vec<EnvEntry> {
+        let mut entries = Vec::new();
+        let mut lines = source.lines().enumerate();
+        while let Some((index, line)) = lines.next() {
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (exported, rest) = match line.strip_prefix("export") {
+                Some(rest) if rest.starts_with(char::is_whitespace) => (true, rest.trim_start()),
+                _ => (false, line),
+            };
+            let Some((key, raw)) = rest.split_once('=') else {
+                continue;
+            };
+            let key = key.trim_end();
+            if !Self::valid_key(key) {
+                continue;
+            }
+            let raw = raw.trim_start();
+            let value = match raw.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let mut body = raw[1..].to_string();
+                    loop {
+                        if let Some(end) = Self::closing_quote(&body, quote) {
+                            body.truncate(end);
+                            break;
+                        }
+                        // Unclosed: the value continues on the next line
+                        match lines.next() {
+                            Some((_, next)) => {
+                                body.push('\n');
+                                body.push_str(next);
+                            }
+                            None => break,
+                        }
+                    }
+                    if quote == '"' {
+                        Self::unescape(&body)
+                    } else {
+                        body
+                    }
+                }
+                _ => {
+                    // `#` starts a comment only after whitespace, so `a#b` stays
+                    let end = raw
+                        .char_indices()
+                        .find(|&(i, c)| {
+                            c == '#' && (i == 0 || raw[..i].ends_with(char::is_whitespace))
+                        })
+                        .map_or(raw.len(), |(i, _)| i);
+                    raw[..end].trim_end().to_string()
+                }
+            };
+            entries.push(EnvEntry {
+                key: key.to_string(),
+                value,
+                masked: false,
+                exported,
+                line: index + 1,
+            });
+        }
+        entries
+    }
+
+    /// Replace the values of entries not named in `reveal` (a comma-separated
+    /// key list, or `all`) with `MASK`. Empty values are left as they are.
+    fn mask(entries: &mut [EnvEntry], reveal: Option<&str>) {
+        let reveal: Vec<&str> = reveal
+            .map(|r| r.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        if reveal.contains(&"all") {
+            return;
+        }
+        for entry in entries {
+            if !entry.value.is_empty() && !reveal.contains(&entry.key.as_str()) {
+                entry.value = MASK.to_string();
+                entry.masked = true;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Projection for EnvView {
+    fn id(&self) -> &str {
+        "env.view"
+    }
+
+    fn name(&self) -> &str {
+        "Environment"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Variables with their values masked")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("lock")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if !resource.is_dir && Self::is_env_name(resource.file_name()) {
+            0.9
+        } else {
+            0.0
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let source = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        let mut entries = Self::parse(&source);
+        Self::mask(&mut entries, request.get("reveal"));
+        Ok(ProjectionOutput::Env { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(entries: &[EnvEntry]) -> Vec<(&str, &str)> {
+        entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_quotes_comments_and_exports() {
+        let source = "\
+# database
+DB_HOST=localhost  # trailing comment
+export DB_USER = admin
+DB_PASS=\"p@ss \\\"word\\\"\\n\"
+TOKEN='raw $value \\n'
+COLOR=#ffffff
+URL=http://a/b#frag
+CERT=\"line one
+line two\"
+not an assignment
+=nokey
+EMPTY=
+";
+        let entries = EnvView::parse(source);
+        assert_eq!(
+            pairs(&entries),
+            [
+                ("DB_HOST", "localhost"),
+                ("DB_USER", "admin"),
+                ("DB_PASS", "p@ss \"word\"\n"),
+                ("TOKEN", "raw $value \\n"),
+                ("COLOR", ""),
+                ("URL", "http://a/b#frag"),
+                ("CERT", "line one\nline two"),
+                ("EMPTY", ""),
+            ]
+        );
+        assert!(entries[1].exported && !entries[0].exported);
+        assert_eq!(entries[6].line, 8);
+        assert_eq!(entries[7].line, 12);
+    }
+
+    #[test]
+    fn masks_unless_revealed() {
+        let source = "A=1\nB=2\nC=\n";
+        let mut entries = EnvView::parse(source);
+        EnvView::mask(&mut entries, None);
+        assert_eq!(pairs(&entries), [("A", MASK), ("B", MASK), ("C", "")]);
+        assert!(entries[0].masked && !entries[2].masked);
+
+        let mut entries = EnvView::parse(source);
+        EnvView::mask(&mut entries, Some("B"));
+        assert_eq!(pairs(&entries), [("A", MASK), ("B", "2"), ("C", "")]);
+
+        let mut entries = EnvView::parse(source);
+        EnvView::mask(&mut entries, Some("all"));
+        assert_eq!(pairs(&entries), [("A", "1"), ("B", "2"), ("C", "")]);
+    }
+
+    #[test]
+    fn claims_dotenv_names() {
+        let confidence = |path: &str| EnvView.confidence(&Resource::new(path.into(), false));
+        assert_eq!(confidence(".env"), 0.9);
+        assert_eq!(confidence("app/.env.local"), 0.9);
+        assert_eq!(confidence("prod.env"), 0.9);
+        assert_eq!(confidence(".envrc"), 0.0);
+        assert_eq!(confidence("environment.txt"), 0.0);
+    }
+}
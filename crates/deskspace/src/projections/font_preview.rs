@@ -0,0 +1,278 @@
+use std::io::Read;
+use std::path::Path;
+
+use async_trait::async_trait;
+use ttf_parser::name::{self, name_id};
+
+use crate::projection::{
+    raw_url, Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "woff", "woff2"];
+
+/// Windows platform, US English: the name record most fonts ship first.
+const LANGUAGE_EN_US: u16 = 0x0409;
+
+/// Size of the WOFF 1.0 header and of each table directory entry.
+const WOFF_HEADER_LEN: usize = 44;
+const WOFF_TABLE_ENTRY_LEN: usize = 20;
+
+pub struct FontPreview;
+
+/// Metadata read from the font's `name` and `maxp` tables.
+#[derive(Debug, Default, PartialEq)]
+struct FontMeta {
+    family: Option<String>,
+    style: Option<String>,
+    glyph_count: Option<u16>,
+}
+
+impl FontPreview {
+    fn read_meta(data: &[u8]) -> Result<FontMeta> {
+        match data.get(..4) {
+            Some(b"wOFF") => Self::read_woff(data),
+            // WOFF2 tables are one Brotli stream, partly transformed; the
+            // browser can still load it, so only the metadata is missing.
+            Some(b"wOF2") => Ok(FontMeta::default()),
+            _ => {
+                let face = ttf_parser::Face::parse(data, 0)
+                    .map_err(|e| ProjectionError::Other(format!("unreadable font: {e}")))?;
+                let (family, style) = Self::names(face.names());
+                Ok(FontMeta {
+                    family,
+                    style,
+                    glyph_count: Some(face.number_of_glyphs()),
+                })
+            }
+        }
+    }
+
+    /// WOFF 1.0 wraps each sfnt table in optional zlib compression, so pull
+    /// out just the two tables we need and parse them directly.
+    fn read_woff(data: &[u8]) -> Result<FontMeta> {
+        let invalid = || ProjectionError::Other("unreadable font: malformed WOFF".to_string());
+        let names = Self::woff_table(data, b"name").map_err(|_| invalid())?;
+        let maxp = Self::woff_table(data, b"maxp").map_err(|_| invalid())?;
+        let (family, style) = match names.as_deref().and_then(name::Table::parse) {
+            Some(table) => Self::names(table.names),
+            None => (None, None),
+        };
+        // numGlyphs follows the 4-byte version in every maxp revision
+        let glyph_count = maxp
+            .as_deref()
+            .and_then(|t| t.get(4..6))
+            .map(|n| u16::from_be_bytes([n[0], n[1]]));
+        Ok(FontMeta {
+            family,
+            style,
+            glyph_count,
+        })
+    }
+
+    /// Decompressed contents of one table from a WOFF 1.0 file, or `None`
+    /// when the font has no such table.
+    fn woff_table(data: &[u8], tag: &[u8; 4]) -> std::io::Result<Option<Vec<u8>>> {
+        let malformed = || std::io::Error::from(std::io::ErrorKind::InvalidData);
+        let be32 = |at: usize| -> std::io::Result<usize> {
+            let b = data.get(at..at + 4).ok_or_else(malformed)?;
+            Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        };
+        let num_tables = data
+            .get(12..14)
+            .map(|n| u16::from_be_bytes([n[0], n[1]]) as usize)
+            .ok_or_else(malformed)?;
+        for i in 0..num_tables {
+            let entry = WOFF_HEADER_LEN + i * WOFF_TABLE_ENTRY_LEN;
+            if data.get(entry..entry + 4).ok_or_else(malformed)? != tag {
+                continue;
+            }
+            let offset = be32(entry + 4)?;
+            let comp_len = be32(entry + 8)?;
+            let orig_len = be32(entry + 12)?;
+            let stored = data
+                .get(offset..offset.saturating_add(comp_len))
+                .ok_or_else(malformed)?;
+            if comp_len >= orig_len {
+                return Ok(Some(stored.to_vec()));
+            }
+            let mut table = Vec::with_capacity(orig_len);
+            flate2::read::ZlibDecoder::new(stored)
+                .take(orig_len as u64)
+                .read_to_end(&mut table)?;
+            return Ok(Some(table));
+        }
+        Ok(None)
+    }
+
+    /// Family and style names, preferring the typographic names (which group
+    /// weights under one family) and US English records.
+    fn names(names: name::Names) -> (Option<String>, Option<String>) {
+        let pick = |ids: [u16; 2]| {
+            ids.into_iter().find_map(|id| {
+                let mut candidates = names.into_iter().filter(|n| n.name_id == id);
+                let mut fallback = None;
+                for n in candidates.by_ref() {
+                    let Some(s) = n.to_string().filter(|s| !s.trim().is_empty()) else {
+                        continue;
+                    };
+                    if n.language_id == LANGUAGE_EN_US {
+                        return Some(s);
+                    }
+                    fallback.get_or_insert(s);
+                }
+                fallback
+            })
+        };
+        (
+            pick([name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]),
+            pick([name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY]),
+        )
+    }
+}
+
+#[async_trait]
+impl Projection for FontPreview {
+    fn id(&self) -> &str {
+        "font.preview"
+    }
+
+    fn name(&self) -> &str {
+        "Font"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Font family, style and sample text")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("font")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match &resource.extension {
+            Some(ext) if FONT_EXTENSIONS.contains(&ext.as_str()) => 0.9,
+            _ if resource
+                .mime_type
+                .as_deref()
+                .is_some_and(|m| m.starts_with("font/")) =>
+            {
+                0.9
+            }
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let data = workspace.read(Path::new(&resource.path)).await?;
+        let meta = Self::read_meta(&data)?;
+        Ok(ProjectionOutput::Font {
+            family: meta.family,
+            style: meta.style,
+            glyph_count: meta.glyph_count,
+            raw_url: raw_url(workspace, &resource.path).await,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `name` table with one Windows/Unicode BMP record per `(id, lang, text)`.
+    fn name_table(records: &[(u16, u16, &str)]) -> Vec<u8> {
+        let mut strings = Vec::new();
+        let mut out = Vec::new();
+        out.extend_from_slice(&0u16.to_be_bytes()); // format
+        out.extend_from_slice(&(records.len() as u16).to_be_bytes());
+        out.extend_from_slice(&(6 + 12 * records.len() as u16).to_be_bytes());
+        for (id, lang, text) in records {
+            let encoded: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            for field in [3, 1, *lang, *id, encoded.len() as u16, strings.len() as u16] {
+                out.extend_from_slice(&field.to_be_bytes());
+            }
+            strings.extend_from_slice(&encoded);
+        }
+        out.extend_from_slice(&strings);
+        out
+    }
+
+    /// WOFF 1.0 file holding `tables`, zlib-compressing the ones flagged.
+    fn woff(tables: &[(&[u8; 4], Vec<u8>, bool)]) -> Vec<u8> {
+        let mut out = vec![0u8; WOFF_HEADER_LEN + tables.len() * WOFF_TABLE_ENTRY_LEN];
+        out[..4].copy_from_slice(b"wOFF");
+        out[12..14].copy_from_slice(&(tables.len() as u16).to_be_bytes());
+        for (i, (tag, data, compress)) in tables.iter().enumerate() {
+            let mut stored = data.clone();
+            if *compress {
+                let mut enc =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                std::io::Write::write_all(&mut enc, data).unwrap();
+                stored = enc.finish().unwrap();
+                assert!(stored.len() < data.len(), "WOFF stores such tables raw");
+            }
+            let entry = WOFF_HEADER_LEN + i * WOFF_TABLE_ENTRY_LEN;
+            let offset = out.len() as u32;
+            out[entry..entry + 4].copy_from_slice(*tag);
+            out[entry + 4..entry + 8].copy_from_slice(&offset.to_be_bytes());
+            out[entry + 8..entry + 12].copy_from_slice(&(stored.len() as u32).to_be_bytes());
+            out[entry + 12..entry + 16].copy_from_slice(&(data.len() as u32).to_be_bytes());
+            out.extend_from_slice(&stored);
+        }
+        out
+    }
+
+    fn maxp(glyphs: u16) -> Vec<u8> {
+        let mut t = 0x0000_5000u32.to_be_bytes().to_vec();
+        t.extend_from_slice(&glyphs.to_be_bytes());
+        t
+    }
+
+    #[test]
+    fn woff_reads_compressed_name_and_maxp() {
+        let description = "A typeface for user interfaces. ".repeat(8);
+        let names = name_table(&[
+            (1, LANGUAGE_EN_US, "Inter"),
+            (2, LANGUAGE_EN_US, "Bold"),
+            (10, LANGUAGE_EN_US, &description),
+        ]);
+        let data = woff(&[(b"maxp", maxp(2548), false), (b"name", names, true)]);
+        let meta = FontPreview::read_meta(&data).unwrap();
+        assert_eq!(meta.family.as_deref(), Some("Inter"));
+        assert_eq!(meta.style.as_deref(), Some("Bold"));
+        assert_eq!(meta.glyph_count, Some(2548));
+    }
+
+    #[test]
+    fn typographic_family_and_english_preferred() {
+        let names = name_table(&[
+            (1, LANGUAGE_EN_US, "Inter Bold"),
+            (16, 0x0407, "Inter (de)"),
+            (16, LANGUAGE_EN_US, "Inter"),
+        ]);
+        let table = name::Table::parse(&names).unwrap();
+        let (family, style) = FontPreview::names(table.names);
+        assert_eq!(family.as_deref(), Some("Inter"));
+        assert_eq!(style, None);
+    }
+
+    #[test]
+    fn woff2_has_no_metadata() {
+        let meta = FontPreview::read_meta(b"wOF2\0\0\0\0").unwrap();
+        assert_eq!(meta, FontMeta::default());
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        assert!(FontPreview::read_meta(b"definitely not a font").is_err());
+        assert!(FontPreview::read_meta(b"wOFF\0\0\0\0\0\0\0\0\0\x01").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt::Write;
+
+use async_trait::async_trait;
+
+use crate::projection::{Projection, ProjectionOutput, ProjectionRequest, Resource, Result};
+use crate::store::FileStore;
+
+/// Number of bytes read from the start of the file.
+const MAX_BYTES: u64 = 64 * 1024;
+
+/// Universal fallback viewer: a hex dump of the start of any file.
+pub struct HexView;
+
+impl HexView {
+    fn encode(data: &[u8]) -> (String, String) {
+        let mut hex = String::with_capacity(data.len() * 2);
+        for b in data {
+            let _ = write!(hex, "{b:02x}");
+        }
+        let ascii = data
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        (hex, ascii)
+    }
+}
+
+#[async_trait]
+impl Projection for HexView {
+    fn id(&self) -> &str {
+        "hex.view"
+    }
+
+    fn name(&self) -> &str {
+        "Hex"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Raw bytes as hex and ASCII")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("binary")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            0.0
+        } else {
+            0.1
+        }
+    }
+
+    fn reads_whole_file(&self) -> bool {
+        false
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let path = std::path::Path::new(&resource.path);
+        let total_size = workspace.stat(path).await?.len;
+        let data = workspace.read_at(path, 0, MAX_BYTES).await?;
+        let (bytes, ascii) = Self::encode(&data);
+        Ok(ProjectionOutput::Hex {
+            offset: 0,
+            truncated: total_size > data.len() as u64,
+            bytes,
+            ascii,
+            total_size,
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use std::borrow::Cow;
+
+use async_trait::async_trait;
+
+use crate::projection::{
+    Projection, ProjectionOutput, ProjectionRequest, RenderFormat, Resource, Result,
+};
+use crate::store::FileStore;
+
+pub struct HtmlPreview;
+
+impl HtmlPreview {
+    /// Raw URL for `url` as written in the page at `dir`: `..` and `.` are
+    /// folded in, a leading `/` means the workspace root and any query is
+    /// dropped. `None` for URLs that climb above the root.
+    fn raw_url(dir: &str, url: &str) -> Option<String> {
+        if url.starts_with('#') || url.starts_with("//") {
+            return Some(url.to_string());
+        }
+        let (path, fragment) = match url.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (url, None),
+        };
+        let path = path.split('?').next().unwrap_or(path);
+        let (base, path) = match path.strip_prefix('/') {
+            Some(rest) => ("", rest),
+            None => (dir, path),
+        };
+        let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+        for part in path.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    parts.pop()?;
+                }
+                part => parts.push(part),
+            }
+        }
+        let mut raw = format!("/api/files/raw/{}", parts.join("/"));
+        if let Some(fragment) = fragment {
+            raw.push('#');
+            raw.push_str(fragment);
+        }
+        Some(raw)
+    }
+
+    /// Sanitize `raw` with ammonia's defaults: no scripts, frames, styles or
+    /// event handlers, and only safe URL schemes. With `rewrite_urls`,
+    /// relative URLs are pointed at the raw endpoint, resolved from `dir`,
+    /// so images and stylesheets in the workspace load.
+    fn sanitize(raw: &str, dir: &str, rewrite_urls: bool) -> String {
+        let mut sanitizer = ammonia::Builder::default();
+        if rewrite_urls {
+            sanitizer.url_relative(ammonia::UrlRelative::Custom(Box::new(RawUrls(
+                dir.to_string(),
+            ))));
+        }
+        sanitizer.clean(raw).to_string()
+    }
+}
+
+/// Rewrites relative URLs in a page under the held directory to raw URLs.
+struct RawUrls(String);
+
+impl ammonia::UrlRelativeEvaluate<'_> for RawUrls {
+    fn evaluate<'url>(&self, url: &'url str) -> Option<Cow<'url, str>> {
+        HtmlPreview::raw_url(&self.0, url).map(Cow::Owned)
+    }
+}
+
+#[async_trait]
+impl Projection for HtmlPreview {
+    fn id(&self) -> &str {
+        "html.preview"
+    }
+
+    fn name(&self) -> &str {
+        "HTML Preview"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Sanitized render of the page, with its source")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("globe")
+    }
+
+    fn formats(&self) -> &[RenderFormat] {
+        &[RenderFormat::Html]
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("html" | "htm" | "xhtml") => 0.9,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let raw = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        let dir = resource.path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let rewrite_urls = request.flag("rewrite_urls")?.unwrap_or(true);
+        let safe_html = Self::sanitize(&raw, dir, rewrite_urls);
+        Ok(ProjectionOutput::Html { raw, safe_html })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_scripts_frames_and_handlers() {
+        let page = r#"<h1 onclick="steal()">Hi</h1>
+<script>alert(1)</script>
+<iframe src="https://evil.example"></iframe>
+<a href="javascript:alert(1)">x</a>
+<img src="https://example.com/a.png" onerror="steal()">"#;
+        let safe = HtmlPreview::sanitize(page, "", true);
+        for banned in ["<script", "<iframe", "onclick", "onerror", "javascript:"] {
+            assert!(!safe.contains(banned), "{banned} survived: {safe}");
+        }
+        assert!(safe.contains("<h1>Hi</h1>"));
+        assert!(safe.contains(r#"src="https://example.com/a.png""#));
+    }
+
+    #[test]
+    fn relative_urls_point_at_the_raw_endpoint() {
+        let page = r##"<img src="img/logo.png"><img src="../shared/bg.jpg?v=2">
+<a href="/index.html#top">home</a><a href="#section">jump</a>
+<img src="../../../etc/passwd">"##;
+        let safe = HtmlPreview::sanitize(page, "site/docs", true);
+        assert!(safe.contains(r#"src="/api/files/raw/site/docs/img/logo.png""#));
+        assert!(safe.contains(r#"src="/api/files/raw/site/shared/bg.jpg""#));
+        assert!(safe.contains(r##"href="/api/files/raw/index.html#top""##));
+        assert!(safe.contains(r##"href="#section""##));
+        assert!(!safe.contains("passwd"), "{safe}");
+
+        let kept = HtmlPreview::sanitize(page, "site/docs", false);
+        assert!(kept.contains(r#"src="img/logo.png""#));
+    }
+}
@@ -1,12 +1,115 @@
+use std::io::BufReader;
+use std::path::Path;
+
 use async_trait::async_trait;
+use exif::{In, Tag, Value};
 
-use crate::projection::{Projection, ProjectionOutput, Resource, Result};
-use crate::workspace::Workspace;
+use crate::projection::{
+    raw_url, GpsPosition, ImageMeta, Projection, ProjectionOutput, ProjectionRequest, Resource,
+    Result,
+};
+use crate::store::FileStore;
 
 const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg"];
 
+/// Formats that can carry an EXIF block.
+const EXIF_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+/// Sniffed types the browser can display, for files without a known
+/// extension.
+const IMAGE_MIME_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+const EXIF_MIME_TYPES: &[&str] = &["image/png", "image/jpeg", "image/webp"];
+
 pub struct ImagePreview;
 
+impl ImagePreview {
+    /// Read the EXIF block from the image container without decoding pixels.
+    /// Missing or unreadable metadata yields `None`; damaged fields are
+    /// skipped and whatever parsed cleanly is kept.
+    fn read_metadata(path: &Path) -> Option<ImageMeta> {
+        let file = std::fs::File::open(path).ok()?;
+        let exif = exif::Reader::new()
+            .continue_on_error(true)
+            .read_from_container(&mut BufReader::new(file))
+            .or_else(|e| {
+                e.distill_partial_result(|errors| {
+                    tracing::debug!(
+                        "skipping {} corrupt EXIF fields in {}",
+                        errors.len(),
+                        path.display()
+                    );
+                })
+            });
+        match exif {
+            Ok(exif) => Some(Self::image_meta(&exif)),
+            Err(exif::Error::NotFound(_)) => None,
+            Err(e) => {
+                tracing::debug!("no EXIF for {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    fn image_meta(exif: &exif::Exif) -> ImageMeta {
+        let ascii = |tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(parts) => {
+                let text = String::from_utf8_lossy(parts.first()?).trim().to_string();
+                (!text.is_empty()).then_some(text)
+            }
+            _ => None,
+        };
+        let uint = |tag| exif.get_field(tag, In::PRIMARY)?.value.get_uint(0);
+
+        ImageMeta {
+            make: ascii(Tag::Make),
+            model: ascii(Tag::Model),
+            width: uint(Tag::PixelXDimension).or_else(|| uint(Tag::ImageWidth)),
+            height: uint(Tag::PixelYDimension).or_else(|| uint(Tag::ImageLength)),
+            orientation: uint(Tag::Orientation).and_then(|o| u16::try_from(o).ok()),
+            gps: Self::gps(exif),
+        }
+    }
+
+    fn gps(exif: &exif::Exif) -> Option<GpsPosition> {
+        // Degrees, minutes, seconds; negated for the southern/western refs.
+        let coordinate = |tag, ref_tag, negative: u8| {
+            let Value::Rational(dms) = &exif.get_field(tag, In::PRIMARY)?.value else {
+                return None;
+            };
+            let [deg, min, sec] = dms.as_slice() else {
+                return None;
+            };
+            let value = deg.to_f64() + min.to_f64() / 60.0 + sec.to_f64() / 3600.0;
+            if !value.is_finite() {
+                return None;
+            }
+            let sign = match &exif.get_field(ref_tag, In::PRIMARY)?.value {
+                Value::Ascii(parts) if parts.first()?.first() == Some(&negative) => -1.0,
+                _ => 1.0,
+            };
+            Some(sign * value)
+        };
+        let latitude = coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+        let longitude = coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+        let altitude = match exif
+            .get_field(Tag::GPSAltitude, In::PRIMARY)
+            .map(|f| &f.value)
+        {
+            Some(Value::Rational(alt)) => alt.first().map(|a| a.to_f64()),
+            _ => None,
+        };
+        let below_sea_level = exif
+            .get_field(Tag::GPSAltitudeRef, In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0))
+            == Some(1);
+        let altitude = altitude.map(|a| if below_sea_level { -a } else { a });
+        Some(GpsPosition {
+            latitude,
+            longitude,
+            altitude: altitude.filter(|a| a.is_finite()),
+        })
+    }
+}
+
 #[async_trait]
 impl Projection for ImagePreview {
     fn id(&self) -> &str {
@@ -17,26 +120,143 @@ impl Projection for ImagePreview {
         "Image Preview"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("Image with EXIF details")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("image")
+    }
+
     fn confidence(&self, resource: &Resource) -> f32 {
         if resource.is_dir {
             return 0.0;
         }
         match &resource.extension {
+            // Behind svg.preview, which inlines a sanitized copy
+            Some(ext) if ext == "svg" => 0.8,
             Some(ext) if IMAGE_EXTENSIONS.contains(&ext.as_str()) => 1.0,
+            _ if resource.mime_is(IMAGE_MIME_TYPES) => 0.9,
             _ => 0.0,
         }
     }
 
+    fn reads_whole_file(&self) -> bool {
+        false
+    }
+
     async fn project(
         &self,
         resource: &Resource,
-        _workspace: &Workspace,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
     ) -> Result<ProjectionOutput> {
         let mime_type = mime_guess::from_path(&resource.path)
             .first()
             .map(|m| m.to_string())
+            .or_else(|| resource.mime_type.clone())
             .unwrap_or_else(|| "application/octet-stream".to_string());
-        let url = format!("/api/files/raw/{}", resource.path);
-        Ok(ProjectionOutput::Image { mime_type, url })
+        let url = raw_url(workspace, &resource.path).await;
+
+        let has_exif = resource
+            .extension
+            .as_deref()
+            .is_some_and(|ext| EXIF_EXTENSIONS.contains(&ext))
+            || resource.mime_is(EXIF_MIME_TYPES);
+        let metadata = if has_exif {
+            let path = workspace.local_path(&resource.path)?;
+            tokio::task::spawn_blocking(move || Self::read_metadata(&path))
+                .await
+                .unwrap_or(None)
+        } else {
+            None
+        };
+
+        Ok(ProjectionOutput::Image {
+            mime_type,
+            url,
+            metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{Field, Rational};
+
+    fn exif_from(fields: &[Field]) -> exif::Exif {
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut buf = std::io::Cursor::new(Vec::new());
+        writer.write(&mut buf, false).unwrap();
+        exif::Reader::new().read_raw(buf.into_inner()).unwrap()
+    }
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    fn rational(num: u32, denom: u32) -> Rational {
+        Rational { num, denom }
+    }
+
+    #[test]
+    fn image_meta_reads_camera_and_gps() {
+        let exif = exif_from(&[
+            field(Tag::Make, Value::Ascii(vec![b"Canon".to_vec()])),
+            field(Tag::Model, Value::Ascii(vec![b"EOS R5".to_vec()])),
+            field(Tag::Orientation, Value::Short(vec![6])),
+            field(Tag::PixelXDimension, Value::Long(vec![4000])),
+            field(Tag::PixelYDimension, Value::Long(vec![3000])),
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"S".to_vec()])),
+            field(
+                Tag::GPSLatitude,
+                Value::Rational(vec![rational(33, 1), rational(30, 1), rational(0, 1)]),
+            ),
+            field(Tag::GPSLongitudeRef, Value::Ascii(vec![b"E".to_vec()])),
+            field(
+                Tag::GPSLongitude,
+                Value::Rational(vec![rational(151, 1), rational(15, 1), rational(0, 1)]),
+            ),
+        ]);
+        let meta = ImagePreview::image_meta(&exif);
+        assert_eq!(meta.make.as_deref(), Some("Canon"));
+        assert_eq!(meta.model.as_deref(), Some("EOS R5"));
+        assert_eq!(meta.orientation, Some(6));
+        assert_eq!((meta.width, meta.height), (Some(4000), Some(3000)));
+        let gps = meta.gps.unwrap();
+        assert_eq!(gps.latitude, -33.5);
+        assert_eq!(gps.longitude, 151.25);
+        assert_eq!(gps.altitude, None);
+    }
+
+    #[test]
+    fn sniffed_mime_type_claims_extensionless_images() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let sniffed = infer::get(png).map(|t| t.mime_type().to_string());
+        let resource = Resource::new("screenshot".to_string(), false).with_mime_type(sniffed);
+        assert_eq!(ImagePreview.confidence(&resource), 0.9);
+
+        let named = Resource::new("shot.png".to_string(), false);
+        assert_eq!(ImagePreview.confidence(&named), 1.0);
+        let unknown = Resource::new("notes".to_string(), false);
+        assert_eq!(ImagePreview.confidence(&unknown), 0.0);
+    }
+
+    #[test]
+    fn read_metadata_tolerates_non_exif_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fake.jpg");
+        std::fs::write(&path, b"\xff\xd8\xff\xe1garbage").unwrap();
+        assert!(ImagePreview::read_metadata(&path).is_none());
+        std::fs::write(&path, b"not an image").unwrap();
+        assert!(ImagePreview::read_metadata(&path).is_none());
     }
 }
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+
+use crate::projection::{Projection, ProjectionOutput, ProjectionRequest, Resource, Result};
+use crate::store::FileStore;
+
+pub struct JsonView;
+
+impl JsonView {
+    /// Parse JSON source into the projection output. Malformed input still
+    /// projects, with the raw source as `pretty` and the parse error set.
+    fn parse(raw: String) -> ProjectionOutput {
+        match serde_json::from_str::<serde_json::Value>(&raw) {
+            Ok(value) => {
+                let pretty = serde_json::to_string_pretty(&value).unwrap_or(raw);
+                ProjectionOutput::Json {
+                    value: Some(value),
+                    pretty,
+                    error: None,
+                }
+            }
+            Err(e) => ProjectionOutput::Json {
+                value: None,
+                pretty: raw,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Projection for JsonView {
+    fn id(&self) -> &str {
+        "json.view"
+    }
+
+    fn name(&self) -> &str {
+        "JSON"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Pretty-printed JSON with parse errors")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("json")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("json") => 0.9,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let raw = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        Ok(Self::parse(raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pretty_prints() {
+        let ProjectionOutput::Json {
+            value,
+            pretty,
+            error,
+        } = JsonView::parse(r#"{"a":[1,2]}"#.into())
+        else {
+            panic!("expected Json output");
+        };
+        assert_eq!(value.unwrap()["a"][1], 2);
+        assert_eq!(pretty, "{\n  \"a\": [\n    1,\n    2\n  ]\n}");
+        assert!(error.is_none());
+    }
+
+    #[test]
+    fn parse_malformed_keeps_source() {
+        let ProjectionOutput::Json {
+            value,
+            pretty,
+            error,
+        } = JsonView::parse("{\"a\": ".into())
+        else {
+            panic!("expected Json output");
+        };
+        assert!(value.is_none());
+        assert_eq!(pretty, "{\"a\": ");
+        assert!(error.unwrap().contains("line 1"));
+    }
+}
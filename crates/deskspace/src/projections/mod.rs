@@ -1,4 +1,24 @@
+pub mod archive_list;
+pub mod audio_preview;
+pub mod command;
+pub mod config_view;
+pub mod csv_table;
+pub mod diff_view;
 pub mod dir_list;
+pub mod dir_tree;
+pub mod env_view;
+pub mod font_preview;
+pub mod hex_view;
+pub mod html_preview;
 pub mod image_preview;
+pub mod json_view;
+pub mod notebook_view;
+pub mod pdf_preview;
+pub mod sql_dump;
+pub mod sqlite_browse;
+pub mod svg_preview;
+#[cfg(feature = "highlight")]
+pub mod syntax_highlight;
 pub mod text_markdown;
 pub mod text_raw;
+pub mod video_preview;
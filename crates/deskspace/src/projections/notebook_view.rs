@@ -0,0 +1,236 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::projection::{
+    NotebookCell, NotebookOutput, Projection, ProjectionError, ProjectionOutput, ProjectionRequest,
+    Resource, Result,
+};
+use crate::projections::text_markdown::TextMarkdown;
+use crate::store::FileStore;
+
+/// Rich output types shown as images, in order of preference.
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/svg+xml"];
+
+pub struct NotebookView;
+
+impl NotebookView {
+    /// nbformat stores multi-line strings either whole or as a list of lines.
+    fn text(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+            _ => String::new(),
+        }
+    }
+
+    /// Remove ANSI colour escapes, which tracebacks are full of.
+    fn strip_ansi(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' && chars.peek() == Some(&'[') {
+                chars.next();
+                for end in chars.by_ref() {
+                    if end.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    fn output(value: &Value) -> Option<NotebookOutput> {
+        match value.get("output_type")?.as_str()? {
+            "stream" => Some(NotebookOutput::Text {
+                text: Self::text(value.get("text")?),
+            }),
+            "execute_result" | "display_data" => {
+                let data = value.get("data")?;
+                for mime in IMAGE_TYPES {
+                    if let Some(image) = data.get(*mime) {
+                        let payload = Self::text(image);
+                        // SVG is stored as markup; raster images as base64
+                        let data_uri = if *mime == "image/svg+xml" {
+                            format!("data:{mime};utf8,{}", Self::percent_encode(&payload))
+                        } else {
+                            let base64: String =
+                                payload.chars().filter(|c| !c.is_whitespace()).collect();
+                            format!("data:{mime};base64,{base64}")
+                        };
+                        return Some(NotebookOutput::Image {
+                            mime_type: mime.to_string(),
+                            data_uri,
+                        });
+                    }
+                }
+                Some(NotebookOutput::Text {
+                    text: Self::text(data.get("text/plain")?),
+                })
+            }
+            "error" => {
+                let traceback = value
+                    .get("traceback")
+                    .and_then(Value::as_array)
+                    .map(|lines| {
+                        lines
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(Self::strip_ansi)
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .unwrap_or_default();
+                Some(NotebookOutput::Error {
+                    name: value.get("ename").map(Self::text).unwrap_or_default(),
+                    value: value.get("evalue").map(Self::text).unwrap_or_default(),
+                    traceback,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn percent_encode(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for b in text.bytes() {
+            if b.is_ascii_alphanumeric() || b" -_.~/:=;,'()".contains(&b) {
+                out.push(b as char);
+            } else {
+                out.push_str(&format!("%{b:02X}"));
+            }
+        }
+        out
+    }
+
+    fn parse(raw: &str) -> std::result::Result<(Option<String>, Vec<NotebookCell>), String> {
+        let doc: Value = serde_json::from_str(raw).map_err(|e| format!("invalid notebook: {e}"))?;
+        let cells = doc
+            .get("cells")
+            .and_then(Value::as_array)
+            .ok_or("invalid notebook: no cells array (nbformat 4 required)")?;
+        let metadata = doc.get("metadata");
+        let language = metadata
+            .and_then(|m| m.pointer("/kernelspec/language"))
+            .or_else(|| metadata.and_then(|m| m.pointer("/language_info/name")))
+            .and_then(Value::as_str)
+            .map(String::from);
+
+        let cells = cells
+            .iter()
+            .map(|cell| {
+                let cell_type = cell
+                    .get("cell_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("raw")
+                    .to_string();
+                let source = cell.get("source").map(Self::text).unwrap_or_default();
+                let html = (cell_type == "markdown").then(|| TextMarkdown::render_html(&source));
+                let outputs = cell
+                    .get("outputs")
+                    .and_then(Value::as_array)
+                    .map(|outputs| outputs.iter().filter_map(Self::output).collect())
+                    .unwrap_or_default();
+                NotebookCell {
+                    cell_type,
+                    source,
+                    html,
+                    execution_count: cell.get("execution_count").and_then(Value::as_u64),
+                    outputs,
+                }
+            })
+            .collect();
+        Ok((language, cells))
+    }
+}
+
+#[async_trait]
+impl Projection for NotebookView {
+    fn id(&self) -> &str {
+        "notebook.view"
+    }
+
+    fn name(&self) -> &str {
+        "Notebook"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Jupyter notebook cells and outputs")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("notebook")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("ipynb") => 0.95,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let raw = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        let (language, cells) = Self::parse(&raw).map_err(ProjectionError::Other)?;
+        Ok(ProjectionOutput::Notebook { language, cells })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cells_and_outputs() {
+        let nb = r##"{
+            "metadata": {"kernelspec": {"language": "python"}},
+            "nbformat": 4,
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Title\n", "Some *text*"]},
+                {"cell_type": "code", "execution_count": 3, "source": "print(1)\n1/0",
+                 "outputs": [
+                    {"output_type": "stream", "name": "stdout", "text": ["1\n"]},
+                    {"output_type": "display_data", "data": {"image/png": "iVBO\nRw==", "text/plain": "<Figure>"}},
+                    {"output_type": "error", "ename": "ZeroDivisionError", "evalue": "division by zero",
+                     "traceback": ["\u001b[0;31mZeroDivisionError\u001b[0m: division by zero"]}
+                 ]}
+            ]
+        }"##;
+        let (language, cells) = NotebookView::parse(nb).unwrap();
+        assert_eq!(language.as_deref(), Some("python"));
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells[0].source, "# Title\nSome *text*");
+        assert!(cells[0].html.as_deref().unwrap().contains("<em>text</em>"));
+        assert_eq!(cells[1].execution_count, Some(3));
+        assert!(cells[1].html.is_none());
+
+        let outputs = &cells[1].outputs;
+        assert!(matches!(&outputs[0], NotebookOutput::Text { text } if text == "1\n"));
+        assert!(matches!(
+            &outputs[1],
+            NotebookOutput::Image { data_uri, .. } if data_uri == "data:image/png;base64,iVBORw=="
+        ));
+        assert!(matches!(
+            &outputs[2],
+            NotebookOutput::Error { traceback, .. } if traceback == "ZeroDivisionError: division by zero"
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_non_notebooks() {
+        assert!(NotebookView::parse("{not json").is_err());
+        assert!(NotebookView::parse(r#"{"worksheets": []}"#).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+/// Maximum number of characters of extracted text returned.
+const MAX_EXCERPT_CHARS: usize = 4000;
+
+/// Pages scanned for text before giving up on filling the excerpt.
+const MAX_SCANNED_PAGES: usize = 20;
+
+/// Cap on decompressed content per page, so a small compressed stream
+/// cannot inflate without bound.
+const MAX_PAGE_CONTENT_BYTES: usize = 16 * 1024 * 1024;
+
+pub struct PdfPreview;
+
+impl PdfPreview {
+    /// Load the document and pull text from its leading pages. Unreadable
+    /// documents produce an output with an `error` note instead of failing.
+    fn preview(path: &Path) -> ProjectionOutput {
+        let doc = match lopdf::Document::load(path) {
+            Ok(doc) => doc,
+            Err(e) => {
+                let error = match e {
+                    lopdf::Error::Decryption(_) | lopdf::Error::InvalidPassword => {
+                        "encrypted PDF".to_string()
+                    }
+                    other => format!("unreadable PDF: {other}"),
+                };
+                return ProjectionOutput::Pdf {
+                    page_count: None,
+                    text_excerpt: None,
+                    error: Some(error),
+                };
+            }
+        };
+
+        let pages = doc.get_pages();
+        let page_count = Some(pages.len());
+        if doc.is_encrypted() {
+            return ProjectionOutput::Pdf {
+                page_count,
+                text_excerpt: None,
+                error: Some("encrypted PDF: text unavailable".to_string()),
+            };
+        }
+
+        let mut excerpt = String::new();
+        for &number in pages.keys().take(MAX_SCANNED_PAGES) {
+            match doc.extract_text_with_limit(&[number], MAX_PAGE_CONTENT_BYTES) {
+                Ok(text) => excerpt.push_str(&text),
+                Err(e) => tracing::debug!("skipping page {number} of {}: {e}", path.display()),
+            }
+            if excerpt.chars().count() >= MAX_EXCERPT_CHARS {
+                break;
+            }
+        }
+        let excerpt: String = excerpt.trim().chars().take(MAX_EXCERPT_CHARS).collect();
+
+        ProjectionOutput::Pdf {
+            page_count,
+            text_excerpt: (!excerpt.is_empty()).then_some(excerpt),
+            error: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Projection for PdfPreview {
+    fn id(&self) -> &str {
+        "pdf.preview"
+    }
+
+    fn name(&self) -> &str {
+        "PDF"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Page count and leading text of a PDF")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("pdf")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("pdf") => 0.9,
+            _ if resource.mime_is(&["application/pdf"]) => 0.9,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let path = workspace.local_path(&resource.path)?;
+        tokio::task::spawn_blocking(move || Self::preview(&path))
+            .await
+            .map_err(|e| ProjectionError::Other(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Document, Object, Stream};
+
+    fn write_pdf(path: &Path, text: &str) {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![100.into(), 600.into()]),
+                Operation::new("Tj", vec![Object::string_literal(text)]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc.save(path).unwrap();
+    }
+
+    #[test]
+    fn preview_extracts_page_count_and_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.pdf");
+        write_pdf(&path, "Hello PDF");
+        let ProjectionOutput::Pdf {
+            page_count,
+            text_excerpt,
+            error,
+        } = PdfPreview::preview(&path)
+        else {
+            panic!("expected Pdf output");
+        };
+        assert_eq!(page_count, Some(1));
+        assert!(text_excerpt.unwrap().contains("Hello PDF"));
+        assert!(error.is_none());
+    }
+
+    #[test]
+    fn preview_degrades_on_malformed_pdf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.pdf");
+        std::fs::write(&path, b"%PDF-1.4\nnot really a pdf").unwrap();
+        let ProjectionOutput::Pdf {
+            page_count, error, ..
+        } = PdfPreview::preview(&path)
+        else {
+            panic!("expected Pdf output");
+        };
+        assert_eq!(page_count, None);
+        assert!(error.is_some());
+    }
+}
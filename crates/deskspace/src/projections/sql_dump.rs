@@ -0,0 +1,362 @@
+use async_trait::async_trait;
+
+use crate::projection::{Projection, ProjectionOutput, ProjectionRequest, Resource, Result};
+use crate::store::FileStore;
+
+/// Maximum number of rows sampled into the table preview.
+const MAX_ROWS: usize = 500;
+
+/// Fraction of statements that must be `INSERT INTO ... VALUES` for the file
+/// to be treated as a data dump rather than schema/DDL.
+const INSERT_RATIO: f32 = 0.8;
+
+pub struct SqlDump;
+
+/// Rows extracted from an INSERT-dominated SQL file.
+#[derive(Debug, PartialEq)]
+struct InsertSample {
+    table: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+    truncated: bool,
+}
+
+impl SqlDump {
+    /// Split SQL source into statements on `;`, ignoring semicolons inside
+    /// quotes and comments. Comments are stripped from the returned statements.
+    fn split_statements(sql: &str) -> Vec<String> {
+        let mut statements = Vec::new();
+        let mut current = String::new();
+        let mut chars = sql.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' | '"' | '`' => {
+                    current.push(c);
+                    while let Some(q) = chars.next() {
+                        current.push(q);
+                        if q == '\\' && c == '\'' {
+                            if let Some(escaped) = chars.next() {
+                                current.push(escaped);
+                            }
+                        } else if q == c {
+                            // Doubled quote is an escaped quote, not the end
+                            if chars.peek() == Some(&c) {
+                                current.push(chars.next().unwrap());
+                            } else {
+                                break;
+                            }
+                        }
+                    }
+                }
+                '-' if chars.peek() == Some(&'-') => {
+                    for skipped in chars.by_ref() {
+                        if skipped == '\n' {
+                            current.push('\n');
+                            break;
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    let mut prev = ' ';
+                    for skipped in chars.by_ref() {
+                        if prev == '*' && skipped == '/' {
+                            break;
+                        }
+                        prev = skipped;
+                    }
+                    current.push(' ');
+                }
+                ';' => {
+                    let stmt = current.trim();
+                    if !stmt.is_empty() {
+                        statements.push(stmt.to_string());
+                    }
+                    current.clear();
+                }
+                _ => current.push(c),
+            }
+        }
+        let stmt = current.trim();
+        if !stmt.is_empty() {
+            statements.push(stmt.to_string());
+        }
+        statements
+    }
+
+    /// Strip identifier quoting (`"name"`, `` `name` ``, `[name]`).
+    fn unquote_ident(ident: &str) -> String {
+        let ident = ident.trim();
+        let inner = ident
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .or_else(|| ident.strip_prefix('`').and_then(|s| s.strip_suffix('`')))
+            .or_else(|| ident.strip_prefix('[').and_then(|s| s.strip_suffix(']')))
+            .unwrap_or(ident);
+        inner.to_string()
+    }
+
+    /// Parse a comma-separated list of SQL values, respecting quotes and
+    /// nested parentheses. Quoted strings are unescaped; everything else is
+    /// returned as written.
+    fn split_values(list: &str) -> Vec<String> {
+        let mut values = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0usize;
+        let mut chars = list.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' if depth == 0 => {
+                    while let Some(q) = chars.next() {
+                        match q {
+                            '\\' => {
+                                if let Some(escaped) = chars.next() {
+                                    current.push(match escaped {
+                                        'n' => '\n',
+                                        't' => '\t',
+                                        'r' => '\r',
+                                        '0' => '\0',
+                                        other => other,
+                                    });
+                                }
+                            }
+                            '\'' if chars.peek() == Some(&'\'') => {
+                                chars.next();
+                                current.push('\'');
+                            }
+                            '\'' => break,
+                            _ => current.push(q),
+                        }
+                    }
+                }
+                '(' => {
+                    depth += 1;
+                    current.push(c);
+                }
+                ')' => {
+                    depth = depth.saturating_sub(1);
+                    current.push(c);
+                }
+                ',' if depth == 0 => {
+                    values.push(current.trim().to_string());
+                    current.clear();
+                }
+                _ => current.push(c),
+            }
+        }
+        values.push(current.trim().to_string());
+        values
+    }
+
+    /// Split the text after `VALUES` into the contents of each top-level
+    /// parenthesized tuple.
+    fn split_tuples(values: &str) -> Vec<&str> {
+        let mut tuples = Vec::new();
+        let mut depth = 0usize;
+        let mut start = 0;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        for (i, c) in values.char_indices() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                '\'' | '"' => quote = Some(c),
+                '(' => {
+                    if depth == 0 {
+                        start = i + 1;
+                    }
+                    depth += 1;
+                }
+                ')' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        tuples.push(&values[start..i]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        tuples
+    }
+
+    /// Parse `INSERT INTO table [(cols)] VALUES (...), (...)` into its parts.
+    fn parse_insert(stmt: &str) -> Option<(String, Vec<String>, Vec<&str>)> {
+        let lower = stmt.to_ascii_lowercase();
+        let rest = lower.strip_prefix("insert")?.trim_start();
+        let rest = rest.strip_prefix("into")?;
+        let offset = stmt.len() - rest.len();
+        let values_at = offset + lower[offset..].find("values")?;
+        let target = stmt[offset..values_at].trim();
+        let (table, columns) = match target.find('(') {
+            Some(open) => {
+                let close = target.rfind(')')?;
+                let columns = target[open + 1..close]
+                    .split(',')
+                    .map(Self::unquote_ident)
+                    .collect();
+                (Self::unquote_ident(&target[..open]), columns)
+            }
+            None => (Self::unquote_ident(target), Vec::new()),
+        };
+        if table.is_empty() {
+            return None;
+        }
+        let tuples = Self::split_tuples(&stmt[values_at + "values".len()..]);
+        Some((table, columns, tuples))
+    }
+
+    /// Sample rows from the file if it is dominated by INSERT statements.
+    /// Returns `None` for schema-heavy or ambiguous SQL.
+    fn sample_inserts(sql: &str) -> Option<InsertSample> {
+        let statements = Self::split_statements(sql);
+        let inserts: Vec<_> = statements
+            .iter()
+            .filter_map(|s| Self::parse_insert(s))
+            .collect();
+        if inserts.is_empty() || (inserts.len() as f32) < statements.len() as f32 * INSERT_RATIO {
+            return None;
+        }
+
+        // Preview the first table the dump inserts into
+        let table = inserts[0].0.clone();
+        let mut columns = Vec::new();
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        for (_, cols, tuples) in inserts.iter().filter(|(t, _, _)| *t == table) {
+            if columns.is_empty() {
+                columns.clone_from(cols);
+            }
+            for tuple in tuples {
+                if rows.len() >= MAX_ROWS {
+                    truncated = true;
+                    break;
+                }
+                rows.push(Self::split_values(tuple));
+            }
+            if truncated {
+                break;
+            }
+        }
+        if rows.is_empty() {
+            return None;
+        }
+        Some(InsertSample {
+            table,
+            columns,
+            rows,
+            truncated,
+        })
+    }
+}
+
+#[async_trait]
+impl Projection for SqlDump {
+    fn id(&self) -> &str {
+        "sql.dump"
+    }
+
+    fn name(&self) -> &str {
+        "SQL"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("SQLite database schema and contents as SQL")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("database")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("sql") => 0.9,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let content = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        if let Some(sample) = Self::sample_inserts(&content) {
+            return Ok(ProjectionOutput::Table {
+                table: Some(sample.table),
+                headers: sample.columns,
+                headers_inferred: false,
+                rows: sample.rows,
+                truncated: sample.truncated,
+            });
+        }
+        // Schema-heavy or ambiguous SQL keeps the highlighted text view
+        let line_count = content.lines().count();
+        Ok(ProjectionOutput::Text {
+            content,
+            language: Some("sql".to_string()),
+            line_count,
+            range: None,
+            encoding: None,
+            lossy: false,
+            line_ending: None,
+            indent: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_inserts_with_columns() {
+        let sql = "-- seed data\n\
+            INSERT INTO `users` (`id`, `name`) VALUES (1, 'Alice'), (2, 'Bob');\n\
+            INSERT INTO `users` (`id`, `name`) VALUES (3, 'Carol');\n";
+        let sample = SqlDump::sample_inserts(sql).unwrap();
+        assert_eq!(sample.table, "users");
+        assert_eq!(sample.columns, vec!["id", "name"]);
+        assert_eq!(sample.rows.len(), 3);
+        assert_eq!(sample.rows[1], vec!["2", "Bob"]);
+        assert!(!sample.truncated);
+    }
+
+    #[test]
+    fn sample_inserts_handles_quoted_commas() {
+        let sql = "insert into notes values (1, 'a, b; c', 'it''s', NOW());";
+        let sample = SqlDump::sample_inserts(sql).unwrap();
+        assert!(sample.columns.is_empty());
+        assert_eq!(sample.rows[0], vec!["1", "a, b; c", "it's", "NOW()"]);
+    }
+
+    #[test]
+    fn schema_heavy_sql_is_not_sampled() {
+        let sql = "CREATE TABLE t (id INT);\n\
+            CREATE INDEX t_id ON t (id);\n\
+            INSERT INTO t VALUES (1);\n";
+        assert!(SqlDump::sample_inserts(sql).is_none());
+    }
+
+    #[test]
+    fn sample_inserts_caps_rows() {
+        let tuples: Vec<_> = (0..MAX_ROWS + 10).map(|i| format!("({i})")).collect();
+        let sql = format!("INSERT INTO t VALUES {};", tuples.join(", "));
+        let sample = SqlDump::sample_inserts(&sql).unwrap();
+        assert_eq!(sample.rows.len(), MAX_ROWS);
+        assert!(sample.truncated);
+    }
+}
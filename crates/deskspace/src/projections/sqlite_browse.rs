@@ -0,0 +1,248 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+/// Maximum number of rows previewed from a table.
+const MAX_ROWS: usize = 500;
+
+/// Maximum number of tables listed.
+const MAX_TABLES: usize = 1000;
+
+/// How long to wait on a database locked by a writer.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+pub struct SqliteBrowse;
+
+impl SqliteBrowse {
+    fn open(path: &Path) -> rusqlite::Result<Connection> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(conn)
+    }
+
+    fn table_names(conn: &Connection) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master \
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let names = stmt.query_map([], |row| row.get(0))?;
+        names.collect()
+    }
+
+    /// Quote an identifier for interpolation into SQL.
+    fn quote_ident(name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+
+    fn cell(value: ValueRef<'_>) -> String {
+        match value {
+            ValueRef::Null => "NULL".to_string(),
+            ValueRef::Integer(i) => i.to_string(),
+            ValueRef::Real(f) => f.to_string(),
+            ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned(),
+            ValueRef::Blob(b) => format!("<blob {} bytes>", b.len()),
+        }
+    }
+
+    /// Table names with their row counts.
+    fn list_tables(conn: &Connection) -> Result<ProjectionOutput> {
+        let names = Self::table_names(conn).map_err(Self::db_error)?;
+        let truncated = names.len() > MAX_TABLES;
+        let mut rows = Vec::new();
+        for name in names.into_iter().take(MAX_TABLES) {
+            let sql = format!("SELECT count(*) FROM {}", Self::quote_ident(&name));
+            let count: i64 = conn
+                .query_row(&sql, [], |row| row.get(0))
+                .map_err(Self::db_error)?;
+            rows.push(vec![name, count.to_string()]);
+        }
+        Ok(ProjectionOutput::Table {
+            table: None,
+            headers: vec!["table".to_string(), "rows".to_string()],
+            headers_inferred: false,
+            rows,
+            truncated,
+        })
+    }
+
+    /// The first rows of `table`, which must be an existing table name.
+    fn table_rows(conn: &Connection, table: &str) -> Result<ProjectionOutput> {
+        let names = Self::table_names(conn).map_err(Self::db_error)?;
+        if !names.iter().any(|n| n == table) {
+            return Err(ProjectionError::InvalidOption(format!("table={table}")));
+        }
+        let sql = format!(
+            "SELECT * FROM {} LIMIT {}",
+            Self::quote_ident(table),
+            MAX_ROWS + 1
+        );
+        let mut stmt = conn.prepare(&sql).map_err(Self::db_error)?;
+        let headers: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        let mut query = stmt.query([]).map_err(Self::db_error)?;
+        while let Some(row) = query.next().map_err(Self::db_error)? {
+            if rows.len() >= MAX_ROWS {
+                truncated = true;
+                break;
+            }
+            let cells = (0..headers.len())
+                .map(|i| row.get_ref(i).map(Self::cell))
+                .collect::<rusqlite::Result<_>>()
+                .map_err(Self::db_error)?;
+            rows.push(cells);
+        }
+        Ok(ProjectionOutput::Table {
+            table: Some(table.to_string()),
+            headers,
+            headers_inferred: false,
+            rows,
+            truncated,
+        })
+    }
+
+    fn browse(path: &Path, table: Option<&str>) -> Result<ProjectionOutput> {
+        let conn = Self::open(path).map_err(Self::db_error)?;
+        match table {
+            Some(table) => Self::table_rows(&conn, table),
+            None => Self::list_tables(&conn),
+        }
+    }
+
+    /// Locked, corrupt, or non-database files are the projection's problem,
+    /// reported in-band rather than as a server error.
+    fn db_error(e: rusqlite::Error) -> ProjectionError {
+        ProjectionError::Other(format!("sqlite: {e}"))
+    }
+}
+
+#[async_trait]
+impl Projection for SqliteBrowse {
+    fn id(&self) -> &str {
+        "sqlite.browse"
+    }
+
+    fn name(&self) -> &str {
+        "SQLite"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Rows of a SQLite table")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("database")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("db" | "sqlite" | "sqlite3") => 0.9,
+            _ if resource.mime_is(&["application/vnd.sqlite3"]) => 0.9,
+            _ => 0.0,
+        }
+    }
+
+    fn reads_whole_file(&self) -> bool {
+        false
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let path = workspace.local_path(&resource.path)?;
+        let table = request.get("table").map(String::from);
+        tokio::task::spawn_blocking(move || Self::browse(&path, table.as_deref()))
+            .await
+            .map_err(|e| ProjectionError::Other(e.to_string()))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_db(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("app.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, avatar BLOB);
+             INSERT INTO users (name, avatar) VALUES ('Alice', x'0102'), (NULL, NULL);
+             CREATE TABLE \"odd \"\"name\" (x);",
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn lists_tables_with_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample_db(dir.path());
+        let ProjectionOutput::Table { headers, rows, .. } =
+            SqliteBrowse::browse(&path, None).unwrap()
+        else {
+            panic!("expected Table output");
+        };
+        assert_eq!(headers, vec!["table", "rows"]);
+        assert_eq!(rows, vec![vec!["odd \"name", "0"], vec!["users", "2"]]);
+    }
+
+    #[test]
+    fn previews_table_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample_db(dir.path());
+        let ProjectionOutput::Table {
+            table,
+            headers,
+            rows,
+            truncated,
+            ..
+        } = SqliteBrowse::browse(&path, Some("users")).unwrap()
+        else {
+            panic!("expected Table output");
+        };
+        assert_eq!(table.as_deref(), Some("users"));
+        assert_eq!(headers, vec!["id", "name", "avatar"]);
+        assert_eq!(rows[0], vec!["1", "Alice", "<blob 2 bytes>"]);
+        assert_eq!(rows[1], vec!["2", "NULL", "NULL"]);
+        assert!(!truncated);
+        // Quoted identifiers round-trip
+        assert!(SqliteBrowse::browse(&path, Some("odd \"name")).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_table_and_corrupt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = sample_db(dir.path());
+        assert!(matches!(
+            SqliteBrowse::browse(&path, Some("users; DROP TABLE users")),
+            Err(ProjectionError::InvalidOption(_))
+        ));
+
+        let corrupt = dir.path().join("corrupt.db");
+        std::fs::write(
+            &corrupt,
+            b"this is not a database, just text padding it out",
+        )
+        .unwrap();
+        assert!(matches!(
+            SqliteBrowse::browse(&corrupt, None),
+            Err(ProjectionError::Other(_))
+        ));
+    }
+}
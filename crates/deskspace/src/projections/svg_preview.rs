@@ -0,0 +1,377 @@
+use async_trait::async_trait;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::XmlVersion;
+
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, RenderFormat, Resource,
+    Result,
+};
+use crate::store::FileStore;
+
+/// Elements kept in the cleaned markup: shapes, text, paint servers and
+/// filters. Anything else, e.g. `script`, `style`, `foreignObject` or the
+/// animation elements that can rewrite attributes, goes with its children.
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "svg",
+    "g",
+    "defs",
+    "symbol",
+    "use",
+    "title",
+    "desc",
+    "switch",
+    "path",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "image",
+    "text",
+    "tspan",
+    "textPath",
+    "linearGradient",
+    "radialGradient",
+    "stop",
+    "pattern",
+    "clipPath",
+    "mask",
+    "marker",
+    "filter",
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDistantLight",
+    "feDropShadow",
+    "feFlood",
+    "feFuncA",
+    "feFuncB",
+    "feFuncG",
+    "feFuncR",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMergeNode",
+    "feMorphology",
+    "feOffset",
+    "fePointLight",
+    "feSpecularLighting",
+    "feSpotLight",
+    "feTile",
+    "feTurbulence",
+];
+
+/// Elements dropped while their children are kept, so linked text and
+/// shapes still show.
+const UNWRAPPED_ELEMENTS: &[&str] = &["a"];
+
+/// Embedded rasters an `image` may carry in place of an external file.
+const DATA_IMAGE_PREFIXES: &[&str] = &[
+    "data:image/png;",
+    "data:image/jpeg;",
+    "data:image/gif;",
+    "data:image/webp;",
+];
+
+pub struct SvgPreview;
+
+impl SvgPreview {
+    /// Re-serialize `raw` keeping only allowlisted elements and attributes.
+    /// Event handlers, scripts, stylesheets, comments, entity declarations
+    /// and references to anything outside the document are dropped; links
+    /// may only point at fragments, and images at embedded rasters. Returns
+    /// the markup with the root element's size.
+    fn sanitize(raw: &str) -> Result<(String, Option<f64>, Option<f64>)> {
+        let invalid =
+            |e: &dyn std::fmt::Display| ProjectionError::Other(format!("invalid SVG: {e}"));
+        let mut reader = quick_xml::Reader::from_str(raw);
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        // Whether each open element was written, so its end tag matches
+        let mut open: Vec<bool> = Vec::new();
+        // Depth inside a dropped element
+        let mut skipping = 0usize;
+        let mut size = None;
+
+        loop {
+            let event = reader.read_event().map_err(|e| invalid(&e))?;
+            let kept = match event {
+                Event::Eof => break,
+                Event::Start(_) | Event::Empty(_) if skipping > 0 => {
+                    if matches!(event, Event::Start(_)) {
+                        skipping += 1;
+                    }
+                    None
+                }
+                Event::End(_) if skipping > 0 => {
+                    skipping -= 1;
+                    None
+                }
+                Event::Start(ref e) | Event::Empty(ref e) => {
+                    let is_start = matches!(event, Event::Start(_));
+                    if size.is_none() {
+                        if e.name().as_ref() != "svg" {
+                            return Err(invalid(&"the root element isn't <svg>"));
+                        }
+                        size = Some(Self::size(e));
+                    }
+                    match Self::clean_element(e) {
+                        Some(clean) => {
+                            if is_start {
+                                open.push(true);
+                                Some(Event::Start(clean))
+                            } else {
+                                Some(Event::Empty(clean))
+                            }
+                        }
+                        None if UNWRAPPED_ELEMENTS.contains(&e.name().as_ref()) => {
+                            if is_start {
+                                open.push(false);
+                            }
+                            None
+                        }
+                        None => {
+                            if is_start {
+                                skipping = 1;
+                            }
+                            None
+                        }
+                    }
+                }
+                Event::End(e) => open.pop().unwrap_or(false).then_some(Event::End(e)),
+                Event::Text(_) | Event::CData(_) | Event::GeneralRef(_) if skipping > 0 => None,
+                Event::Text(e) if !open.is_empty() => Some(Event::Text(e)),
+                Event::CData(e) if !open.is_empty() => {
+                    Some(Event::Text(BytesText::new(&e.xml10_content()).into_owned()))
+                }
+                // Only the predefined entities and character references;
+                // anything else would need the dropped DOCTYPE
+                Event::GeneralRef(e)
+                    if !open.is_empty()
+                        && (e.is_char_ref()
+                            || matches!(e.as_ref(), "lt" | "gt" | "amp" | "apos" | "quot")) =>
+                {
+                    Some(Event::GeneralRef(e))
+                }
+                _ => None,
+            };
+            if let Some(event) = kept {
+                writer.write_event(event).map_err(|e| invalid(&e))?;
+            }
+        }
+
+        let Some((width, height)) = size else {
+            return Err(invalid(&"no <svg> element"));
+        };
+        let markup = String::from_utf8(writer.into_inner()).map_err(|e| invalid(&e))?;
+        Ok((markup, width, height))
+    }
+
+    /// `element` with only its safe attributes, or `None` if the element
+    /// isn't allowed at all.
+    fn clean_element(element: &BytesStart) -> Option<BytesStart<'static>> {
+        let name = element.name();
+        let name = name.as_ref();
+        if !ALLOWED_ELEMENTS.contains(&name) {
+            return None;
+        }
+        let mut clean = BytesStart::new(name.to_string());
+        for attr in element.attributes().flatten() {
+            let Ok(value) = attr.normalized_value(XmlVersion::Implicit1_0) else {
+                continue;
+            };
+            let key = attr.key.as_ref();
+            if Self::attribute_allowed(name, key, &value) {
+                clean.push_attribute((key, value.as_ref()));
+            }
+        }
+        Some(clean)
+    }
+
+    fn attribute_allowed(element: &str, key: &str, value: &str) -> bool {
+        let lower_key = key.to_ascii_lowercase();
+        if lower_key.starts_with("on") {
+            return false;
+        }
+        if let Some((prefix, _)) = key.split_once(':') {
+            if !matches!(prefix, "xmlns" | "xml") && key != "xlink:href" {
+                return false;
+            }
+        }
+        let folded: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if key == "href" || key == "xlink:href" {
+            return folded.starts_with('#')
+                || (element == "image"
+                    && DATA_IMAGE_PREFIXES.iter().any(|p| folded.starts_with(p)));
+        }
+        if folded.contains("javascript:") || folded.contains("expression(") {
+            return false;
+        }
+        // Paint and filter references may only name fragments
+        folded.match_indices("url(").all(|(i, _)| {
+            let target = folded[i + 4..].trim_start_matches(['\'', '"']);
+            target.starts_with('#')
+        })
+    }
+
+    /// Width and height of the root element, from its attributes or else
+    /// its `viewBox`. Percentages and other relative units give `None`.
+    fn size(root: &BytesStart) -> (Option<f64>, Option<f64>) {
+        let attr = |name: &str| -> Option<String> {
+            root.try_get_attribute(name)
+                .ok()
+                .flatten()
+                .and_then(|a| a.normalized_value(XmlVersion::Implicit1_0).ok())
+                .map(|v| v.into_owned())
+        };
+        let length = |value: Option<String>| {
+            let value = value?;
+            let value = value.trim();
+            let number = value.strip_suffix("px").unwrap_or(value).trim();
+            number
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n > 0.0)
+        };
+        let view_box: Vec<f64> = attr("viewBox")
+            .unwrap_or_default()
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|p| !p.is_empty())
+            .filter_map(|p| p.parse().ok())
+            .collect();
+        let from_view_box = |i: usize| {
+            (view_box.len() == 4)
+                .then(|| view_box[i])
+                .filter(|n| n.is_finite() && *n > 0.0)
+        };
+        (
+            length(attr("width")).or_else(|| from_view_box(2)),
+            length(attr("height")).or_else(|| from_view_box(3)),
+        )
+    }
+}
+
+#[async_trait]
+impl Projection for SvgPreview {
+    fn id(&self) -> &str {
+        "svg.preview"
+    }
+
+    fn name(&self) -> &str {
+        "SVG Preview"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Drawing with scripts and external references removed")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("image")
+    }
+
+    fn formats(&self) -> &[RenderFormat] {
+        &[RenderFormat::Html]
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match resource.extension.as_deref() {
+            Some("svg") => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let raw = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        let (safe_markup, width, height) = Self::sanitize(&raw)?;
+        Ok(ProjectionOutput::Svg {
+            safe_markup,
+            width,
+            height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_scripts_handlers_and_external_references() {
+        let svg = r##"<?xml version="1.0"?>
+<!DOCTYPE svg [<!ENTITY x "boom">]>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="120px" height="80" onload="steal()">
+  <script>alert(1)</script>
+  <style>@import url(https://evil.example/x.css);</style>
+  <!-- comment -->
+  <a href="javascript:alert(1)"><text x="1" y="2">Hi &amp; &x;<![CDATA[<b>]]></text></a>
+  <foreignObject><div xmlns="http://www.w3.org/1999/xhtml">x</div></foreignObject>
+  <set attributeName="href" to="javascript:alert(1)"/>
+  <rect fill="url(#grad)" stroke="url('https://evil.example/p')" onclick="steal()"/>
+  <use xlink:href="#shape"/><use href="https://evil.example/s.svg#a"/>
+  <image href="data:image/png;base64,AAAA"/><image href="http://evil.example/a.png"/>
+</svg>"##;
+        let (safe, width, height) = SvgPreview::sanitize(svg).unwrap();
+        for banned in [
+            "script",
+            "style",
+            "onload",
+            "onclick",
+            "javascript:",
+            "evil.example",
+            "foreignObject",
+            "<set",
+            "<a",
+            "<!",
+            "boom",
+            "<b>",
+        ] {
+            assert!(!safe.contains(banned), "{banned} survived: {safe}");
+        }
+        assert!(
+            safe.contains(r#"<text x="1" y="2">Hi &amp; &lt;b&gt;</text>"#),
+            "{safe}"
+        );
+        assert!(safe.contains(r##"<rect fill="url(#grad)"/>"##), "{safe}");
+        assert!(
+            safe.contains(r##"<use xlink:href="#shape"/><use/>"##),
+            "{safe}"
+        );
+        assert!(safe.contains(r#"<image href="data:image/png;base64,AAAA"/><image/>"#));
+        assert!(safe.trim_end().ends_with("</svg>"));
+        assert_eq!((width, height), (Some(120.0), Some(80.0)));
+    }
+
+    #[test]
+    fn size_falls_back_to_the_view_box() {
+        let (_, width, height) =
+            SvgPreview::sanitize(r#"<svg width="100%" viewBox="0 0 24,16"></svg>"#).unwrap();
+        assert_eq!((width, height), (Some(24.0), Some(16.0)));
+        let (_, width, height) = SvgPreview::sanitize("<svg/>").unwrap();
+        assert_eq!((width, height), (None, None));
+    }
+
+    #[test]
+    fn rejects_documents_that_are_not_svg() {
+        assert!(SvgPreview::sanitize("<html><script/></html>").is_err());
+        assert!(SvgPreview::sanitize("<svg><g></svg>").is_err());
+        assert!(SvgPreview::sanitize("").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, RenderFormat, Resource,
+    Result,
+};
+use crate::projections::text_raw::TextRaw;
+use crate::store::FileStore;
+
+/// Theme used for the inline-styled HTML; matches the dark UI.
+const THEME: &str = "base16-ocean.dark";
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme_set() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// Server-side syntax highlighting for recognized source files.
+pub struct SyntaxHighlight;
+
+impl SyntaxHighlight {
+    fn highlight(content: &str, file_name: &str) -> Result<String> {
+        let syntaxes = syntax_set();
+        // Syntaxes list whole names like `Makefile` among their extensions;
+        // unknown languages fall back to plain (escaped) text
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .and_then(|e| e.to_str());
+        let syntax = syntaxes
+            .find_syntax_by_extension(file_name)
+            .or_else(|| extension.and_then(|ext| syntaxes.find_syntax_by_extension(ext)))
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+        let theme = &theme_set().themes[THEME];
+        highlighted_html_for_string(content, syntaxes, syntax, theme)
+            .map_err(|e| ProjectionError::Other(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl Projection for SyntaxHighlight {
+    fn id(&self) -> &str {
+        "text.highlight"
+    }
+
+    fn name(&self) -> &str {
+        "Highlighted"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Source code with syntax highlighting")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("code")
+    }
+
+    fn formats(&self) -> &[RenderFormat] {
+        &[RenderFormat::Html]
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        // Above text.raw, below dedicated viewers like text.markdown
+        match TextRaw::language_for(resource) {
+            Some(_) => 0.85,
+            None => 0.0,
+        }
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let content = workspace
+            .read_to_string(std::path::Path::new(&resource.path))
+            .await?;
+        let line_count = content.lines().count();
+        let file_name = resource.file_name().to_owned();
+        let html = tokio::task::spawn_blocking(move || Self::highlight(&content, &file_name))
+            .await
+            .map_err(|e| ProjectionError::Other(e.to_string()))??;
+        let language = TextRaw::language_for(resource);
+        Ok(ProjectionOutput::HighlightedText {
+            html,
+            language,
+            line_count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_known_language() {
+        let html = SyntaxHighlight::highlight("fn main() {}\n", "main.rs").unwrap();
+        assert!(html.contains("<span"));
+        assert!(html.contains("main"));
+    }
+
+    #[test]
+    fn highlight_unknown_language_escapes() {
+        let html = SyntaxHighlight::highlight("<b>&</b>\n", "page.unknown").unwrap();
+        assert!(html.contains("&lt;b&gt;&amp;&lt;/b&gt;"));
+    }
+
+    #[test]
+    fn highlight_by_file_name() {
+        let html = SyntaxHighlight::highlight("all:\n\tcc -o app app.c\n", "Makefile").unwrap();
+        assert!(html.contains("<span"));
+        assert_ne!(
+            html,
+            SyntaxHighlight::highlight("all:\n\tcc -o app app.c\n", "notes").unwrap()
+        );
+    }
+}
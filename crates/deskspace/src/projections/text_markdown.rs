@@ -1,11 +1,33 @@
 use async_trait::async_trait;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
 
-use crate::projection::{Projection, ProjectionOutput, Resource, Result, TocEntry};
-use crate::workspace::Workspace;
+use crate::projection::{
+    Projection, ProjectionError, ProjectionOutput, ProjectionRequest, RenderFormat, Resource,
+    Result, TocEntry,
+};
+use crate::store::FileStore;
 
 pub struct TextMarkdown;
 
 impl TextMarkdown {
+    /// Anchor id for a heading; shared by the TOC and rendered HTML so
+    /// in-page links resolve.
+    fn slugify(text: &str) -> String {
+        text.to_lowercase()
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else if c == ' ' {
+                    '-'
+                } else {
+                    ' ' // will be filtered
+                }
+            })
+            .filter(|c| *c != ' ')
+            .collect()
+    }
+
     fn extract_toc(raw: &str) -> Vec<TocEntry> {
         let mut toc = Vec::new();
         for line in raw.lines() {
@@ -24,25 +46,45 @@ impl TextMarkdown {
                 if text.is_empty() {
                     continue;
                 }
-                let slug = text
-                    .to_lowercase()
-                    .chars()
-                    .map(|c| {
-                        if c.is_alphanumeric() || c == '-' || c == '_' {
-                            c
-                        } else if c == ' ' {
-                            '-'
-                        } else {
-                            ' ' // will be filtered
-                        }
-                    })
-                    .filter(|c| *c != ' ')
-                    .collect();
+                let slug = Self::slugify(&text);
                 toc.push(TocEntry { level, text, slug });
             }
         }
         toc
     }
+
+    /// Render Markdown to sanitized HTML, giving headings the same ids as
+    /// their TOC slugs.
+    pub(crate) fn render_html(raw: &str) -> String {
+        let mut events: Vec<Event> = Parser::new_ext(raw, Options::all()).collect();
+
+        let mut i = 0;
+        while i < events.len() {
+            if let Event::Start(Tag::Heading { .. }) = events[i] {
+                let mut text = String::new();
+                for event in &events[i + 1..] {
+                    match event {
+                        Event::End(TagEnd::Heading(_)) => break,
+                        Event::Text(t) | Event::Code(t) => text.push_str(t),
+                        _ => {}
+                    }
+                }
+                if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+                    *id = Some(CowStr::from(Self::slugify(text.trim())));
+                }
+            }
+            i += 1;
+        }
+
+        let mut unsafe_html = String::new();
+        html::push_html(&mut unsafe_html, events.into_iter());
+
+        let mut sanitizer = ammonia::Builder::default();
+        for tag in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+            sanitizer.add_tag_attributes(tag, &["id"]);
+        }
+        sanitizer.clean(&unsafe_html).to_string()
+    }
 }
 
 #[async_trait]
@@ -55,6 +97,18 @@ impl Projection for TextMarkdown {
         "Markdown"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("Markdown with a table of contents")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("markdown")
+    }
+
+    fn formats(&self) -> &[RenderFormat] {
+        &[RenderFormat::Html]
+    }
+
     fn confidence(&self, resource: &Resource) -> f32 {
         if resource.is_dir {
             return 0.0;
@@ -68,13 +122,22 @@ impl Projection for TextMarkdown {
     async fn project(
         &self,
         resource: &Resource,
-        workspace: &Workspace,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
     ) -> Result<ProjectionOutput> {
         let raw = workspace
             .read_to_string(std::path::Path::new(&resource.path))
             .await?;
         let toc = Self::extract_toc(&raw);
-        Ok(ProjectionOutput::Markdown { raw, toc })
+        let html = match request.get("render") {
+            Some("html") => Some(Self::render_html(&raw)),
+            Some(other) => {
+                return Err(ProjectionError::InvalidOption(format!("render={other}")));
+            }
+            None if request.format()? == Some(RenderFormat::Html) => Some(Self::render_html(&raw)),
+            None => None,
+        };
+        Ok(ProjectionOutput::Markdown { raw, toc, html })
     }
 }
 
@@ -102,6 +165,21 @@ mod tests {
         assert_eq!(toc[0].slug, "hello-world-v20");
     }
 
+    #[test]
+    fn render_html_heading_ids_match_toc() {
+        let md = "# Hello, World!\n\n## Second `part`\n";
+        let html = TextMarkdown::render_html(md);
+        for entry in TextMarkdown::extract_toc(md) {
+            assert!(html.contains(&format!("id=\"{}\"", entry.slug)), "{html}");
+        }
+    }
+
+    #[test]
+    fn render_html_strips_scripts() {
+        let html = TextMarkdown::render_html("hi <script>alert(1)</script>\n");
+        assert!(!html.contains("<script"));
+    }
+
     #[test]
     fn extract_toc_skips_empty_headings() {
         let md = "# \n## Real heading\n";
@@ -1,7 +1,10 @@
 use async_trait::async_trait;
 
-use crate::projection::{Projection, ProjectionOutput, Resource, Result};
-use crate::workspace::Workspace;
+use crate::projection::{
+    Indent, IndentKind, LineEnding, LineRange, Projection, ProjectionError, ProjectionOutput,
+    ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
 
 const TEXT_EXTENSIONS: &[&str] = &[
     "txt",
@@ -75,10 +78,73 @@ const TEXT_EXTENSIONS: &[&str] = &[
     "makefile",
 ];
 
+/// Files recognized by their whole name, which usually have no extension,
+/// with their language. Matched case-insensitively.
+const TEXT_FILE_NAMES: &[(&str, &str)] = &[
+    ("dockerfile", "dockerfile"),
+    ("containerfile", "dockerfile"),
+    ("makefile", "makefile"),
+    ("gnumakefile", "makefile"),
+    ("cmakelists.txt", "cmake"),
+    ("rakefile", "ruby"),
+    ("gemfile", "ruby"),
+    ("vagrantfile", "ruby"),
+    ("jenkinsfile", "groovy"),
+    (".bashrc", "bash"),
+    (".bash_profile", "bash"),
+    (".zshrc", "bash"),
+    (".profile", "bash"),
+];
+
+/// Bytes inspected when deciding whether a file is binary.
+const SNIFF_BYTES: usize = 8192;
+
+/// Bytes of decoded text examined for line endings and indentation.
+const WHITESPACE_SCAN_BYTES: usize = 64 * 1024;
+
 pub struct TextRaw;
 
+/// File contents decoded to UTF-8.
+#[derive(Debug, PartialEq)]
+struct Decoded {
+    content: String,
+    /// Name of the source encoding, e.g. `UTF-8` or `windows-1252`.
+    encoding: &'static str,
+    /// Whether undecodable bytes were replaced with U+FFFD.
+    lossy: bool,
+}
+
 impl TextRaw {
-    fn detect_language(ext: &str) -> Option<String> {
+    /// Language of `resource`, from its file name when that is a known one,
+    /// otherwise from its extension.
+    pub(crate) fn language_for(resource: &Resource) -> Option<String> {
+        if resource.is_dir {
+            return None;
+        }
+        Self::language_for_name(resource.file_name())
+            .map(String::from)
+            .or_else(|| {
+                resource
+                    .extension
+                    .as_deref()
+                    .and_then(Self::detect_language)
+            })
+    }
+
+    /// Language of a file known by name, like `Dockerfile` or `Makefile`.
+    /// Variants such as `Dockerfile.dev` count too.
+    fn language_for_name(name: &str) -> Option<&'static str> {
+        let name = name.to_lowercase();
+        if name.starts_with("dockerfile.") {
+            return Some("dockerfile");
+        }
+        TEXT_FILE_NAMES
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, language)| *language)
+    }
+
+    pub(crate) fn detect_language(ext: &str) -> Option<String> {
         match ext {
             "rs" => Some("rust"),
             "py" => Some("python"),
@@ -109,10 +175,207 @@ impl TextRaw {
             "r" => Some("r"),
             "jl" => Some("julia"),
             "diff" | "patch" => Some("diff"),
+            "dockerfile" => Some("dockerfile"),
+            "makefile" => Some("makefile"),
             _ => None,
         }
         .map(String::from)
     }
+
+    /// Treat the file as binary if more than 10% of its leading bytes are NUL.
+    /// UTF-16 text also has many NULs, so callers check for a BOM first.
+    fn looks_binary(bytes: &[u8]) -> bool {
+        let head = &bytes[..bytes.len().min(SNIFF_BYTES)];
+        let nuls = head.iter().filter(|&&b| b == 0).count();
+        nuls * 10 > head.len()
+    }
+
+    /// UTF-16 without a BOM, recognised by mostly-ASCII text leaving one
+    /// byte of nearly every code unit NUL and the other almost never.
+    fn utf16_without_bom(bytes: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+        let head = &bytes[..bytes.len().min(SNIFF_BYTES)];
+        let units = head.len() / 2;
+        if units < 2 {
+            return None;
+        }
+        let (mut high_first, mut high_second) = (0, 0);
+        for unit in head.chunks_exact(2) {
+            match unit {
+                [0, 0] => return None,
+                [0, _] => high_first += 1,
+                [_, 0] => high_second += 1,
+                _ => {}
+            }
+        }
+        let mostly = |n: usize| n * 10 >= units * 7;
+        let rarely = |n: usize| n * 20 <= units;
+        if mostly(high_second) && rarely(high_first) {
+            Some(encoding_rs::UTF_16LE)
+        } else if mostly(high_first) && rarely(high_second) {
+            Some(encoding_rs::UTF_16BE)
+        } else {
+            None
+        }
+    }
+
+    /// Whether `head`, the start of a file, decodes as text.
+    pub(crate) fn looks_like_text(head: &[u8]) -> bool {
+        encoding_rs::Encoding::for_bom(head).is_some()
+            || Self::utf16_without_bom(head).is_some()
+            || !Self::looks_binary(head)
+    }
+
+    /// Encoding of text starting with `head`, for a `charset` parameter: a
+    /// BOM wins, then BOM-less UTF-16, then UTF-8, then a statistical
+    /// guess. `None` for binary data.
+    pub(crate) fn charset(head: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+        if let Some((encoding, _)) = encoding_rs::Encoding::for_bom(head) {
+            return Some(encoding);
+        }
+        if let Some(encoding) = Self::utf16_without_bom(head) {
+            return Some(encoding);
+        }
+        if Self::looks_binary(head) {
+            return None;
+        }
+        // The head may end partway through a character
+        match std::str::from_utf8(head) {
+            Ok(_) => return Some(encoding_rs::UTF_8),
+            Err(e) if e.error_len().is_none() => return Some(encoding_rs::UTF_8),
+            Err(_) => {}
+        }
+        let mut detector = chardetng::EncodingDetector::new(chardetng::Iso2022JpDetection::Allow);
+        detector.feed(head, false);
+        Some(detector.guess(None, chardetng::Utf8Detection::Allow))
+    }
+
+    /// Whether the resource's name alone marks it as text: a known text
+    /// extension or a name like `Dockerfile`.
+    pub(crate) fn claims_name(resource: &Resource) -> bool {
+        Self::language_for_name(resource.file_name()).is_some()
+            || resource
+                .extension
+                .as_deref()
+                .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext))
+    }
+
+    /// Decode file bytes: a BOM wins, then BOM-less UTF-16, then strict
+    /// UTF-8, then a statistical guess (Latin-1 logs, Shift_JIS, ...).
+    /// Returns `None` for binary data.
+    fn decode(bytes: &[u8]) -> Option<Decoded> {
+        let encoding = match encoding_rs::Encoding::for_bom(bytes) {
+            Some((encoding, _)) => encoding,
+            None => match Self::utf16_without_bom(bytes) {
+                Some(encoding) => encoding,
+                None => {
+                    if Self::looks_binary(bytes) {
+                        return None;
+                    }
+                    if let Ok(text) = std::str::from_utf8(bytes) {
+                        return Some(Decoded {
+                            content: text.to_string(),
+                            encoding: encoding_rs::UTF_8.name(),
+                            lossy: false,
+                        });
+                    }
+                    // Mostly-UTF-8 files with a few bad bytes still guess UTF-8
+                    let mut detector =
+                        chardetng::EncodingDetector::new(chardetng::Iso2022JpDetection::Allow);
+                    detector.feed(bytes, true);
+                    detector.guess(None, chardetng::Utf8Detection::Allow)
+                }
+            },
+        };
+        let (content, encoding, lossy) = encoding.decode(bytes);
+        Some(Decoded {
+            content: content.into_owned(),
+            encoding: encoding.name(),
+            lossy,
+        })
+    }
+
+    /// Start of `content`, at most `WHITESPACE_SCAN_BYTES` long.
+    fn scan_prefix(content: &str) -> &str {
+        let mut end = content.len().min(WHITESPACE_SCAN_BYTES);
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        &content[..end]
+    }
+
+    /// Whether the line breaks in `content` are LF, CRLF, or a mix.
+    fn line_ending(content: &str) -> Option<LineEnding> {
+        let content = Self::scan_prefix(content);
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        match (lf, crlf) {
+            (0, 0) => None,
+            (_, 0) => Some(LineEnding::Lf),
+            (0, _) => Some(LineEnding::Crlf),
+            _ => Some(LineEnding::Mixed),
+        }
+    }
+
+    /// Indentation style of `content`: tabs if more indented lines start
+    /// with a tab than a space, otherwise spaces with the width that most
+    /// often separates one line's indent from the previous line's.
+    fn indent(content: &str) -> Option<Indent> {
+        let mut tabs = 0;
+        let mut spaces = 0;
+        // How often each increase in leading spaces occurs, for 1..=8
+        let mut steps = [0usize; 9];
+        let mut previous = 0;
+        for line in Self::scan_prefix(content).lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with('\t') {
+                tabs += 1;
+                continue;
+            }
+            let width = line.len() - line.trim_start_matches(' ').len();
+            if width > 0 {
+                spaces += 1;
+            }
+            if width > previous && width - previous < steps.len() {
+                steps[width - previous] += 1;
+            }
+            previous = width;
+        }
+        if tabs == 0 && spaces == 0 {
+            return None;
+        }
+        if tabs > spaces {
+            return Some(Indent {
+                kind: IndentKind::Tabs,
+                width: 1,
+            });
+        }
+        // Single-space steps are mostly alignment, like ` *` in block
+        // comments, so they only count when nothing else does
+        let width = (2..steps.len())
+            .filter(|&w| steps[w] > 0)
+            .max_by_key(|&w| (steps[w], std::cmp::Reverse(w)))
+            .or((steps[1] > 0).then_some(1))?;
+        Some(Indent {
+            kind: IndentKind::Spaces,
+            width: width as u8,
+        })
+    }
+
+    /// Cut `content` down to lines `start..end` (zero-based, end exclusive),
+    /// clamping both bounds to the lines that exist.
+    fn slice_lines(content: &str, start: usize, end: Option<usize>) -> (String, LineRange) {
+        let line_count = content.lines().count();
+        let start = start.min(line_count);
+        let end = end.unwrap_or(line_count).clamp(start, line_count);
+        let slice = content
+            .split_inclusive('\n')
+            .skip(start)
+            .take(end - start)
+            .collect();
+        (slice, LineRange { start, end })
+    }
 }
 
 #[async_trait]
@@ -125,13 +388,25 @@ impl Projection for TextRaw {
         "Plain Text"
     }
 
+    fn description(&self) -> Option<&str> {
+        Some("File contents as plain text")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("text")
+    }
+
     fn confidence(&self, resource: &Resource) -> f32 {
         if resource.is_dir {
             return 0.0;
         }
+        if Self::claims_name(resource) {
+            return 0.8;
+        }
         match &resource.extension {
-            Some(ext) if TEXT_EXTENSIONS.contains(&ext.as_str()) => 0.8,
             Some(_) => 0.0,
+            // Sniffed as a binary format: leave it to that format's viewer
+            None if resource.sniffed_binary() => 0.0,
             None => 0.3, // extensionless files are often text
         }
     }
@@ -139,20 +414,188 @@ impl Projection for TextRaw {
     async fn project(
         &self,
         resource: &Resource,
-        workspace: &Workspace,
+        workspace: &dyn FileStore,
+        request: &ProjectionRequest,
     ) -> Result<ProjectionOutput> {
-        let content = workspace
-            .read_to_string(std::path::Path::new(&resource.path))
-            .await?;
+        let start = request.parse::<usize>("start")?;
+        let end = request.parse::<usize>("end")?;
+        let bytes = workspace.read(std::path::Path::new(&resource.path)).await?;
+        let Decoded {
+            content,
+            encoding,
+            lossy,
+        } = Self::decode(&bytes)
+            .ok_or_else(|| ProjectionError::Other("file looks binary".to_string()))?;
         let line_count = content.lines().count();
-        let language = resource
-            .extension
-            .as_deref()
-            .and_then(Self::detect_language);
+        let line_ending = Self::line_ending(&content);
+        let indent = Self::indent(&content);
+        let (content, range) = if start.is_some() || end.is_some() {
+            let (slice, range) = Self::slice_lines(&content, start.unwrap_or(0), end);
+            (slice, Some(range))
+        } else {
+            (content, None)
+        };
+        let language = Self::language_for(resource);
         Ok(ProjectionOutput::Text {
             content,
             language,
             line_count,
+            range,
+            encoding: Some(encoding.to_string()),
+            lossy,
+            line_ending,
+            indent,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_utf8() {
+        let decoded = TextRaw::decode("héllo".as_bytes()).unwrap();
+        assert_eq!(decoded.content, "héllo");
+        assert_eq!(decoded.encoding, "UTF-8");
+        assert!(!decoded.lossy);
+    }
+
+    #[test]
+    fn decode_latin1() {
+        let decoded = TextRaw::decode(b"caf\xe9 cr\xe8me br\xfbl\xe9e\n").unwrap();
+        assert_eq!(decoded.content, "café crème brûlée\n");
+        assert_eq!(decoded.encoding, "windows-1252");
+        assert!(!decoded.lossy);
+    }
+
+    #[test]
+    fn decode_utf16_bom() {
+        let decoded = TextRaw::decode(b"\xff\xfeh\x00i\x00").unwrap();
+        assert_eq!(decoded.content, "hi");
+        assert_eq!(decoded.encoding, "UTF-16LE");
+    }
+
+    #[test]
+    fn decode_utf16_without_bom() {
+        let le: Vec<u8> = "fn main() {}\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let decoded = TextRaw::decode(&le).unwrap();
+        assert_eq!(decoded.content, "fn main() {}\n");
+        assert_eq!(decoded.encoding, "UTF-16LE");
+
+        let be: Vec<u8> = "fn main() {}\n"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        assert_eq!(TextRaw::decode(&be).unwrap().encoding, "UTF-16BE");
+    }
+
+    #[test]
+    fn decode_rejects_binary() {
+        assert!(TextRaw::decode(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00").is_none());
+        assert!(TextRaw::decode(&[0u8; 64]).is_none());
+    }
+
+    #[test]
+    fn charset_of_a_head() {
+        let name = |head: &[u8]| TextRaw::charset(head).map(|e| e.name());
+        assert_eq!(name(b"\xef\xbb\xbfhi"), Some("UTF-8"));
+        assert_eq!(name(b"\xfe\xff\x00h\x00i"), Some("UTF-16BE"));
+        // Cut off inside `é`
+        assert_eq!(name(&"caf\u{e9}".as_bytes()[..4]), Some("UTF-8"));
+        assert_eq!(
+            name(b"caf\xe9 cr\xe8me br\xfbl\xe9e\n"),
+            Some("windows-1252")
+        );
+        assert_eq!(name(&[0u8; 64]), None);
+    }
+
+    #[test]
+    fn sniffed_binary_extensionless_files_are_not_claimed() {
+        let elf = Resource::new("a.out.d/program".into(), false)
+            .with_mime_type(Some("application/x-executable".into()));
+        assert_eq!(TextRaw.confidence(&elf), 0.0);
+        let script = Resource::new("bin/run".into(), false)
+            .with_mime_type(Some("text/x-shellscript".into()));
+        assert_eq!(TextRaw.confidence(&script), 0.3);
+    }
+
+    #[test]
+    fn slice_lines_window() {
+        let content = "a\nb\nc\nd\n";
+        let (slice, range) = TextRaw::slice_lines(content, 1, Some(3));
+        assert_eq!(slice, "b\nc\n");
+        assert_eq!(range, LineRange { start: 1, end: 3 });
+    }
+
+    #[test]
+    fn slice_lines_clamps_out_of_range() {
+        let content = "a\nb\nc";
+        let (slice, range) = TextRaw::slice_lines(content, 2, Some(100));
+        assert_eq!(slice, "c");
+        assert_eq!(range, LineRange { start: 2, end: 3 });
+
+        let (slice, range) = TextRaw::slice_lines(content, 50, None);
+        assert_eq!(slice, "");
+        assert_eq!(range, LineRange { start: 3, end: 3 });
+
+        let (_, range) = TextRaw::slice_lines(content, 2, Some(1));
+        assert_eq!(range, LineRange { start: 2, end: 2 });
+    }
+
+    #[test]
+    fn language_from_file_name() {
+        let language = |path: &str| TextRaw::language_for(&Resource::new(path.into(), false));
+        assert_eq!(language("Dockerfile").as_deref(), Some("dockerfile"));
+        assert_eq!(
+            language("deploy/Dockerfile.dev").as_deref(),
+            Some("dockerfile")
+        );
+        assert_eq!(language("src/Makefile").as_deref(), Some("makefile"));
+        assert_eq!(language("CMakeLists.txt").as_deref(), Some("cmake"));
+        assert_eq!(language("main.rs").as_deref(), Some("rust"));
+        assert_eq!(language("README"), None);
+
+        let dockerfile = Resource::new("deploy/Dockerfile.dev".into(), false);
+        assert_eq!(TextRaw.confidence(&dockerfile), 0.8);
+        assert_eq!(
+            TextRaw.confidence(&Resource::new("README".into(), false)),
+            0.3
+        );
+    }
+
+    #[test]
+    fn detects_line_endings() {
+        assert_eq!(TextRaw::line_ending("a\nb\n"), Some(LineEnding::Lf));
+        assert_eq!(TextRaw::line_ending("a\r\nb\r\n"), Some(LineEnding::Crlf));
+        assert_eq!(TextRaw::line_ending("a\r\nb\n"), Some(LineEnding::Mixed));
+        assert_eq!(TextRaw::line_ending("no breaks"), None);
+    }
+
+    #[test]
+    fn detects_indentation() {
+        let spaces = |width| Indent {
+            kind: IndentKind::Spaces,
+            width,
+        };
+        let four = "fn main() {\n    if x {\n        y();\n    }\n}\n";
+        assert_eq!(TextRaw::indent(four), Some(spaces(4)));
+        let two = "a:\n  b:\n    c: 1\n  d: 2\n";
+        assert_eq!(TextRaw::indent(two), Some(spaces(2)));
+        let tabs = "func f() {\n\tif x {\n\t\ty()\n\t}\n}\n";
+        assert_eq!(
+            TextRaw::indent(tabs),
+            Some(Indent {
+                kind: IndentKind::Tabs,
+                width: 1
+            })
+        );
+        // Block comment alignment doesn't make it one-space indented
+        let comment = "/**\n * Docs\n */\nfn f() {\n    g();\n}\n";
+        assert_eq!(TextRaw::indent(comment), Some(spaces(4)));
+        assert_eq!(TextRaw::indent("flat\ntext\n"), None);
+    }
+}
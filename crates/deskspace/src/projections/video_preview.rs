@@ -0,0 +1,201 @@
+use std::io::{BufReader, Seek};
+use std::path::Path;
+
+use async_trait::async_trait;
+
+use crate::projection::{
+    raw_url, Projection, ProjectionOutput, ProjectionRequest, Resource, Result,
+};
+use crate::store::FileStore;
+
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "webm", "mkv"];
+const VIDEO_MIME_TYPES: &[&str] = &[
+    "video/mp4",
+    "video/quicktime",
+    "video/webm",
+    "video/x-matroska",
+];
+
+pub struct VideoPreview;
+
+/// Header metadata; every field is best-effort.
+#[derive(Debug, Default, PartialEq)]
+struct VideoMeta {
+    duration_secs: Option<f64>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+impl VideoPreview {
+    /// Parse container headers only. Both parsers seek past media data, so
+    /// large files are never buffered. Unparseable files yield empty metadata;
+    /// the raw URL is still playable by the browser.
+    fn read_meta(path: &Path, ext: &str) -> VideoMeta {
+        let result = match ext {
+            "webm" | "mkv" => Self::read_matroska(path),
+            _ => Self::read_mp4(path),
+        };
+        result.unwrap_or_else(|e| {
+            tracing::debug!("no video metadata for {}: {e}", path.display());
+            VideoMeta::default()
+        })
+    }
+
+    fn read_mp4(path: &Path) -> std::result::Result<VideoMeta, Box<dyn std::error::Error>> {
+        let mut file = BufReader::new(std::fs::File::open(path)?);
+        let size = file.seek(std::io::SeekFrom::End(0))?;
+        file.rewind()?;
+        let mp4 = mp4::Mp4Reader::read_header(file, size)?;
+        let video = mp4
+            .tracks()
+            .values()
+            .find(|t| matches!(t.track_type(), Ok(mp4::TrackType::Video)));
+        let duration = mp4.duration();
+        Ok(VideoMeta {
+            duration_secs: (!duration.is_zero()).then_some(duration.as_secs_f64()),
+            width: video.map(|t| u32::from(t.width())).filter(|&w| w > 0),
+            height: video.map(|t| u32::from(t.height())).filter(|&h| h > 0),
+        })
+    }
+
+    fn read_matroska(path: &Path) -> std::result::Result<VideoMeta, Box<dyn std::error::Error>> {
+        let mkv = matroska::open(path)?;
+        let video = mkv.tracks.iter().find_map(|t| match &t.settings {
+            matroska::Settings::Video(video) => Some(video),
+            _ => None,
+        });
+        Ok(VideoMeta {
+            duration_secs: mkv.info.duration.map(|d| d.as_secs_f64()),
+            width: video.and_then(|v| u32::try_from(v.pixel_width).ok()),
+            height: video.and_then(|v| u32::try_from(v.pixel_height).ok()),
+        })
+    }
+}
+
+#[async_trait]
+impl Projection for VideoPreview {
+    fn id(&self) -> &str {
+        "video.preview"
+    }
+
+    fn name(&self) -> &str {
+        "Video"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Video player with dimensions and duration")
+    }
+
+    fn icon(&self) -> Option<&str> {
+        Some("video")
+    }
+
+    fn confidence(&self, resource: &Resource) -> f32 {
+        if resource.is_dir {
+            return 0.0;
+        }
+        match &resource.extension {
+            Some(ext) if VIDEO_EXTENSIONS.contains(&ext.as_str()) => 0.9,
+            _ if resource.mime_is(VIDEO_MIME_TYPES) => 0.8,
+            _ => 0.0,
+        }
+    }
+
+    fn reads_whole_file(&self) -> bool {
+        false
+    }
+
+    async fn project(
+        &self,
+        resource: &Resource,
+        workspace: &dyn FileStore,
+        _request: &ProjectionRequest,
+    ) -> Result<ProjectionOutput> {
+        let path = workspace.local_path(&resource.path)?;
+        let ext = match resource.extension.as_deref() {
+            Some(ext) if VIDEO_EXTENSIONS.contains(&ext) => ext.to_string(),
+            _ if resource.mime_is(&["video/webm", "video/x-matroska"]) => "mkv".to_string(),
+            _ => "mp4".to_string(),
+        };
+        let mime_type = mime_guess::from_path(&resource.path)
+            .first()
+            .map(|m| m.to_string())
+            .or_else(|| resource.mime_type.clone())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        // The matroska parser asserts on some malformed input; a panic only
+        // costs the metadata, the file stays playable.
+        let meta = tokio::task::spawn_blocking(move || Self::read_meta(&path, &ext))
+            .await
+            .unwrap_or_else(|e| {
+                tracing::debug!("video metadata reader failed for {}: {e}", resource.path);
+                VideoMeta::default()
+            });
+        Ok(ProjectionOutput::Video {
+            raw_url: raw_url(workspace, &resource.path).await,
+            mime_type,
+            duration_secs: meta.duration_secs,
+            width: meta.width,
+            height: meta.height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_mp4_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.mp4");
+        let config = mp4::Mp4Config {
+            major_brand: "isom".parse().unwrap(),
+            minor_version: 512,
+            compatible_brands: vec!["isom".parse().unwrap()],
+            timescale: 1000,
+        };
+        let mut writer =
+            mp4::Mp4Writer::write_start(std::fs::File::create(&path).unwrap(), &config).unwrap();
+        writer
+            .add_track(&mp4::TrackConfig {
+                track_type: mp4::TrackType::Video,
+                timescale: 1000,
+                language: "und".to_string(),
+                media_conf: mp4::MediaConfig::AvcConfig(mp4::AvcConfig {
+                    width: 320,
+                    height: 240,
+                    seq_param_set: vec![0x67, 0x42, 0x00, 0x0a],
+                    pic_param_set: vec![0x68, 0xce, 0x3c, 0x80],
+                }),
+            })
+            .unwrap();
+        for i in 0..3 {
+            writer
+                .write_sample(
+                    1,
+                    &mp4::Mp4Sample {
+                        start_time: i * 1000,
+                        duration: 1000,
+                        rendering_offset: 0,
+                        is_sync: true,
+                        bytes: vec![0u8; 16].into(),
+                    },
+                )
+                .unwrap();
+        }
+        writer.write_end().unwrap();
+
+        let meta = VideoPreview::read_meta(&path, "mp4");
+        assert_eq!(meta.width, Some(320));
+        assert_eq!(meta.height, Some(240));
+        assert_eq!(meta.duration_secs, Some(3.0));
+    }
+
+    #[test]
+    fn unparseable_video_has_no_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.mp4");
+        std::fs::write(&path, b"not a video at all").unwrap();
+        assert_eq!(VideoPreview::read_meta(&path, "mp4"), VideoMeta::default());
+    }
+}
@@ -2,17 +2,34 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use serde::Serialize;
+use utoipa::ToSchema;
 
-use crate::projection::{Projection, Resource};
+use crate::projection::{Projection, RenderFormat, Resource, ResourceCategory};
 
 pub struct ProjectionRegistry {
     projections: HashMap<String, Arc<dyn Projection>>,
+    /// Confidence overrides keyed by (extension, projection id).
+    overrides: HashMap<(String, String), ConfidenceOverride>,
+    /// Configured fallback chains keyed by extension, `*` for all files.
+    fallbacks: HashMap<String, Vec<String>>,
+    /// Preferred projection id for each category of resource.
+    defaults: HashMap<ResourceCategory, String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ConfidenceOverride {
+    confidence: f32,
+    /// Apply even when the projection reports no confidence on its own.
+    force: bool,
 }
 
 impl ProjectionRegistry {
     pub fn new() -> Self {
         Self {
             projections: HashMap::new(),
+            overrides: HashMap::new(),
+            fallbacks: HashMap::new(),
+            defaults: HashMap::new(),
         }
     }
 
@@ -25,16 +42,158 @@ impl ProjectionRegistry {
         self.projections.get(id)
     }
 
-    /// Return the projection with the highest confidence for the given resource.
+    /// Number of registered projections.
+    pub fn len(&self) -> usize {
+        self.projections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.projections.is_empty()
+    }
+
+    /// Keep only the projections whose id satisfies `keep`.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.projections.retain(|id, _| keep(id));
+    }
+
+    /// Replace a projection's confidence for files with `extension`. Unless
+    /// `force` is set, the override only applies when the projection already
+    /// reports some confidence, so it can't be pinned to files it can't read.
+    pub fn register_override(
+        &mut self,
+        extension: &str,
+        projection_id: &str,
+        confidence: f32,
+        force: bool,
+    ) {
+        self.overrides.insert(
+            (extension.to_lowercase(), projection_id.to_owned()),
+            ConfidenceOverride { confidence, force },
+        );
+    }
+
+    /// Projections to try, in order, when the chosen one fails on files with
+    /// `extension` (`*` for any file without a chain of its own). Replaces
+    /// the default of falling back through the other matches by confidence.
+    pub fn register_fallbacks(&mut self, extension: &str, projection_ids: Vec<String>) {
+        self.fallbacks
+            .insert(extension.to_lowercase(), projection_ids);
+    }
+
+    /// Open resources in `category` with `projection_id` by default, when
+    /// it reports some confidence for them, ahead of the most confident.
+    pub fn register_default(&mut self, category: ResourceCategory, projection_id: &str) {
+        self.defaults.insert(category, projection_id.to_owned());
+    }
+
+    /// `register_default` for each configured category, warning about and
+    /// skipping any that name a projection that isn't registered.
+    pub fn register_defaults(&mut self, defaults: &HashMap<ResourceCategory, String>) {
+        for (&category, id) in defaults {
+            if self.get(id).is_none() {
+                tracing::warn!(
+                    "default for {category:?} names unavailable projection {id:?}, ignoring"
+                );
+                continue;
+            }
+            self.register_default(category, id);
+        }
+    }
+
+    /// The configured default for `resource`'s category, if it's registered
+    /// and can show the resource.
+    fn default_for(&self, resource: &Resource) -> Option<&Arc<dyn Projection>> {
+        let id = self.defaults.get(&resource.category())?;
+        self.get(id)
+            .filter(|p| self.confidence(p.as_ref(), resource) > 0.0)
+    }
+
+    /// Projections to try after `failed` couldn't show `resource`: the
+    /// configured chain for its extension or the global one, else every
+    /// other match in descending confidence.
+    pub fn fallbacks_for(&self, resource: &Resource, failed: &str) -> Vec<Arc<dyn Projection>> {
+        let configured = resource
+            .extension
+            .as_ref()
+            .and_then(|ext| self.fallbacks.get(ext))
+            .or_else(|| self.fallbacks.get("*"));
+        match configured {
+            Some(ids) => ids
+                .iter()
+                .filter(|id| *id != failed)
+                .filter_map(|id| self.get(id).cloned())
+                .collect(),
+            None => self
+                .available_for(resource)
+                .into_iter()
+                .filter(|info| info.id != failed)
+                .filter_map(|info| self.get(&info.id).cloned())
+                .collect(),
+        }
+    }
+
+    /// Confidence of `projection` for `resource`, after overrides.
+    fn confidence(&self, projection: &dyn Projection, resource: &Resource) -> f32 {
+        self.scored(projection, resource).0
+    }
+
+    /// Confidence of `projection` for `resource` and whether an override
+    /// supplied it.
+    fn scored(&self, projection: &dyn Projection, resource: &Resource) -> (f32, bool) {
+        let reported = projection.confidence(resource);
+        let Some(ext) = &resource.extension else {
+            return (reported, false);
+        };
+        match self
+            .overrides
+            .get(&(ext.clone(), projection.id().to_owned()))
+        {
+            Some(o) if o.force || reported > 0.0 => (o.confidence, true),
+            _ => (reported, false),
+        }
+    }
+
+    /// Why `projection` is the one shown for `resource`: `explicit` when the
+    /// client asked for it by id, `default` when it's the configured default
+    /// for the resource's category, otherwise it won on confidence.
+    pub fn selection(
+        &self,
+        projection: &dyn Projection,
+        resource: &Resource,
+        explicit: bool,
+    ) -> Selection {
+        let (confidence, overridden) = self.scored(projection, resource);
+        let reason = if explicit {
+            SelectionReason::Explicit
+        } else if self
+            .default_for(resource)
+            .is_some_and(|p| p.id() == projection.id())
+        {
+            SelectionReason::Default
+        } else if overridden {
+            SelectionReason::Override
+        } else {
+            SelectionReason::HighestConfidence
+        };
+        Selection {
+            chosen: projection.id().to_owned(),
+            reason,
+            confidence,
+        }
+    }
+
+    /// Return the configured default for the resource's category, or else
+    /// the projection with the highest confidence for it.
     pub fn best_for(&self, resource: &Resource) -> Option<&Arc<dyn Projection>> {
+        if let Some(preferred) = self.default_for(resource) {
+            return Some(preferred);
+        }
         self.projections
             .values()
-            .filter(|p| p.confidence(resource) > 0.0)
-            .max_by(|a, b| {
-                a.confidence(resource)
-                    .partial_cmp(&b.confidence(resource))
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            })
+            .map(|p| (p, self.confidence(p.as_ref(), resource)))
+            .filter(|(_, c)| *c > 0.0)
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(p, _)| p)
     }
 
     /// Return all projections that match the resource, sorted by confidence descending.
@@ -42,12 +201,15 @@ impl ProjectionRegistry {
         let mut matches: Vec<_> = self
             .projections
             .values()
-            .filter(|p| p.confidence(resource) > 0.0)
             .map(|p| ProjectionInfo {
                 id: p.id().to_owned(),
                 name: p.name().to_owned(),
-                confidence: p.confidence(resource),
+                description: p.description().map(str::to_owned),
+                icon: p.icon().map(str::to_owned),
+                confidence: self.confidence(p.as_ref(), resource),
+                formats: p.formats().to_vec(),
             })
+            .filter(|info| info.confidence > 0.0)
             .collect();
         matches.sort_by(|a, b| {
             b.confidence
@@ -64,18 +226,46 @@ impl Default for ProjectionRegistry {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct ProjectionInfo {
     pub id: String,
     pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
     pub confidence: f32,
+    /// Forms the output can be served in besides JSON.
+    pub formats: Vec<RenderFormat>,
+}
+
+/// How the active projection was picked.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Selection {
+    pub chosen: String,
+    pub reason: SelectionReason,
+    /// Confidence of the chosen projection, after overrides. Can be 0 for
+    /// an explicitly requested projection.
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionReason {
+    /// Requested with `?projection=`.
+    Explicit,
+    /// Highest confidence of the available projections.
+    HighestConfidence,
+    /// Highest confidence, with the value coming from a configured override.
+    Override,
+    /// The configured default for the resource's category.
+    Default,
+    /// Tried after the projections ahead of it in the fallback chain failed.
+    Fallback,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::projection::{ProjectionError, ProjectionOutput, Resource};
-    use crate::workspace::Workspace;
+    use crate::projection::{ProjectionError, ProjectionOutput, ProjectionRequest, Resource};
     use async_trait::async_trait;
 
     struct DummyProjection {
@@ -97,7 +287,8 @@ mod tests {
         async fn project(
             &self,
             _resource: &Resource,
-            _workspace: &Workspace,
+            _workspace: &dyn crate::store::FileStore,
+            _request: &ProjectionRequest,
         ) -> crate::projection::Result<ProjectionOutput> {
             Err(ProjectionError::Unsupported)
         }
@@ -135,4 +326,240 @@ mod tests {
         assert_eq!(available[0].id, "high");
         assert_eq!(available[1].id, "low");
     }
+
+    #[test]
+    fn available_for_carries_description_and_icon() {
+        let mut reg = ProjectionRegistry::new();
+        reg.register(Arc::new(crate::projections::text_raw::TextRaw));
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "plain",
+            conf: 0.3,
+        }));
+        let resource = Resource::new("test.txt".into(), false);
+        let available = reg.available_for(&resource);
+        let raw = available.iter().find(|p| p.id == "text.raw").unwrap();
+        assert_eq!(raw.icon.as_deref(), Some("text"));
+        assert!(raw.description.is_some());
+        let plain = available.iter().find(|p| p.id == "plain").unwrap();
+        assert!(plain.icon.is_none() && plain.description.is_none());
+    }
+
+    #[test]
+    fn retain_drops_unlisted() {
+        let mut reg = ProjectionRegistry::new();
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "keep",
+            conf: 0.3,
+        }));
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "drop",
+            conf: 0.9,
+        }));
+        reg.retain(|id| id == "keep");
+        let resource = Resource::new("test.txt".into(), false);
+        assert_eq!(reg.best_for(&resource).unwrap().id(), "keep");
+        assert!(reg.get("drop").is_none());
+    }
+
+    #[test]
+    fn override_replaces_confidence_for_extension() {
+        let mut reg = ProjectionRegistry::new();
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "low",
+            conf: 0.3,
+        }));
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "high",
+            conf: 0.9,
+        }));
+        reg.register_override("TXT", "low", 1.0, false);
+        let txt = Resource::new("notes.txt".into(), false);
+        assert_eq!(reg.best_for(&txt).unwrap().id(), "low");
+        assert_eq!(reg.available_for(&txt)[0].confidence, 1.0);
+        let other = Resource::new("notes.md".into(), false);
+        assert_eq!(reg.best_for(&other).unwrap().id(), "high");
+    }
+
+    #[test]
+    fn selection_explains_the_choice() {
+        let mut reg = ProjectionRegistry::new();
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "low",
+            conf: 0.3,
+        }));
+        reg.register_override("txt", "low", 1.0, false);
+        let low = reg.get("low").unwrap().clone();
+
+        let md = Resource::new("notes.md".into(), false);
+        let selection = reg.selection(low.as_ref(), &md, false);
+        assert_eq!(selection.reason, SelectionReason::HighestConfidence);
+        assert_eq!(
+            (selection.chosen.as_str(), selection.confidence),
+            ("low", 0.3)
+        );
+
+        let txt = Resource::new("notes.txt".into(), false);
+        let selection = reg.selection(low.as_ref(), &txt, false);
+        assert_eq!(
+            (selection.reason, selection.confidence),
+            (SelectionReason::Override, 1.0)
+        );
+        assert_eq!(
+            reg.selection(low.as_ref(), &txt, true).reason,
+            SelectionReason::Explicit
+        );
+    }
+
+    #[test]
+    fn fallbacks_follow_confidence_unless_configured() {
+        let mut reg = ProjectionRegistry::new();
+        for (proj_id, conf) in [("best", 0.9), ("middle", 0.5), ("last", 0.1)] {
+            reg.register(Arc::new(DummyProjection { proj_id, conf }));
+        }
+        let ids = |reg: &ProjectionRegistry, path: &str| {
+            reg.fallbacks_for(&Resource::new(path.into(), false), "best")
+                .iter()
+                .map(|p| p.id().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&reg, "a.bin"), ["middle", "last"]);
+
+        reg.register_fallbacks("*", vec!["last".into(), "missing".into()]);
+        reg.register_fallbacks("BIN", vec!["best".into(), "middle".into()]);
+        assert_eq!(ids(&reg, "a.bin"), ["middle"]);
+        assert_eq!(ids(&reg, "a.txt"), ["last"]);
+    }
+
+    #[test]
+    fn override_needs_force_for_zero_confidence() {
+        let mut reg = ProjectionRegistry::new();
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "none",
+            conf: 0.0,
+        }));
+        let txt = Resource::new("notes.txt".into(), false);
+        reg.register_override("txt", "none", 1.0, false);
+        assert!(reg.best_for(&txt).is_none());
+        reg.register_override("txt", "none", 1.0, true);
+        assert_eq!(reg.best_for(&txt).unwrap().id(), "none");
+    }
+
+    #[test]
+    fn category_default_wins_when_it_can_show_the_resource() {
+        let mut reg = ProjectionRegistry::new();
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "meta",
+            conf: 0.3,
+        }));
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "preview",
+            conf: 0.9,
+        }));
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "blind",
+            conf: 0.0,
+        }));
+        reg.register_default(ResourceCategory::Image, "meta");
+        reg.register_default(ResourceCategory::Dir, "blind");
+
+        let png = Resource::new("photo.png".into(), false);
+        assert_eq!(reg.best_for(&png).unwrap().id(), "meta");
+        let meta = reg.get("meta").unwrap().clone();
+        assert_eq!(
+            reg.selection(meta.as_ref(), &png, false).reason,
+            SelectionReason::Default
+        );
+
+        // Other categories, and defaults with no confidence, go by confidence
+        let txt = Resource::new("notes.txt".into(), false);
+        assert_eq!(reg.best_for(&txt).unwrap().id(), "preview");
+        assert_eq!(
+            reg.selection(meta.as_ref(), &txt, false).reason,
+            SelectionReason::HighestConfidence
+        );
+        let dir = Resource::new("src".into(), true);
+        assert_eq!(reg.best_for(&dir).unwrap().id(), "preview");
+    }
+
+    #[test]
+    fn configured_defaults_win_in_each_category() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            [projections.defaults]
+            image = "image.default"
+            text = "text.default"
+            dir = "dir.default"
+            "#,
+        )
+        .unwrap();
+        let mut reg = ProjectionRegistry::new();
+        for (proj_id, conf) in [
+            ("preview", 0.9),
+            ("image.default", 0.2),
+            ("text.default", 0.3),
+            ("dir.default", 0.4),
+        ] {
+            reg.register(Arc::new(DummyProjection { proj_id, conf }));
+        }
+        reg.register_defaults(&config.projections.defaults);
+
+        let file = |path: &str| Resource::new(path.into(), false);
+        let binary = file("blob.bin").with_mime_type(Some("application/zip".into()));
+        for (resource, expected) in [
+            (file("photo.png"), "image.default"),
+            (file("notes.txt"), "text.default"),
+            (Resource::new("src".into(), true), "dir.default"),
+            // No default for binaries, so the most confident still wins
+            (binary, "preview"),
+        ] {
+            let best = reg.best_for(&resource).unwrap();
+            assert_eq!(best.id(), expected, "{}", resource.path);
+        }
+    }
+
+    #[test]
+    fn unknown_default_is_logged_and_ignored() {
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut reg = ProjectionRegistry::new();
+        reg.register(Arc::new(DummyProjection {
+            proj_id: "preview",
+            conf: 0.9,
+        }));
+        let defaults = HashMap::from([(ResourceCategory::Image, "image.missing".to_string())]);
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || reg.register_defaults(&defaults));
+
+        let log = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(log.contains("WARN"), "{log}");
+        assert!(log.contains(
+            r#"default for Image names unavailable projection "image.missing", ignoring"#
+        ));
+        assert!(reg.defaults.is_empty());
+        let png = Resource::new("photo.png".into(), false);
+        let preview = reg.best_for(&png).unwrap();
+        assert_eq!(preview.id(), "preview");
+        assert_eq!(
+            reg.selection(preview.as_ref(), &png, false).reason,
+            SelectionReason::HighestConfidence
+        );
+    }
 }
@@ -0,0 +1,356 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+use crate::workspace::{sort_listing, DirEntry, Result, Workspace, WorkspaceError};
+
+/// The reads projections make, over the real workspace or a tree held in
+/// memory. Paths are relative to the store's root.
+#[async_trait]
+pub trait FileStore: Send + Sync {
+    async fn stat(&self, path: &Path) -> Result<FileStat>;
+
+    async fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// Read at most `len` bytes starting at `offset`.
+    async fn read_at(&self, path: &Path, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let data = self.read(path).await?;
+        let start = data.len().min(offset as usize);
+        let end = data.len().min(start.saturating_add(len as usize));
+        Ok(data[start..end].to_vec())
+    }
+
+    async fn read_to_string(&self, path: &Path) -> Result<String> {
+        String::from_utf8(self.read(path).await?).map_err(|e| {
+            WorkspaceError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
+    }
+
+    /// List a directory, directories first then by case-insensitive name.
+    async fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>>;
+
+    /// The on-disk workspace behind this store, for projections that walk
+    /// trees or hand files to libraries and tools by path.
+    fn local(&self) -> Option<&Workspace> {
+        None
+    }
+
+    /// Absolute path of `path` on disk. Fails with `NotLocal` for stores
+    /// that aren't backed by one.
+    fn local_path(&self, path: &str) -> Result<PathBuf> {
+        match self.local() {
+            Some(workspace) => workspace.resolve(path),
+            None => Err(WorkspaceError::NotLocal(path.to_string())),
+        }
+    }
+}
+
+/// What a projection needs to know about a file without opening it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileStat {
+    /// Token that changes whenever the modification time or size does,
+    /// used for ETags, versioned URLs and cache keys.
+    pub fn version(&self) -> Option<String> {
+        let modified = self.modified?.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(format!("{:x}-{:x}", modified.as_nanos(), self.len))
+    }
+}
+
+impl From<&std::fs::Metadata> for FileStat {
+    fn from(meta: &std::fs::Metadata) -> Self {
+        Self {
+            is_dir: meta.is_dir(),
+            len: meta.len(),
+            modified: meta.modified().ok(),
+        }
+    }
+}
+
+#[async_trait]
+impl FileStore for Workspace {
+    async fn stat(&self, path: &Path) -> Result<FileStat> {
+        Ok(FileStat::from(&Workspace::metadata(self, path).await?))
+    }
+
+    async fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Workspace::read(self, path).await
+    }
+
+    async fn read_at(&self, path: &Path, offset: u64, len: u64) -> Result<Vec<u8>> {
+        Workspace::read_at(self, path, offset, len).await
+    }
+
+    async fn read_to_string(&self, path: &Path) -> Result<String> {
+        Workspace::read_to_string(self, path).await
+    }
+
+    async fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>> {
+        Workspace::read_dir(self, path).await
+    }
+
+    fn local(&self) -> Option<&Workspace> {
+        Some(self)
+    }
+}
+
+/// A fixed tree of files held in memory, for tests and read-only demos.
+/// Directories holding files exist implicitly; empty ones are added with
+/// [`MemoryStore::with_dir`]. Everything shares the store's creation time
+/// as its modification time.
+#[derive(Debug, Clone)]
+pub struct MemoryStore {
+    files: BTreeMap<String, Arc<[u8]>>,
+    dirs: BTreeSet<String>,
+    created: SystemTime,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+            dirs: BTreeSet::new(),
+            created: SystemTime::now(),
+        }
+    }
+
+    /// Add a file at `path`, a `/`-separated path relative to the root.
+    /// Panics if the path is empty or leaves the root.
+    pub fn with_file(mut self, path: &str, contents: impl Into<Vec<u8>>) -> Self {
+        let path = Self::key(Path::new(path)).expect("invalid memory store path");
+        assert!(!path.is_empty(), "a file can't be the root");
+        let mut parent = path.as_str();
+        while let Some((dir, _)) = parent.rsplit_once('/') {
+            self.dirs.insert(dir.to_string());
+            parent = dir;
+        }
+        self.files.insert(path, contents.into().into());
+        self
+    }
+
+    /// Add an empty directory at `path`, and any missing parents.
+    pub fn with_dir(mut self, path: &str) -> Self {
+        let mut path = Self::key(Path::new(path)).expect("invalid memory store path");
+        while !path.is_empty() {
+            let parent = path.rsplit_once('/').map_or("", |(p, _)| p).to_string();
+            self.dirs.insert(std::mem::replace(&mut path, parent));
+        }
+        self
+    }
+
+    /// `path` as a map key: components joined by `/`, the root being empty.
+    fn key(path: &Path) -> Result<String> {
+        let mut parts = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => parts.push(part.to_string_lossy()),
+                Component::CurDir => {}
+                _ => return Err(WorkspaceError::PathTraversal(path.display().to_string())),
+            }
+        }
+        Ok(parts.join("/"))
+    }
+
+    fn is_dir(&self, key: &str) -> bool {
+        key.is_empty() || self.dirs.contains(key)
+    }
+
+    fn not_found(path: &Path) -> WorkspaceError {
+        WorkspaceError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} not found", path.display()),
+        ))
+    }
+
+    fn file(&self, path: &Path) -> Result<&Arc<[u8]>> {
+        let key = Self::key(path)?;
+        match self.files.get(&key) {
+            Some(data) => Ok(data),
+            None if self.is_dir(&key) => {
+                Err(std::io::Error::from(std::io::ErrorKind::IsADirectory).into())
+            }
+            None => Err(Self::not_found(path)),
+        }
+    }
+}
+
+#[async_trait]
+impl FileStore for MemoryStore {
+    async fn stat(&self, path: &Path) -> Result<FileStat> {
+        let key = Self::key(path)?;
+        let (is_dir, len) = match self.files.get(&key) {
+            Some(data) => (false, data.len() as u64),
+            None if self.is_dir(&key) => (true, 0),
+            None => return Err(Self::not_found(path)),
+        };
+        Ok(FileStat {
+            is_dir,
+            len,
+            modified: Some(self.created),
+        })
+    }
+
+    async fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(self.file(path)?.to_vec())
+    }
+
+    async fn read_at(&self, path: &Path, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let data = self.file(path)?;
+        let start = data.len().min(offset as usize);
+        let end = data.len().min(start.saturating_add(len as usize));
+        Ok(data[start..end].to_vec())
+    }
+
+    async fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>> {
+        let key = Self::key(path)?;
+        if !self.is_dir(&key) {
+            if self.files.contains_key(&key) {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotADirectory).into());
+            }
+            return Err(Self::not_found(path));
+        }
+        let prefix = if key.is_empty() {
+            String::new()
+        } else {
+            format!("{key}/")
+        };
+        // Direct children only: the part after the prefix has no `/`
+        let child = |k: &String| {
+            k.strip_prefix(&prefix)
+                .filter(|rest| !rest.contains('/'))
+                .map(str::to_string)
+        };
+        let modified = self
+            .created
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+        let dirs = self
+            .dirs
+            .iter()
+            .filter_map(child)
+            .map(|name| (name, 0, true));
+        let files = self
+            .files
+            .iter()
+            .filter_map(|(k, data)| child(k).map(|name| (name, data.len() as u64, false)));
+        let mut entries: Vec<DirEntry> = dirs
+            .chain(files)
+            .map(|(name, size, is_dir)| DirEntry {
+                name,
+                is_dir,
+                size,
+                modified,
+                mode: None,
+                is_symlink: false,
+                symlink_target: None,
+                outside_root: false,
+            })
+            .collect();
+        sort_listing(&mut entries);
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn demo() -> MemoryStore {
+        MemoryStore::new()
+            .with_file("README.md", "# demo")
+            .with_file("src/main.rs", "fn main() {}\n")
+            .with_file("src/lib/util.rs", "")
+            .with_dir("empty")
+    }
+
+    #[tokio::test]
+    async fn memory_store_reads_and_lists() {
+        let store = demo();
+        let names = |entries: Vec<DirEntry>| -> Vec<(String, bool)> {
+            entries.into_iter().map(|e| (e.name, e.is_dir)).collect()
+        };
+        assert_eq!(
+            names(store.read_dir(Path::new("")).await.unwrap()),
+            [
+                ("empty".to_string(), true),
+                ("src".to_string(), true),
+                ("README.md".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            names(store.read_dir(Path::new("src/")).await.unwrap()),
+            [("lib".to_string(), true), ("main.rs".to_string(), false)]
+        );
+        assert!(store.read_dir(Path::new("empty")).await.unwrap().is_empty());
+
+        let stat = store.stat(Path::new("./src/main.rs")).await.unwrap();
+        assert!(!stat.is_dir);
+        assert_eq!(stat.len, 13);
+        assert!(stat.version().is_some());
+        assert!(store.stat(Path::new("src/lib")).await.unwrap().is_dir);
+        assert_eq!(
+            store.read_to_string(Path::new("README.md")).await.unwrap(),
+            "# demo"
+        );
+        assert_eq!(
+            store.read_at(Path::new("src/main.rs"), 3, 4).await.unwrap(),
+            b"main"
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_store_errors_like_the_filesystem() {
+        use std::io::ErrorKind;
+
+        let store = demo();
+        let kind = |e: WorkspaceError| match e {
+            WorkspaceError::Io(io) => Some(io.kind()),
+            _ => None,
+        };
+        let err = store.read(Path::new("missing.txt")).await.unwrap_err();
+        assert_eq!(kind(err), Some(ErrorKind::NotFound));
+        let err = store.read(Path::new("src")).await.unwrap_err();
+        assert_eq!(kind(err), Some(ErrorKind::IsADirectory));
+        let err = store.read_dir(Path::new("README.md")).await.unwrap_err();
+        assert_eq!(kind(err), Some(ErrorKind::NotADirectory));
+        assert!(matches!(
+            store.read(Path::new("../etc/passwd")).await,
+            Err(WorkspaceError::PathTraversal(_))
+        ));
+        assert!(matches!(
+            store.local_path("README.md"),
+            Err(WorkspaceError::NotLocal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn workspace_stat_matches_content_version() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "abc").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let store: &dyn FileStore = &ws;
+        let stat = store.stat(Path::new("a.txt")).await.unwrap();
+        let meta = std::fs::metadata(dir.path().join("a.txt")).unwrap();
+        assert_eq!(stat.len, 3);
+        assert_eq!(stat.version(), crate::workspace::content_version(&meta));
+        assert_eq!(
+            store.local_path("a.txt").unwrap(),
+            dir.path().canonicalize().unwrap().join("a.txt")
+        );
+    }
+}
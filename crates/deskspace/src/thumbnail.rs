@@ -0,0 +1,172 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{ImageError, ImageReader, Limits};
+
+/// Widest thumbnail generated; larger requests are clamped.
+pub const MAX_WIDTH: u32 = 1024;
+
+/// Largest source width or height decoded. Bigger images are refused
+/// rather than allocated, which also defuses decompression bombs.
+const MAX_SOURCE_DIMENSION: u32 = 16_384;
+
+/// Ceiling on decoder allocations for a single image.
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+const JPEG_QUALITY: u8 = 80;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThumbnailError {
+    #[error("not a supported image")]
+    NotAnImage,
+    #[error("cannot decode image: {0}")]
+    Decode(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Decode the image at `path` and scale it to `width` pixels wide, keeping
+/// its aspect ratio and never upscaling. Returns JPEG bytes.
+pub fn generate(path: &Path, width: u32) -> Result<Vec<u8>, ThumbnailError> {
+    let mut reader = ImageReader::open(path)?.with_guessed_format()?;
+    if reader.format().is_none() {
+        return Err(ThumbnailError::NotAnImage);
+    }
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    reader.limits(limits);
+
+    let image = reader.decode().map_err(|e| match e {
+        ImageError::Unsupported(_) => ThumbnailError::NotAnImage,
+        ImageError::IoError(io) => ThumbnailError::Io(io),
+        e => ThumbnailError::Decode(e.to_string()),
+    })?;
+    if image.width() == 0 || image.height() == 0 {
+        return Err(ThumbnailError::Decode("image is empty".to_string()));
+    }
+    let width = width.clamp(1, image.width());
+    let height = (u64::from(image.height()) * u64::from(width) / u64::from(image.width())).max(1);
+    let thumb = image.thumbnail_exact(width, height as u32).into_rgb8();
+
+    let mut jpeg = Vec::new();
+    thumb
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY))
+        .map_err(|e| ThumbnailError::Decode(e.to_string()))?;
+    Ok(jpeg)
+}
+
+/// Generated thumbnails kept on disk. Entries are keyed by source path,
+/// modification time, size and width, so an edited file never hits a stale
+/// thumbnail; superseded entries are left for the OS to clean up.
+#[derive(Debug, Clone)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache key for a thumbnail of the file at absolute `path`.
+    pub fn key(path: &Path, meta: &std::fs::Metadata, width: u32) -> String {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        meta.len().hash(&mut hasher);
+        meta.modified().ok().hash(&mut hasher);
+        width.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.entry(key)).ok()
+    }
+
+    /// Store a thumbnail. Written to a temporary file first so concurrent
+    /// readers never see a partial entry.
+    pub fn put(&self, key: &str, data: &[u8]) -> std::io::Result<()> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        std::fs::create_dir_all(&self.dir)?;
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp = self
+            .dir
+            .join(format!(".{key}.{}.{n}.tmp", std::process::id()));
+        std::fs::write(&temp, data)?;
+        std::fs::rename(&temp, self.entry(key)).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })
+    }
+
+    fn entry(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.jpg"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, width: u32, height: u32) {
+        image::RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]))
+            .save(path)
+            .unwrap();
+    }
+
+    fn dimensions(jpeg: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory(jpeg).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn scales_to_width_without_upscaling() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wide.png");
+        write_png(&path, 400, 200);
+        assert_eq!(dimensions(&generate(&path, 100).unwrap()), (100, 50));
+        assert_eq!(dimensions(&generate(&path, 1000).unwrap()), (400, 200));
+    }
+
+    #[test]
+    fn rejects_non_images_and_oversized_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, "plain text").unwrap();
+        assert!(matches!(
+            generate(&text, 64),
+            Err(ThumbnailError::NotAnImage)
+        ));
+        let corrupt = dir.path().join("broken.png");
+        std::fs::write(&corrupt, "not really a png").unwrap();
+        assert!(matches!(
+            generate(&corrupt, 64),
+            Err(ThumbnailError::Decode(_))
+        ));
+
+        let huge = dir.path().join("strip.png");
+        write_png(&huge, MAX_SOURCE_DIMENSION + 1, 1);
+        assert!(matches!(
+            generate(&huge, 64),
+            Err(ThumbnailError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("a.png");
+        write_png(&source, 2, 2);
+        let meta = std::fs::metadata(&source).unwrap();
+        let key = ThumbnailCache::key(&source, &meta, 64);
+        assert_ne!(key, ThumbnailCache::key(&source, &meta, 128));
+
+        let cache = ThumbnailCache::new(dir.path().join("thumbs"));
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, b"jpeg").unwrap();
+        assert_eq!(cache.get(&key).as_deref(), Some(&b"jpeg"[..]));
+    }
+}
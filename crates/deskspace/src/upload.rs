@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{BuildHasher, RandomState};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Serialize;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+use crate::workspace::WorkspaceError;
+
+/// How long an upload may go without a chunk before it's discarded.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// How often the server sweeps out idle uploads.
+pub const COLLECT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("no such upload: {0}")]
+    Unknown(String),
+    #[error("upload is at offset {expected}, not {given}")]
+    OffsetMismatch { expected: u64, given: u64 },
+    #[error("upload would exceed its declared size of {0} bytes")]
+    TooLarge(u64),
+    #[error("upload has {received} of its {size} bytes")]
+    Incomplete { received: u64, size: u64 },
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, UploadError>;
+
+/// Where an upload stands, as reported to the client.
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadStatus {
+    pub id: String,
+    /// Destination, relative to the workspace root.
+    pub path: String,
+    /// Bytes received so far; the next chunk must start here.
+    pub offset: u64,
+    /// Total size declared when the upload began, if any.
+    pub size: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Upload {
+    path: String,
+    size: Option<u64>,
+    received: u64,
+    touched: Instant,
+}
+
+/// Uploads in progress, each staged as a `<id>.part` file in a directory
+/// outside the workspace until finished. Chunks must arrive in order, so a
+/// client that loses its connection asks for the offset and carries on
+/// from there. Uploads idle for longer than the timeout are discarded,
+/// along with parts left behind by earlier runs.
+#[derive(Debug)]
+pub struct UploadStaging {
+    dir: PathBuf,
+    idle_timeout: Duration,
+    uploads: Mutex<HashMap<String, Arc<tokio::sync::Mutex<Upload>>>>,
+}
+
+impl UploadStaging {
+    pub fn new(dir: impl Into<PathBuf>, idle_timeout: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            idle_timeout,
+            uploads: Mutex::default(),
+        }
+    }
+
+    fn part(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.part"))
+    }
+
+    fn get(&self, id: &str) -> Result<Arc<tokio::sync::Mutex<Upload>>> {
+        self.uploads
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| UploadError::Unknown(id.to_string()))
+    }
+
+    /// Begin an upload to `path`, optionally declaring its total size.
+    pub async fn begin(&self, path: &str, size: Option<u64>) -> Result<UploadStatus> {
+        self.collect_garbage().await;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let id = new_id();
+        tokio::fs::File::create(self.part(&id)).await?;
+        let upload = Upload {
+            path: path.to_string(),
+            size,
+            received: 0,
+            touched: Instant::now(),
+        };
+        let status = upload.status(&id);
+        self.uploads
+            .lock()
+            .unwrap()
+            .insert(id, Arc::new(tokio::sync::Mutex::new(upload)));
+        Ok(status)
+    }
+
+    pub async fn status(&self, id: &str) -> Result<UploadStatus> {
+        Ok(self.get(id)?.lock().await.status(id))
+    }
+
+    /// Append `data` at `offset`, which must be where the upload left off.
+    /// A failed write is rolled back, so the offset stays trustworthy.
+    pub async fn append(&self, id: &str, offset: u64, data: &[u8]) -> Result<UploadStatus> {
+        let upload = self.get(id)?;
+        let mut upload = upload.lock().await;
+        if offset != upload.received {
+            return Err(UploadError::OffsetMismatch {
+                expected: upload.received,
+                given: offset,
+            });
+        }
+        let end = offset + data.len() as u64;
+        if let Some(size) = upload.size.filter(|&size| end > size) {
+            return Err(UploadError::TooLarge(size));
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(self.part(id))
+            .await?;
+        let written = async {
+            file.seek(SeekFrom::Start(offset)).await?;
+            file.write_all(data).await?;
+            file.sync_data().await
+        }
+        .await;
+        if let Err(e) = written {
+            let _ = file.set_len(offset).await;
+            return Err(e.into());
+        }
+        upload.received = end;
+        upload.touched = Instant::now();
+        Ok(upload.status(id))
+    }
+
+    /// Hand the complete upload to `install`, given its destination and
+    /// staged file, and forget it once that succeeds. If `install` fails
+    /// the upload stays, so finishing can be retried.
+    pub async fn finish<F, Fut>(&self, id: &str, install: F) -> Result<String>
+    where
+        F: FnOnce(String, PathBuf) -> Fut,
+        Fut: Future<Output = std::result::Result<(), WorkspaceError>>,
+    {
+        let upload = self.get(id)?;
+        let mut upload = upload.lock().await;
+        if let Some(size) = upload.size.filter(|&size| upload.received != size) {
+            return Err(UploadError::Incomplete {
+                received: upload.received,
+                size,
+            });
+        }
+        upload.touched = Instant::now();
+        install(upload.path.clone(), self.part(id)).await?;
+        self.uploads.lock().unwrap().remove(id);
+        Ok(upload.path.clone())
+    }
+
+    /// Drop uploads idle past the timeout, and stray parts in the staging
+    /// directory that are at least as old.
+    pub async fn collect_garbage(&self) {
+        let expired: Vec<String> = self
+            .uploads
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, upload)| {
+                // One being written to isn't idle
+                upload
+                    .try_lock()
+                    .is_ok_and(|u| u.touched.elapsed() > self.idle_timeout)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            self.uploads.lock().unwrap().remove(id);
+            tracing::debug!("discarding idle upload {id}");
+            let _ = tokio::fs::remove_file(self.part(id)).await;
+        }
+
+        let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(id) = name.strip_suffix(".part") else {
+                continue;
+            };
+            if self.uploads.lock().unwrap().contains_key(id) {
+                continue;
+            }
+            let stale = entry
+                .metadata()
+                .await
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| {
+                    SystemTime::now()
+                        .duration_since(modified)
+                        .is_ok_and(|age| age > self.idle_timeout)
+                });
+            if stale {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+            }
+        }
+    }
+
+    /// Run `collect_garbage` every `period` in the background, so abandoned
+    /// parts go even when no new upload begins. Stops once the staging is
+    /// dropped.
+    pub fn collect_every(self: &Arc<Self>, period: Duration) {
+        let staging = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(period);
+            loop {
+                tick.tick().await;
+                let Some(staging) = staging.upgrade() else {
+                    break;
+                };
+                staging.collect_garbage().await;
+            }
+        });
+    }
+
+    /// Number of uploads in progress.
+    pub fn len(&self) -> usize {
+        self.uploads.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Upload {
+    fn status(&self, id: &str) -> UploadStatus {
+        UploadStatus {
+            id: id.to_string(),
+            path: self.path.clone(),
+            offset: self.received,
+            size: self.size,
+        }
+    }
+}
+
+/// 128 random-looking bits in hex, safe as a file name.
+fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let high = RandomState::new().hash_one((n, SystemTime::now()));
+    let low = RandomState::new().hash_one((n, std::process::id()));
+    format!("{high:016x}{low:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn chunks_must_be_contiguous() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = UploadStaging::new(dir.path(), DEFAULT_IDLE_TIMEOUT);
+        let id = staging.begin("big.bin", Some(6)).await.unwrap().id;
+
+        assert_eq!(staging.append(&id, 0, b"abc").await.unwrap().offset, 3);
+        // A retried chunk and a gap are both refused
+        for offset in [0, 4] {
+            assert!(matches!(
+                staging.append(&id, offset, b"def").await,
+                Err(UploadError::OffsetMismatch { expected: 3, .. })
+            ));
+        }
+        assert!(matches!(
+            staging.append(&id, 3, b"defg").await,
+            Err(UploadError::TooLarge(6))
+        ));
+        assert!(matches!(
+            staging.finish(&id, |_, _| async { Ok(()) }).await,
+            Err(UploadError::Incomplete {
+                received: 3,
+                size: 6
+            })
+        ));
+
+        staging.append(&id, 3, b"def").await.unwrap();
+        let mut staged = None;
+        let path = staging
+            .finish(&id, |path, part| {
+                staged = Some((path, std::fs::read(part).unwrap()));
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(path, "big.bin");
+        assert_eq!(staged.unwrap(), ("big.bin".to_string(), b"abcdef".to_vec()));
+        assert!(matches!(
+            staging.status(&id).await,
+            Err(UploadError::Unknown(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn idle_uploads_are_collected() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("leftover.part"), "x").unwrap();
+        std::fs::write(dir.path().join("keep.txt"), "x").unwrap();
+        let staging = UploadStaging::new(dir.path(), Duration::ZERO);
+        let id = staging.begin("a.txt", None).await.unwrap().id;
+        assert!(dir.path().join(format!("{id}.part")).exists());
+
+        std::thread::sleep(Duration::from_millis(10));
+        staging.collect_garbage().await;
+        assert!(staging.is_empty());
+        assert!(!dir.path().join(format!("{id}.part")).exists());
+        assert!(!dir.path().join("leftover.part").exists());
+        assert!(dir.path().join("keep.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn idle_uploads_expire_without_a_new_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = Arc::new(UploadStaging::new(dir.path(), Duration::from_millis(20)));
+        let id = staging.begin("a.txt", None).await.unwrap().id;
+        staging.append(&id, 0, b"abc").await.unwrap();
+
+        staging.collect_every(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(staging.is_empty());
+        assert!(!dir.path().join(format!("{id}.part")).exists());
+    }
+}
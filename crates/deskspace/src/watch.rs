@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::workspace::{Result, Workspace};
+
+/// Events buffered per watch before new ones are dropped.
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Renamed,
+    Removed,
+}
+
+/// A filesystem change, with the path relative to the workspace root.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub path: String,
+}
+
+/// A live filesystem watch. The underlying watcher stops when this is dropped.
+pub struct Watch {
+    _watcher: RecommendedWatcher,
+    rx: mpsc::Receiver<ChangeEvent>,
+}
+
+impl Watch {
+    /// Wait for the next change event.
+    pub async fn next(&mut self) -> Option<ChangeEvent> {
+        self.rx.recv().await
+    }
+}
+
+fn change_kind(kind: &EventKind) -> Option<ChangeKind> {
+    match kind {
+        EventKind::Create(_) => Some(ChangeKind::Created),
+        EventKind::Modify(ModifyKind::Name(_)) => Some(ChangeKind::Renamed),
+        EventKind::Modify(_) => Some(ChangeKind::Modified),
+        EventKind::Remove(_) => Some(ChangeKind::Removed),
+        _ => None,
+    }
+}
+
+fn notify_error(e: notify::Error) -> std::io::Error {
+    match e.kind {
+        notify::ErrorKind::Io(io) => io,
+        notify::ErrorKind::PathNotFound => std::io::ErrorKind::NotFound.into(),
+        _ => std::io::Error::other(e),
+    }
+}
+
+/// Watch `path` inside the workspace for changes. Events outside the
+/// workspace root are never reported.
+pub fn watch(workspace: &Workspace, path: &Path, recursive: bool) -> Result<Watch> {
+    let resolved = workspace.resolve(path)?;
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let mut watcher = notify::recommended_watcher({
+        let workspace = workspace.clone();
+        move |res: notify::Result<notify::Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("watch error: {e}");
+                    return;
+                }
+            };
+            let Some(kind) = change_kind(&event.kind) else {
+                return;
+            };
+            for path in event.paths {
+                if !workspace.contains(&path) {
+                    continue;
+                }
+                let change = ChangeEvent {
+                    kind,
+                    path: workspace.relative(&path),
+                };
+                if tx.try_send(change).is_err() {
+                    tracing::debug!("watch: dropping event, receiver full or closed");
+                }
+            }
+        }
+    })
+    .map_err(notify_error)?;
+
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(&resolved, mode).map_err(notify_error)?;
+    // A recursive watch on the root covers the mounts beneath it too
+    if recursive && resolved == workspace.root() {
+        for (_, root) in workspace.mounts() {
+            watcher.watch(root, mode).map_err(notify_error)?;
+        }
+    }
+
+    Ok(Watch {
+        _watcher: watcher,
+        rx,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn watch_reports_relative_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let mut watch = watch(&ws, Path::new("sub"), false).unwrap();
+
+        std::fs::write(dir.path().join("sub/new.txt"), "x").unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), watch.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.path, "sub/new.txt");
+    }
+}
@@ -1,58 +1,173 @@
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::store::FileStat;
+
 #[derive(Error, Debug)]
 pub enum WorkspaceError {
     #[error("path escapes workspace root: {0}")]
     PathTraversal(String),
+    #[error("operation not permitted on the workspace root")]
+    Root,
+    /// The path can't be resolved because part of it doesn't exist.
+    #[error("not found: {0}")]
+    NotFound(String),
+    /// The store has no file on disk for a path that needs one.
+    #[error("not a file on disk: {0}")]
+    NotLocal(String),
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, WorkspaceError>;
 
+/// A directory tree served over the API. Extra directories can be mounted
+/// by name: a path whose first component names a mount is resolved under
+/// that mount's root instead, and the mount shows up as a directory at the
+/// top of the workspace, hiding any real entry with the same name.
 #[derive(Clone)]
 pub struct Workspace {
     root: PathBuf,
+    /// Canonical roots of the named mounts.
+    mounts: Arc<BTreeMap<String, PathBuf>>,
+    /// Shared by clones, so every handle on the workspace sees the same locks.
+    locks: Arc<PathLocks>,
 }
 
 impl Workspace {
     pub fn new(root: impl AsRef<Path>) -> std::io::Result<Self> {
         let root = root.as_ref().canonicalize()?;
-        Ok(Self { root })
+        Ok(Self {
+            root,
+            mounts: Arc::default(),
+            locks: Arc::default(),
+        })
+    }
+
+    /// Mount the directory at `path` as `name`. Names are single path
+    /// components; mounting a name twice replaces the earlier mount. A name
+    /// already taken by an entry in the root fails with `AlreadyExists`,
+    /// since the mount would hide it.
+    pub fn with_mount(mut self, name: &str, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        if !is_mount_name(name) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid mount name: {name:?}"),
+            ));
+        }
+        let shadowed = self.root.join(name);
+        if shadowed.symlink_metadata().is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!(
+                    "mount {name:?} would hide {} in the workspace",
+                    shadowed.display()
+                ),
+            ));
+        }
+        let root = path.as_ref().canonicalize()?;
+        if !root.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotADirectory,
+                format!("mount {name:?} is not a directory: {}", root.display()),
+            ));
+        }
+        Arc::make_mut(&mut self.mounts).insert(name.to_owned(), root);
+        Ok(self)
     }
 
     pub fn root(&self) -> &Path {
         &self.root
     }
 
+    /// Named mounts and their canonical roots, ordered by name.
+    pub fn mounts(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.mounts
+            .iter()
+            .map(|(name, root)| (name.as_str(), root.as_path()))
+    }
+
+    /// The root `relative` lives under, and the rest of the path below it.
+    fn base<'a>(&self, relative: &'a Path) -> (&Path, &'a Path) {
+        let relative = relative.strip_prefix("/").unwrap_or(relative);
+        let mut components = relative.components();
+        if let Some(Component::Normal(first)) = components.next() {
+            if let Some(root) = first.to_str().and_then(|name| self.mounts.get(name)) {
+                return (root, components.as_path());
+            }
+        }
+        (&self.root, relative)
+    }
+
+    /// Absolute path of an entry reported by the workspace itself, such as
+    /// one from `walk`, without resolving links or checking for traversal.
+    pub(crate) fn join(&self, relative: impl AsRef<Path>) -> PathBuf {
+        let (root, rest) = self.base(relative.as_ref());
+        root.join(rest)
+    }
+
+    /// Whether an absolute, canonical path lies under the root or a mount.
+    pub fn contains(&self, absolute: &Path) -> bool {
+        absolute.starts_with(&self.root) || self.mounts.values().any(|r| absolute.starts_with(r))
+    }
+
+    fn is_mount_root(&self, absolute: &Path) -> bool {
+        self.mounts.values().any(|r| r == absolute)
+    }
+
     /// Resolve a relative path to an absolute path within the workspace.
     /// Returns an error if the resolved path escapes the workspace root.
     pub fn resolve(&self, relative: impl AsRef<Path>) -> Result<PathBuf> {
         let relative = relative.as_ref();
 
-        // Join with root — if relative is absolute, strip the leading /
-        let joined = if relative.is_absolute() {
-            self.root
-                .join(relative.strip_prefix("/").unwrap_or(relative))
-        } else {
-            self.root.join(relative)
-        };
+        // Join with the root, or the mount's root when the first component
+        // names one; a leading / is ignored
+        let (root, rest) = self.base(relative);
+        let joined = root.join(rest);
 
-        // Canonicalize if the path exists, otherwise canonicalize the parent
+        // Canonicalize if the path exists, otherwise canonicalize the deepest
+        // existing ancestor and re-append the missing components, so paths
+        // under not-yet-created directories still resolve
+        let canonicalize = |path: &Path| {
+            path.canonicalize().map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    WorkspaceError::NotFound(relative.display().to_string())
+                }
+                _ => e.into(),
+            })
+        };
         let resolved = if joined.exists() {
-            joined.canonicalize()?
+            canonicalize(&joined)?
         } else {
-            let parent = joined
-                .parent()
-                .ok_or_else(|| WorkspaceError::PathTraversal(relative.display().to_string()))?;
-            let file_name = joined
-                .file_name()
-                .ok_or_else(|| WorkspaceError::PathTraversal(relative.display().to_string()))?;
-            parent.canonicalize()?.join(file_name)
+            let mut existing = joined.as_path();
+            let mut missing = Vec::new();
+            while !existing.exists() {
+                // A `..` under a missing directory can't be followed, so
+                // only a lexical escape counts as traversal
+                let Some(file_name) = existing.file_name() else {
+                    return Err(if escapes_lexically(rest) {
+                        WorkspaceError::PathTraversal(relative.display().to_string())
+                    } else {
+                        WorkspaceError::NotFound(relative.display().to_string())
+                    });
+                };
+                missing.push(file_name);
+                existing = existing
+                    .parent()
+                    .ok_or_else(|| WorkspaceError::PathTraversal(relative.display().to_string()))?;
+            }
+            let mut resolved = canonicalize(existing)?;
+            resolved.extend(missing.iter().rev());
+            resolved
         };
 
-        if !resolved.starts_with(&self.root) {
+        if !resolved.starts_with(root) {
             return Err(WorkspaceError::PathTraversal(
                 relative.display().to_string(),
             ));
@@ -61,22 +176,380 @@ impl Workspace {
         Ok(resolved)
     }
 
-    pub async fn read(&self, path: &Path) -> Result<Vec<u8>> {
+    /// Resolve a path without following a symlink in its final component, so
+    /// operations on a link affect the link itself rather than its target.
+    /// Refuses the workspace root and mount roots.
+    fn resolve_entry(&self, relative: &Path) -> Result<PathBuf> {
+        let resolved = self.resolve(relative)?;
+        if resolved == self.root || self.is_mount_root(&resolved) {
+            return Err(WorkspaceError::Root);
+        }
+        let Some(file_name) = relative.file_name() else {
+            return Ok(resolved);
+        };
+        let parent = self.resolve(relative.parent().unwrap_or(Path::new("")))?;
+        Ok(parent.join(file_name))
+    }
+
+    /// Take the write lock on `path`, waiting for writes to it and reads
+    /// that asked to see whole writes. The writes here take it themselves;
+    /// hold it when a write depends on a check made first, and write with
+    /// `write_locked`.
+    pub async fn lock(&self, path: &Path) -> Result<PathGuard> {
         let resolved = self.resolve(path)?;
-        Ok(tokio::fs::read(resolved).await?)
+        Ok(self.locks.clone().acquire(resolved, true).await)
     }
 
-    pub async fn read_to_string(&self, path: &Path) -> Result<String> {
+    /// Take a read lock on `path`, waiting out any write in flight.
+    pub async fn lock_shared(&self, path: &Path) -> Result<PathGuard> {
         let resolved = self.resolve(path)?;
-        Ok(tokio::fs::read_to_string(resolved).await?)
+        Ok(self.locks.clone().acquire(resolved, false).await)
+    }
+
+    /// Read a whole file, after any write to it in flight.
+    pub async fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let guard = self.lock_shared(path).await?;
+        Ok(tokio::fs::read(guard.path()).await?)
+    }
+
+    /// Open a file for streaming reads, returning it with its metadata. The
+    /// file holds a read lock until dropped, so an in-place write waits for
+    /// the stream to finish rather than landing halfway through it.
+    pub async fn open(&self, path: &Path) -> Result<(LockedFile, std::fs::Metadata)> {
+        let guard = self.lock_shared(path).await?;
+        let file = tokio::fs::File::open(guard.path()).await?;
+        let meta = file.metadata().await?;
+        Ok((
+            LockedFile {
+                file,
+                _guard: guard,
+            },
+            meta,
+        ))
+    }
+
+    /// Read at most `len` bytes starting at `offset`, without loading the
+    /// rest of the file, after any write to it in flight.
+    pub async fn read_at(&self, path: &Path, offset: u64, len: u64) -> Result<Vec<u8>> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let guard = self.lock_shared(path).await?;
+        let mut file = tokio::fs::File::open(guard.path()).await?;
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut buf = Vec::new();
+        file.take(len).read_to_end(&mut buf).await?;
+        Ok(buf)
     }
 
+    pub async fn read_to_string(&self, path: &Path) -> Result<String> {
+        let guard = self.lock_shared(path).await?;
+        Ok(tokio::fs::read_to_string(guard.path()).await?)
+    }
+
+    /// Replace a file's contents atomically: the data goes to a temporary
+    /// sibling that is renamed over the target, so readers see either the
+    /// old file or the new one, never a partial write. An existing file
+    /// keeps its permissions.
     pub async fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let guard = self.lock(path).await?;
+        self.write_locked(&guard, contents).await
+    }
+
+    /// `write` to the path `guard` holds the write lock on.
+    pub async fn write_locked(&self, guard: &PathGuard, contents: &[u8]) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        debug_assert!(guard.is_exclusive(), "writing under a shared lock");
+        let resolved = guard.path();
+        let parent = resolved.parent().ok_or(WorkspaceError::Root)?;
+        tokio::fs::create_dir_all(parent).await?;
+
+        let temp = temp_sibling(resolved);
+        let result = async {
+            let mut file = tokio::fs::File::create(&temp).await?;
+            file.write_all(contents).await?;
+            file.sync_all().await?;
+            if let Ok(meta) = tokio::fs::metadata(resolved).await {
+                tokio::fs::set_permissions(&temp, meta.permissions()).await?;
+            }
+            tokio::fs::rename(&temp, resolved).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+        Ok(result?)
+    }
+
+    /// Write `data` at `offset` in the existing file `guard` holds the write
+    /// lock on, leaving the rest of it as it is. Unlike `write` this edits
+    /// the file in place, so it isn't atomic. An offset past the end leaves
+    /// a gap of zeros.
+    pub async fn write_at_locked(&self, guard: &PathGuard, offset: u64, data: &[u8]) -> Result<()> {
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+        debug_assert!(guard.is_exclusive(), "writing under a shared lock");
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(guard.path())
+            .await?;
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        file.write_all(data).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    /// Move `staged`, a file outside the workspace or from `stage`, to
+    /// `path` atomically, as `write` would. When the two are on different
+    /// filesystems it's copied to a temporary sibling first; `staged` is
+    /// gone either way.
+    pub async fn install(&self, path: &Path, staged: &Path) -> Result<()> {
+        let guard = self.lock(path).await?;
+        let resolved = guard.path();
+        let parent = resolved.parent().ok_or(WorkspaceError::Root)?;
+        tokio::fs::create_dir_all(parent).await?;
+        if let Ok(meta) = tokio::fs::metadata(resolved).await {
+            if meta.is_dir() {
+                return Err(std::io::Error::from(std::io::ErrorKind::IsADirectory).into());
+            }
+            tokio::fs::set_permissions(staged, meta.permissions()).await?;
+        }
+        match tokio::fs::rename(staged, resolved).await {
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {}
+            result => return Ok(result?),
+        }
+        let temp = temp_sibling(resolved);
+        let result = async {
+            tokio::fs::copy(staged, &temp).await?;
+            tokio::fs::File::open(&temp).await?.sync_all().await?;
+            tokio::fs::rename(&temp, resolved).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Ok(result?);
+        }
+        let _ = tokio::fs::remove_file(staged).await;
+        Ok(())
+    }
+
+    /// Remove a file or directory. Directories are removed with their contents
+    /// only when `recursive` is set; otherwise a non-empty directory fails
+    /// with `DirectoryNotEmpty`.
+    pub async fn remove(&self, path: &Path, recursive: bool) -> Result<()> {
+        let target = self.resolve_entry(path)?;
+        let _guard = self.locks.clone().acquire(target.clone(), true).await;
+        let meta = tokio::fs::symlink_metadata(&target).await?;
+        if !meta.is_dir() {
+            tokio::fs::remove_file(&target).await?;
+        } else if recursive {
+            tokio::fs::remove_dir_all(&target).await?;
+        } else {
+            tokio::fs::remove_dir(&target).await?;
+        }
+        Ok(())
+    }
+
+    /// Move `from` to `to`, creating the destination's parent directories.
+    /// Fails with `AlreadyExists` if the destination exists and `overwrite`
+    /// is not set.
+    pub async fn rename(&self, from: &Path, to: &Path, overwrite: bool) -> Result<()> {
+        let source = self.resolve_entry(from)?;
+        let dest = self.resolve_entry(to)?;
+        // Always in path order, so two opposite renames can't deadlock
+        let (first, second) = if source <= dest {
+            (&source, &dest)
+        } else {
+            (&dest, &source)
+        };
+        let _first = self.locks.clone().acquire(first.clone(), true).await;
+        let _second = match first == second {
+            true => None,
+            false => Some(self.locks.clone().acquire(second.clone(), true).await),
+        };
+        tokio::fs::symlink_metadata(&source).await?;
+        if !overwrite && tokio::fs::symlink_metadata(&dest).await.is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("destination exists: {}", to.display()),
+            )
+            .into());
+        }
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(tokio::fs::rename(source, dest).await?)
+    }
+
+    /// Create a directory and any missing parents. Fails with `AlreadyExists`
+    /// if the path exists as a file.
+    pub async fn create_dir(&self, path: &Path) -> Result<()> {
+        let resolved = self.resolve(path)?;
+        if tokio::fs::metadata(&resolved)
+            .await
+            .is_ok_and(|meta| !meta.is_dir())
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("file exists: {}", path.display()),
+            )
+            .into());
+        }
+        Ok(tokio::fs::create_dir_all(resolved).await?)
+    }
+
+    /// Create a temporary sibling of `path` to stream a new version of it
+    /// into, creating parent directories as needed. Readers keep seeing the
+    /// old file until the staged one is passed to `install`; remove it
+    /// instead to abandon the write.
+    pub async fn stage(&self, path: &Path) -> Result<(PathBuf, tokio::fs::File)> {
         let resolved = self.resolve(path)?;
-        if let Some(parent) = resolved.parent() {
+        let parent = resolved.parent().ok_or(WorkspaceError::Root)?;
+        tokio::fs::create_dir_all(parent).await?;
+        let staged = temp_sibling(&resolved);
+        let file = tokio::fs::File::create(&staged).await?;
+        Ok((staged, file))
+    }
+
+    /// Copy a file or directory tree to `to`, creating the destination's
+    /// parent directories. Fails with `AlreadyExists` if the destination
+    /// exists and `overwrite` is not set. Symlinks inside a copied tree are
+    /// skipped.
+    pub async fn copy(&self, from: &Path, to: &Path, overwrite: bool) -> Result<CopySummary> {
+        let source = self.resolve(from)?;
+        let dest = self.resolve_entry(to)?;
+        let _guard = self.locks.clone().acquire(dest.clone(), true).await;
+        let meta = tokio::fs::metadata(&source).await?;
+        if !overwrite && tokio::fs::symlink_metadata(&dest).await.is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("destination exists: {}", to.display()),
+            )
+            .into());
+        }
+        if meta.is_dir() && dest.starts_with(&source) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "cannot copy a directory into itself",
+            )
+            .into());
+        }
+        // Only once the copy is known to go ahead, so a refused one leaves
+        // nothing behind
+        if let Some(parent) = dest.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
-        Ok(tokio::fs::write(resolved, contents).await?)
+        if !meta.is_dir() {
+            tokio::fs::copy(&source, &dest).await?;
+            return Ok(CopySummary {
+                files: 1,
+                truncated: false,
+            });
+        }
+
+        let workspace = self.clone();
+        let from = from.to_path_buf();
+        tokio::task::spawn_blocking(move || workspace.copy_tree(&from, &source, &dest))
+            .await
+            .map_err(std::io::Error::other)?
+    }
+
+    fn copy_tree(&self, from: &Path, source: &Path, dest: &Path) -> Result<CopySummary> {
+        std::fs::create_dir_all(dest)?;
+        let mut files = 0;
+        let mut error = None;
+        let summary = self.walk(from, &WalkOptions::default(), |entry| {
+            let abs = self.join(&entry.path);
+            let Ok(rel) = abs.strip_prefix(source) else {
+                return ControlFlow::Continue(());
+            };
+            let target = dest.join(rel);
+            let result = if entry.is_symlink {
+                tracing::debug!("copy: skipping symlink {}", entry.path);
+                Ok(())
+            } else if entry.is_dir {
+                std::fs::create_dir_all(&target)
+            } else {
+                std::fs::copy(&abs, &target).map(|_| files += 1)
+            };
+            match result {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(e) => {
+                    error = Some(e);
+                    ControlFlow::Break(())
+                }
+            }
+        })?;
+        if let Some(e) = error {
+            return Err(e.into());
+        }
+        Ok(CopySummary {
+            files,
+            truncated: summary.truncated,
+        })
+    }
+
+    /// Set the permission bits of `path` to `mode`, e.g. `0o644`, and with
+    /// `recursive` of everything below it, children before their directory
+    /// so a mode without `x` can't cut the walk off. Symlinks are skipped
+    /// rather than followed, which also keeps the walk from looping.
+    /// Failures below `path` are counted, not fatal. Only the rwx bits may
+    /// be set; setuid, setgid and sticky fail with `InvalidInput`. Unix
+    /// only; elsewhere this fails with `Unsupported`.
+    pub async fn set_permissions(
+        &self,
+        path: &Path,
+        mode: u32,
+        recursive: bool,
+    ) -> Result<ChmodSummary> {
+        if mode & !0o777 != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("mode {mode:#o} sets more than the permission bits"),
+            )
+            .into());
+        }
+        let target = self.resolve_entry(path)?;
+        let meta = tokio::fs::symlink_metadata(&target).await?;
+        if meta.is_symlink() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "can't change the mode of a symlink",
+            )
+            .into());
+        }
+        let workspace = self.clone();
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut summary = ChmodSummary::default();
+            if recursive && meta.is_dir() {
+                let mut below = Vec::new();
+                let walked = workspace.walk(&path, &WalkOptions::default(), |entry| {
+                    if !entry.is_symlink {
+                        below.push(entry.path.clone());
+                    }
+                    ControlFlow::Continue(())
+                })?;
+                summary.truncated = walked.truncated;
+                for entry in below.iter().rev() {
+                    let changed = workspace
+                        .resolve(entry)
+                        .and_then(|abs| Ok(chmod(&abs, mode)?));
+                    match changed {
+                        Ok(()) => summary.changed += 1,
+                        Err(e) => {
+                            tracing::debug!("chmod: skipping {entry}: {e}");
+                            summary.failed += 1;
+                        }
+                    }
+                }
+            }
+            chmod(&target, mode)?;
+            summary.changed += 1;
+            Ok(summary)
+        })
+        .await
+        .map_err(std::io::Error::other)?
     }
 
     pub async fn metadata(&self, path: &Path) -> Result<std::fs::Metadata> {
@@ -84,25 +557,412 @@ impl Workspace {
         Ok(tokio::fs::metadata(resolved).await?)
     }
 
+    /// Metadata of `path` without following a symlink in its final
+    /// component. Only the parent has to resolve inside the workspace, so
+    /// links pointing outside it can still be inspected.
+    pub async fn symlink_metadata(&self, path: &Path) -> Result<std::fs::Metadata> {
+        let (_, rest) = self.base(path);
+        let resolved = match (rest.file_name(), path.parent()) {
+            (Some(name), Some(parent)) => self.resolve(parent)?.join(name),
+            _ => self.resolve(path)?,
+        };
+        Ok(tokio::fs::symlink_metadata(resolved).await?)
+    }
+
+    /// List a directory, directories first then by case-insensitive name.
+    /// Entry metadata is fetched concurrently, which matters on network
+    /// filesystems where each stat is a round trip.
     pub async fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>> {
+        use futures::{StreamExt, TryStreamExt};
+
         let resolved = self.resolve(path)?;
         let mut rd = tokio::fs::read_dir(&resolved).await?;
-        let mut entries = Vec::new();
+        let mut raw = Vec::new();
         while let Some(entry) = rd.next_entry().await? {
-            let meta = entry.metadata().await?;
-            entries.push(DirEntry {
-                name: entry.file_name().to_string_lossy().into_owned(),
-                is_dir: meta.is_dir(),
-                size: meta.len(),
-            });
+            raw.push(entry);
         }
-        entries.sort_by(|a, b| {
-            b.is_dir
-                .cmp(&a.is_dir)
-                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-        });
+        let mut entries: Vec<DirEntry> = futures::stream::iter(raw)
+            .map(|entry| self.dir_entry(entry))
+            .buffer_unordered(READ_DIR_CONCURRENCY)
+            .try_collect()
+            .await?;
+        if resolved == self.root && !self.mounts.is_empty() {
+            entries.retain(|e| !self.mounts.contains_key(&e.name));
+            for (name, root) in self.mounts() {
+                entries.push(mount_entry(name, root).await?);
+            }
+        }
+        sort_listing(&mut entries);
         Ok(entries)
     }
+
+    /// List a directory as its entries are read, in no particular order,
+    /// keeping memory bounded however large it is. Entries that vanish or
+    /// can't be inspected mid-listing are skipped; a failure to read the
+    /// directory itself ends the stream with an error.
+    pub async fn read_dir_stream(
+        &self,
+        path: &Path,
+    ) -> Result<impl futures::Stream<Item = Result<DirEntry>> + Send + 'static> {
+        use futures::StreamExt;
+
+        let resolved = self.resolve(path)?;
+        let rd = tokio::fs::read_dir(&resolved).await?;
+        let mounts = if resolved == self.root {
+            self.mounts.clone()
+        } else {
+            Arc::default()
+        };
+        let raw = futures::stream::unfold(Some(rd), |rd| async move {
+            let mut rd = rd?;
+            match rd.next_entry().await {
+                Ok(Some(entry)) => Some((Ok(entry), Some(rd))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        let workspace = self.clone();
+        let shadowed = mounts.clone();
+        let entries = raw
+            .filter(move |entry| {
+                let hidden = entry.as_ref().is_ok_and(|e| {
+                    e.file_name()
+                        .to_str()
+                        .is_some_and(|n| shadowed.contains_key(n))
+                });
+                std::future::ready(!hidden)
+            })
+            .map(move |entry| {
+                let workspace = workspace.clone();
+                async move {
+                    match workspace.dir_entry(entry?).await {
+                        Ok(entry) => Ok(Some(entry)),
+                        Err(e) => {
+                            tracing::debug!("read_dir: skipping entry: {e}");
+                            Ok(None)
+                        }
+                    }
+                }
+            })
+            .buffer_unordered(READ_DIR_CONCURRENCY)
+            .filter_map(|entry: std::io::Result<Option<DirEntry>>| {
+                std::future::ready(entry.map_err(WorkspaceError::from).transpose())
+            });
+        let mounts = futures::stream::iter(
+            mounts
+                .iter()
+                .map(|(name, root)| (name.clone(), root.clone()))
+                .collect::<Vec<_>>(),
+        )
+        .filter_map(|(name, root)| async move { mount_entry(&name, &root).await.ok().map(Ok) });
+        Ok(mounts.chain(entries))
+    }
+
+    async fn dir_entry(&self, entry: tokio::fs::DirEntry) -> Result<DirEntry> {
+        // Doesn't traverse symlinks, so links are seen as links
+        let link_meta = entry.metadata().await?;
+        let is_symlink = link_meta.file_type().is_symlink();
+        let (meta, symlink_target, outside_root) = if is_symlink {
+            match tokio::fs::canonicalize(entry.path()).await {
+                Ok(target) if self.contains(&target) => {
+                    let meta = tokio::fs::metadata(&target).await?;
+                    (meta, Some(self.relative(&target)), false)
+                }
+                // Flagged but not followed, and the host path isn't leaked
+                Ok(_) => (link_meta, None, true),
+                // Dangling link or a loop
+                Err(_) => (link_meta, None, false),
+            }
+        } else {
+            (link_meta, None, false)
+        };
+        Ok(DirEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: meta.is_dir(),
+            size: meta.len(),
+            modified: modified_secs(&meta),
+            mode: file_mode(&meta),
+            is_symlink,
+            symlink_target,
+            outside_root,
+        })
+    }
+
+    /// Express an absolute path inside the workspace relative to the root,
+    /// using `/` separators. Paths under a mount are prefixed with its name;
+    /// when roots nest, the innermost one wins.
+    pub fn relative(&self, absolute: &Path) -> String {
+        let (prefix, root) = std::iter::once(("", self.root.as_path()))
+            .chain(self.mounts())
+            .filter(|(_, root)| absolute.starts_with(root))
+            .max_by_key(|(_, root)| root.components().count())
+            .unwrap_or(("", &self.root));
+        let rest = absolute
+            .strip_prefix(root)
+            .unwrap_or(absolute)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy());
+        (!prefix.is_empty())
+            .then(|| prefix.into())
+            .into_iter()
+            .chain(rest)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Find entries under the root whose relative path contains `query`,
+    /// case-insensitively. Stops after `limit` hits; symlinks resolving
+    /// outside the root are skipped.
+    pub fn search(&self, query: &str, limit: usize) -> Result<SearchResults> {
+        let needle = query.to_lowercase();
+        let mut hits = Vec::new();
+        let mut more = false;
+        let summary = self.walk(Path::new(""), &WalkOptions::default(), |entry| {
+            if !entry.path.to_lowercase().contains(&needle) {
+                return ControlFlow::Continue(());
+            }
+            if entry.is_symlink && self.resolve(&entry.path).is_err() {
+                return ControlFlow::Continue(());
+            }
+            if hits.len() >= limit {
+                more = true;
+                return ControlFlow::Break(());
+            }
+            hits.push(SearchHit {
+                path: entry.path.clone(),
+                is_dir: entry.is_dir,
+                size: entry.size,
+            });
+            ControlFlow::Continue(())
+        })?;
+        Ok(SearchResults {
+            hits,
+            truncated: summary.truncated || more,
+        })
+    }
+
+    /// Find lines containing `query`, case-insensitively, in the text files
+    /// under the root. Files over `options.max_file_size` are skipped, as are
+    /// files with a NUL byte near the start, which are taken to be binary.
+    /// Symlinks aren't followed. Files are searched by a fixed pool of
+    /// threads; once `options.limit` hits are found the rest are abandoned.
+    /// The caller blocks until the pool is done.
+    pub fn grep(&self, query: &str, options: &GrepOptions) -> Result<GrepResults> {
+        let mut files = Vec::new();
+        let mut skipped = 0;
+        let summary = self.walk(Path::new(""), &WalkOptions::default(), |entry| {
+            if entry.is_dir || entry.is_symlink {
+                return ControlFlow::Continue(());
+            }
+            if options
+                .glob
+                .as_ref()
+                .is_some_and(|g| !g.is_match(&entry.path))
+            {
+                return ControlFlow::Continue(());
+            }
+            if entry.size > options.max_file_size {
+                skipped += 1;
+            } else {
+                files.push(entry.path.clone());
+            }
+            ControlFlow::Continue(())
+        })?;
+
+        let needle = query.to_lowercase();
+        let searched = files.len();
+        let queue = std::sync::Mutex::new(files.into_iter());
+        let hits = std::sync::Mutex::new(Vec::new());
+        let binary = std::sync::atomic::AtomicUsize::new(0);
+        let full = AtomicBool::new(false);
+        let workers = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(GREP_WORKERS);
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    if full.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let Some(path) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+                    let data = match std::fs::read(self.join(&path)) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            tracing::debug!("grep: skipping {path}: {e}");
+                            continue;
+                        }
+                    };
+                    if data[..data.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+                        binary.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    let text = String::from_utf8_lossy(&data);
+                    for (i, line) in text.lines().enumerate() {
+                        if !line.to_lowercase().contains(&needle) {
+                            continue;
+                        }
+                        let mut hits = hits.lock().unwrap();
+                        if hits.len() >= options.limit {
+                            full.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                        hits.push(GrepHit {
+                            path: path.clone(),
+                            line_number: i + 1,
+                            line: line.chars().take(GREP_MAX_LINE_CHARS).collect(),
+                        });
+                    }
+                });
+            }
+        });
+
+        let mut hits = hits.into_inner().unwrap();
+        hits.sort_by(|a, b| (&a.path, a.line_number).cmp(&(&b.path, b.line_number)));
+        Ok(GrepResults {
+            hits,
+            searched,
+            skipped: skipped + binary.into_inner(),
+            truncated: summary.truncated || full.into_inner(),
+        })
+    }
+
+    /// Sum the sizes of the files under `path`, or report a single file's
+    /// size. Symlinks are neither followed nor counted. The walk stops early,
+    /// returning what it has, once `cancelled` is set, which a caller on
+    /// another thread can use to give up on a large tree.
+    pub fn directory_size(
+        &self,
+        path: &Path,
+        options: &WalkOptions,
+        cancelled: &AtomicBool,
+    ) -> Result<DirectorySize> {
+        let mut size = DirectorySize {
+            path: self.relative(&self.resolve(path)?),
+            total_bytes: 0,
+            file_count: 0,
+            dir_count: 0,
+            truncated: false,
+        };
+        let summary = self.walk(path, options, |entry| {
+            if cancelled.load(Ordering::Relaxed) {
+                return ControlFlow::Break(());
+            }
+            if entry.is_dir {
+                size.dir_count += 1;
+            } else if !entry.is_symlink {
+                size.file_count += 1;
+                size.total_bytes += entry.size;
+            }
+            ControlFlow::Continue(())
+        })?;
+        size.truncated = summary.truncated;
+        Ok(size)
+    }
+
+    /// Recursively walk the tree under `path`, calling `visit` for every entry
+    /// (the starting directory itself is not visited). Symlinks are reported
+    /// and, unless `options.follow_symlinks` is set, never followed. Followed
+    /// links are only descended into when they resolve inside the root and
+    /// to a directory not already walked, so the walk can't escape or loop.
+    ///
+    /// Every directory is read synchronously on the calling thread, so keep
+    /// large walks off the async runtime.
+    pub fn walk(
+        &self,
+        path: &Path,
+        options: &WalkOptions,
+        mut visit: impl FnMut(&WalkEntry) -> ControlFlow<()>,
+    ) -> Result<WalkSummary> {
+        let start = self.resolve(path)?;
+        let mut summary = WalkSummary::default();
+
+        let meta = std::fs::symlink_metadata(&start)?;
+        if !meta.is_dir() {
+            summary.visited = 1;
+            let _ = visit(&WalkEntry::new(self.relative(&start), &meta, 0));
+            return Ok(summary);
+        }
+
+        // Canonical paths of directories already queued, for cycle detection
+        // when links are followed
+        let mut seen = HashSet::from([start.clone()]);
+        let mut stack = vec![(start, 0usize)];
+        while let Some((dir, depth)) = stack.pop() {
+            let rd = match std::fs::read_dir(&dir) {
+                Ok(rd) => rd,
+                Err(e) => {
+                    tracing::debug!("walk: skipping {}: {e}", dir.display());
+                    continue;
+                }
+            };
+            // Mounts are listed at the top level in place of any real entry
+            // they hide
+            let at_root = dir == self.root;
+            let entries = rd
+                .map(|entry| entry.and_then(|e| Ok((e.path(), e.metadata()?))))
+                .filter(|entry| {
+                    !at_root
+                        || entry.as_ref().map_or(true, |(abs, _)| {
+                            !self.is_mount_root(abs)
+                                && !abs
+                                    .file_name()
+                                    .and_then(|n| n.to_str())
+                                    .is_some_and(|n| self.mounts.contains_key(n))
+                        })
+                })
+                .chain(
+                    self.mounts
+                        .values()
+                        .filter(|_| at_root)
+                        .map(|root| std::fs::metadata(root).map(|meta| (root.clone(), meta))),
+                );
+            for entry in entries {
+                let (abs, meta) = match entry {
+                    Ok(pair) => pair,
+                    Err(e) => {
+                        tracing::debug!("walk: skipping entry in {}: {e}", dir.display());
+                        continue;
+                    }
+                };
+                if summary.visited >= options.max_entries {
+                    summary.truncated = true;
+                    return Ok(summary);
+                }
+                summary.visited += 1;
+
+                let walk_entry = WalkEntry::new(self.relative(&abs), &meta, depth + 1);
+                if visit(&walk_entry).is_break() {
+                    return Ok(summary);
+                }
+                let descend = if !options.follow_symlinks {
+                    walk_entry.is_dir
+                } else if walk_entry.is_dir || walk_entry.is_symlink {
+                    // Below a followed link even real directories can repeat
+                    match std::fs::canonicalize(&abs) {
+                        Ok(target) => {
+                            self.contains(&target) && target.is_dir() && seen.insert(target)
+                        }
+                        Err(e) => {
+                            tracing::debug!("walk: not following {}: {e}", abs.display());
+                            false
+                        }
+                    }
+                } else {
+                    false
+                };
+                if descend {
+                    if depth + 1 < options.max_depth {
+                        stack.push((abs, depth + 1));
+                    } else {
+                        summary.truncated = true;
+                    }
+                }
+            }
+        }
+        Ok(summary)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -110,6 +970,387 @@ pub struct DirEntry {
     pub name: String,
     pub is_dir: bool,
     pub size: u64,
+    /// Last modification time in unix seconds, if the platform reports it.
+    pub modified: Option<u64>,
+    /// Unix permission bits; `None` on platforms without them.
+    pub mode: Option<u32>,
+    /// The entry is a symlink. `is_dir`, `size` and friends describe its
+    /// target when that resolves inside the root, otherwise the link itself.
+    pub is_symlink: bool,
+    /// Resolved target relative to the root, for links that stay inside it.
+    pub symlink_target: Option<String>,
+    /// The link resolves outside the root and was not followed.
+    pub outside_root: bool,
+}
+
+/// Order a listing directories first, then by case-insensitive name. Names
+/// equal but for case fall back to byte order, so the result doesn't depend
+/// on the order entries were read in.
+pub(crate) fn sort_listing(entries: &mut [DirEntry]) {
+    entries.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Listing entry for the mount `name`, described by its root directory.
+async fn mount_entry(name: &str, root: &Path) -> Result<DirEntry> {
+    let meta = tokio::fs::metadata(root).await?;
+    Ok(DirEntry {
+        name: name.to_owned(),
+        is_dir: true,
+        size: meta.len(),
+        modified: modified_secs(&meta),
+        mode: file_mode(&meta),
+        is_symlink: false,
+        symlink_target: None,
+        outside_root: false,
+    })
+}
+
+/// Whether `name` can name a mount: a single normal path component.
+fn is_mount_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(n)) if n == name)
+        && components.next().is_none()
+}
+
+/// Whether `..` components take `path` above its starting directory,
+/// judged from the components alone.
+fn escapes_lexically(path: &Path) -> bool {
+    use std::path::Component;
+
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return true,
+            },
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    false
+}
+
+/// Async locks keyed by resolved path, so writes to one file run one at a
+/// time. An entry is dropped when the last guard on it is.
+#[derive(Debug, Default)]
+struct PathLocks(std::sync::Mutex<HashMap<PathBuf, Arc<tokio::sync::RwLock<()>>>>);
+
+impl PathLocks {
+    async fn acquire(self: Arc<Self>, path: PathBuf, exclusive: bool) -> PathGuard {
+        let lock = self
+            .0
+            .lock()
+            .unwrap()
+            .entry(path.clone())
+            .or_default()
+            .clone();
+        let held: Box<dyn Send + Sync> = if exclusive {
+            Box::new(lock.write_owned().await)
+        } else {
+            Box::new(lock.read_owned().await)
+        };
+        PathGuard {
+            path,
+            held: Some(held),
+            exclusive,
+            locks: self,
+        }
+    }
+
+    /// Forget `path`'s lock when nothing but the map refers to it.
+    fn prune(&self, path: &Path) {
+        let mut locks = self.0.lock().unwrap();
+        if locks.get(path).is_some_and(|l| Arc::strong_count(l) == 1) {
+            locks.remove(path);
+        }
+    }
+}
+
+/// A lock on a path from [`Workspace::lock`] or [`Workspace::lock_shared`],
+/// released when dropped.
+pub struct PathGuard {
+    path: PathBuf,
+    /// The lock guard; taken on drop, so the lock is free before pruning.
+    held: Option<Box<dyn Send + Sync>>,
+    exclusive: bool,
+    locks: Arc<PathLocks>,
+}
+
+impl PathGuard {
+    /// The resolved path locked.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+}
+
+impl Drop for PathGuard {
+    fn drop(&mut self) {
+        self.held = None;
+        self.locks.prune(&self.path);
+    }
+}
+
+/// A file from [`Workspace::open`], read-locked until dropped.
+pub struct LockedFile {
+    file: tokio::fs::File,
+    _guard: PathGuard,
+}
+
+impl tokio::io::AsyncRead for LockedFile {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.get_mut().file).poll_read(cx, buf)
+    }
+}
+
+impl tokio::io::AsyncSeek for LockedFile {
+    fn start_seek(
+        self: std::pin::Pin<&mut Self>,
+        position: std::io::SeekFrom,
+    ) -> std::io::Result<()> {
+        std::pin::Pin::new(&mut self.get_mut().file).start_seek(position)
+    }
+
+    fn poll_complete(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<u64>> {
+        std::pin::Pin::new(&mut self.get_mut().file).poll_complete(cx)
+    }
+}
+
+/// Unique hidden name next to `path` for staging an atomic write.
+fn temp_sibling(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{name}.{}.{n}.tmp", std::process::id()))
+}
+
+/// Token that changes whenever the file's modification time or size does,
+/// used for ETags and versioned URLs.
+pub fn content_version(meta: &std::fs::Metadata) -> Option<String> {
+    FileStat::from(meta).version()
+}
+
+/// Modification time of `meta` in seconds since the unix epoch.
+pub fn modified_secs(meta: &std::fs::Metadata) -> Option<u64> {
+    meta.modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+/// Creation (birth) time of `meta` in unix seconds, where the platform and
+/// filesystem record it.
+pub fn created_secs(meta: &std::fs::Metadata) -> Option<u64> {
+    meta.created()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+/// Last status change time (`ctime`) of `meta` in unix seconds.
+#[cfg(unix)]
+pub fn changed_secs(meta: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    u64::try_from(meta.ctime()).ok()
+}
+
+#[cfg(not(unix))]
+pub fn changed_secs(_meta: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Unix permission bits of `meta`.
+#[cfg(unix)]
+pub fn file_mode(meta: &std::fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(meta.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+pub fn file_mode(_meta: &std::fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn chmod(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn chmod(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "permission bits are only supported on unix",
+    ))
+}
+
+/// Metadata fetches in flight at once in [`Workspace::read_dir`].
+const READ_DIR_CONCURRENCY: usize = 32;
+
+/// Threads searching file contents concurrently in [`Workspace::grep`].
+const GREP_WORKERS: usize = 8;
+
+/// Leading bytes scanned for a NUL when deciding whether a file is binary.
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+/// Matched lines longer than this are cut short, so minified files don't
+/// bloat the response.
+const GREP_MAX_LINE_CHARS: usize = 500;
+
+/// Bounds and filters for [`Workspace::grep`].
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    /// Only search files whose workspace-relative path matches.
+    pub glob: Option<globset::GlobMatcher>,
+    /// Maximum number of matching lines returned.
+    pub limit: usize,
+    /// Files larger than this many bytes are skipped.
+    pub max_file_size: u64,
+}
+
+/// Bounds for [`Workspace::walk`] so a giant tree can't hang a request.
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Maximum depth below the starting directory to descend into.
+    pub max_depth: usize,
+    /// Maximum number of entries visited before the walk stops.
+    pub max_entries: usize,
+    /// Descend into symlinked directories inside the root.
+    pub follow_symlinks: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 32,
+            max_entries: 200_000,
+            follow_symlinks: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    /// Path relative to the workspace root.
+    pub path: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub size: u64,
+    /// Last modification time in unix seconds, if the platform reports it.
+    pub modified: Option<u64>,
+    /// Unix permission bits; `None` on platforms without them.
+    pub mode: Option<u32>,
+    /// Depth below the starting directory (direct children are 1).
+    pub depth: usize,
+}
+
+impl WalkEntry {
+    fn new(path: String, meta: &std::fs::Metadata, depth: usize) -> Self {
+        Self {
+            path,
+            is_dir: meta.is_dir(),
+            is_symlink: meta.file_type().is_symlink(),
+            size: if meta.is_dir() { 0 } else { meta.len() },
+            modified: modified_secs(meta),
+            mode: file_mode(meta),
+            depth,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CopySummary {
+    /// Number of files copied.
+    pub files: u64,
+    /// Whether the walk bounds stopped the copy before the whole tree.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChmodSummary {
+    /// Number of files and directories whose mode was set.
+    pub changed: u64,
+    /// Entries below the target that couldn't be changed.
+    pub failed: u64,
+    /// Whether the walk bounds stopped before the whole tree.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectorySize {
+    /// Path relative to the workspace root.
+    pub path: String,
+    pub total_bytes: u64,
+    pub file_count: u64,
+    /// Directories below `path`, not counting `path` itself.
+    pub dir_count: u64,
+    /// Whether the walk bounds stopped the count before the whole tree.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    /// Path relative to the workspace root.
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Whether more matches may exist beyond the limit or walk bounds.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepHit {
+    /// Path relative to the workspace root.
+    pub path: String,
+    /// One-based line number.
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepResults {
+    pub hits: Vec<GrepHit>,
+    /// Number of files considered for searching.
+    pub searched: usize,
+    /// Files passed over for being too large or binary.
+    pub skipped: usize,
+    /// Whether more matches may exist beyond the limit or walk bounds.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkSummary {
+    /// Number of entries visited.
+    pub visited: usize,
+    /// Whether the depth or entry cap cut the walk short.
+    pub truncated: bool,
 }
 
 #[cfg(test)]
@@ -155,5 +1396,704 @@ mod tests {
         let entries = ws.read_dir(Path::new("")).await.unwrap();
         assert!(entries[0].is_dir);
         assert_eq!(entries[0].name, "a_dir");
+        assert!(entries[1].modified.is_some());
+        #[cfg(unix)]
+        assert!(entries[1].mode.is_some());
+    }
+
+    #[tokio::test]
+    async fn read_dir_order_is_stable() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut expected_files = Vec::new();
+        for i in 0..200 {
+            let name = format!("f{:03}", 199 - i);
+            fs::write(dir.path().join(&name), "").unwrap();
+            expected_files.push(name);
+        }
+        fs::create_dir(dir.path().join("Sub")).unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("README"), "").unwrap();
+        fs::write(dir.path().join("readme"), "").unwrap();
+        expected_files.sort();
+        let mut expected = vec!["Sub".to_string(), "sub".to_string()];
+        expected.extend(expected_files);
+        expected.extend(["README".to_string(), "readme".to_string()]);
+
+        let ws = Workspace::new(dir.path()).unwrap();
+        for _ in 0..5 {
+            let names: Vec<_> = ws
+                .read_dir(Path::new(""))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect();
+            assert_eq!(names, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn read_at_reads_bounded_slice() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("data.bin"), b"0123456789").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let slice = ws.read_at(Path::new("data.bin"), 2, 4).await.unwrap();
+        assert_eq!(slice, b"2345");
+        let tail = ws.read_at(Path::new("data.bin"), 8, 100).await.unwrap();
+        assert_eq!(tail, b"89");
+    }
+
+    #[tokio::test]
+    async fn create_dir_conflicts_with_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), "").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        ws.create_dir(Path::new("a/b")).await.unwrap();
+        assert!(dir.path().join("a/b").is_dir());
+        // Existing directories are fine
+        ws.create_dir(Path::new("a")).await.unwrap();
+        let err = ws.create_dir(Path::new("file")).await.unwrap_err();
+        assert!(
+            matches!(err, WorkspaceError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn copy_tree_recursively() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::write(dir.path().join("src/a.txt"), "a").unwrap();
+        fs::write(dir.path().join("src/nested/b.txt"), "b").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let summary = ws
+            .copy(Path::new("src"), Path::new("out/copy"), false)
+            .await
+            .unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out/copy/nested/b.txt")).unwrap(),
+            "b"
+        );
+
+        let err = ws
+            .copy(Path::new("src"), Path::new("out/copy"), false)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, WorkspaceError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert!(ws
+            .copy(Path::new("src"), Path::new("src/inner/deeper"), false)
+            .await
+            .is_err());
+        assert!(!dir.path().join("src/inner").exists());
+        assert!(ws
+            .copy(Path::new("src/a.txt"), Path::new("fresh/dir/a.txt"), false)
+            .await
+            .is_ok());
+        fs::write(dir.path().join("taken.txt"), "t").unwrap();
+        assert!(ws
+            .copy(Path::new("src/a.txt"), Path::new("taken.txt"), false)
+            .await
+            .is_err());
+        assert!(ws
+            .copy(Path::new("missing"), Path::new("never/made/x"), false)
+            .await
+            .is_err());
+        assert!(!dir.path().join("never").exists());
+    }
+
+    #[tokio::test]
+    async fn remove_file_and_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/file.txt"), "data").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let err = ws.remove(Path::new("sub"), false).await.unwrap_err();
+        assert!(
+            matches!(err, WorkspaceError::Io(e) if e.kind() == std::io::ErrorKind::DirectoryNotEmpty)
+        );
+
+        ws.remove(Path::new("sub/file.txt"), false).await.unwrap();
+        assert!(!dir.path().join("sub/file.txt").exists());
+        ws.remove(Path::new("sub"), false).await.unwrap();
+        assert!(!dir.path().join("sub").exists());
+    }
+
+    #[tokio::test]
+    async fn remove_rejects_root_and_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        assert!(matches!(
+            ws.remove(Path::new(""), true).await,
+            Err(WorkspaceError::Root)
+        ));
+        assert!(matches!(
+            ws.remove(Path::new("../../etc"), true).await,
+            Err(WorkspaceError::PathTraversal(_))
+        ));
+    }
+
+    #[test]
+    fn resolve_missing_ancestors() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let resolved = ws.resolve("new/deep/file.txt").unwrap();
+        assert_eq!(resolved, ws.root().join("new/deep/file.txt"));
+        assert!(matches!(
+            ws.resolve("new/../../escape"),
+            Err(WorkspaceError::PathTraversal(_))
+        ));
+        // Stepping back out of a missing directory stays inside the root,
+        // but can't be followed on disk
+        assert!(matches!(
+            ws.resolve("new/../file.txt"),
+            Err(WorkspaceError::NotFound(_))
+        ));
+        assert!(matches!(
+            ws.resolve("a/b/../../.."),
+            Err(WorkspaceError::PathTraversal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn write_replaces_atomically_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let leftovers = |sub: &str| -> Vec<String> {
+            fs::read_dir(dir.path().join(sub))
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+                .filter(|n| n.ends_with(".tmp"))
+                .collect()
+        };
+
+        ws.write(Path::new("a/doc.txt"), b"first").await.unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let perms = fs::Permissions::from_mode(0o600);
+            fs::set_permissions(dir.path().join("a/doc.txt"), perms).unwrap();
+        }
+        ws.write(Path::new("a/doc.txt"), b"second").await.unwrap();
+        assert_eq!(fs::read(dir.path().join("a/doc.txt")).unwrap(), b"second");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let meta = fs::metadata(dir.path().join("a/doc.txt")).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        }
+        assert!(leftovers("a").is_empty());
+
+        // Renaming over a directory fails; the staged file must not linger
+        fs::create_dir(dir.path().join("a/sub")).unwrap();
+        assert!(ws.write(Path::new("a/sub"), b"x").await.is_err());
+        assert!(leftovers("a").is_empty());
+    }
+
+    #[tokio::test]
+    async fn install_moves_staged_files_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = tempfile::tempdir().unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let staged = staging.path().join("x.part");
+
+        fs::write(&staged, "uploaded").unwrap();
+        ws.install(Path::new("in/new.bin"), &staged).await.unwrap();
+        assert_eq!(
+            fs::read(dir.path().join("in/new.bin")).unwrap(),
+            b"uploaded"
+        );
+        assert!(!staged.exists());
+
+        fs::write(&staged, "x").unwrap();
+        assert!(ws.install(Path::new("in"), &staged).await.is_err());
+        assert!(ws.install(Path::new("../out"), &staged).await.is_err());
+        assert!(staged.exists());
+    }
+
+    #[tokio::test]
+    async fn readers_never_see_partial_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let big = vec![b'a'; 4 * 1024 * 1024];
+        let small = vec![b'b'; 16];
+        ws.write(Path::new("f"), &big).await.unwrap();
+
+        let writer = {
+            let ws = ws.clone();
+            let (big, small) = (big.clone(), small.clone());
+            tokio::spawn(async move {
+                for i in 0..20 {
+                    let contents = if i % 2 == 0 { &small } else { &big };
+                    ws.write(Path::new("f"), contents).await.unwrap();
+                }
+            })
+        };
+        while !writer.is_finished() {
+            let seen = fs::read(dir.path().join("f")).unwrap();
+            assert!(
+                seen == big || seen == small,
+                "partial read of {} bytes",
+                seen.len()
+            );
+            tokio::task::yield_now().await;
+        }
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn writes_to_a_path_wait_for_its_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        fs::write(dir.path().join("a.txt"), "old").unwrap();
+
+        let guard = ws.lock(Path::new("a.txt")).await.unwrap();
+        let write = tokio::spawn({
+            let ws = ws.clone();
+            async move { ws.write(Path::new("a.txt"), b"new").await }
+        });
+        let read = tokio::spawn({
+            let ws = ws.clone();
+            async move { ws.read(Path::new("./a.txt")).await }
+        });
+        // Other paths aren't held up
+        ws.write(Path::new("b.txt"), b"b").await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!write.is_finished() && !read.is_finished());
+        assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"old");
+
+        ws.write_locked(&guard, b"mine").await.unwrap();
+        drop(guard);
+        write.await.unwrap().unwrap();
+        let read = read.await.unwrap().unwrap();
+        assert!(read == b"new" || read == b"mine");
+        assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"new");
+        assert!(ws.locks.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn in_place_writes_wait_for_streaming_reads() {
+        use tokio::io::AsyncReadExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        fs::write(dir.path().join("a.bin"), "hello world").unwrap();
+
+        // A patch waits for an open stream to be dropped
+        let (mut file, _) = ws.open(Path::new("a.bin")).await.unwrap();
+        let patch = tokio::spawn({
+            let ws = ws.clone();
+            async move {
+                let guard = ws.lock(Path::new("a.bin")).await?;
+                ws.write_at_locked(&guard, 0, b"HELLO").await
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!patch.is_finished());
+        let mut streamed = String::new();
+        file.read_to_string(&mut streamed).await.unwrap();
+        assert_eq!(streamed, "hello world");
+        drop(file);
+        patch.await.unwrap().unwrap();
+
+        // And a ranged read waits for a patch in flight
+        let guard = ws.lock(Path::new("a.bin")).await.unwrap();
+        let read = tokio::spawn({
+            let ws = ws.clone();
+            async move { ws.read_at(Path::new("a.bin"), 6, 5).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!read.is_finished());
+        ws.write_at_locked(&guard, 6, b"WORLD").await.unwrap();
+        drop(guard);
+        assert_eq!(read.await.unwrap().unwrap(), b"WORLD");
+    }
+
+    #[tokio::test]
+    async fn rename_creates_parent_and_respects_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        fs::write(dir.path().join("b.txt"), "b").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let err = ws
+            .rename(Path::new("a.txt"), Path::new("b.txt"), false)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, WorkspaceError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+
+        ws.rename(Path::new("a.txt"), Path::new("moved/a.txt"), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("moved/a.txt")).unwrap(),
+            "a"
+        );
+
+        ws.rename(Path::new("moved/a.txt"), Path::new("b.txt"), true)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("b.txt")).unwrap(), "a");
+    }
+
+    #[test]
+    fn walk_visits_nested_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/b/c.txt"), "abc").unwrap();
+        fs::write(dir.path().join("top.txt"), "t").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let mut paths = Vec::new();
+        let summary = ws
+            .walk(Path::new(""), &WalkOptions::default(), |e| {
+                paths.push(e.path.clone());
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        paths.sort();
+        assert_eq!(paths, vec!["a", "a/b", "a/b/c.txt", "top.txt"]);
+        assert_eq!(summary.visited, 4);
+        assert!(!summary.truncated);
+    }
+
+    #[test]
+    fn search_matches_case_insensitively() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/Parser")).unwrap();
+        fs::write(dir.path().join("src/Parser/mod.rs"), "").unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let results = ws.search("parser", 10).unwrap();
+        let mut paths: Vec<_> = results.hits.iter().map(|h| h.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["src/Parser", "src/Parser/mod.rs"]);
+        assert!(!results.truncated);
+
+        let results = ws.search("parser", 1).unwrap();
+        assert_eq!(results.hits.len(), 1);
+        assert!(results.truncated);
+    }
+
+    #[test]
+    fn grep_finds_lines_and_skips_binary_and_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "fn main() {}\n// TODO: Fix\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.md"), "todo list\n").unwrap();
+        fs::write(dir.path().join("blob.bin"), b"todo\0\x01").unwrap();
+        fs::write(dir.path().join("big.txt"), "todo ".repeat(100)).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let options = GrepOptions {
+            glob: None,
+            limit: 10,
+            max_file_size: 100,
+        };
+
+        let results = ws.grep("todo", &options).unwrap();
+        let hits: Vec<_> = results
+            .hits
+            .iter()
+            .map(|h| (h.path.as_str(), h.line_number, h.line.as_str()))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                ("notes.md", 1, "todo list"),
+                ("src/lib.rs", 2, "// TODO: Fix")
+            ]
+        );
+        assert_eq!((results.searched, results.skipped), (3, 2));
+        assert!(!results.truncated);
+
+        let glob = globset::Glob::new("*.rs").unwrap().compile_matcher();
+        let results = ws
+            .grep(
+                "todo",
+                &GrepOptions {
+                    glob: Some(glob),
+                    ..options.clone()
+                },
+            )
+            .unwrap();
+        assert_eq!(results.hits.len(), 1);
+
+        let results = ws
+            .grep(
+                "todo",
+                &GrepOptions {
+                    limit: 1,
+                    ..options
+                },
+            )
+            .unwrap();
+        assert_eq!(results.hits.len(), 1);
+        assert!(results.truncated);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn set_permissions_walks_below_and_skips_links() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("d/sub")).unwrap();
+        fs::write(dir.path().join("d/sub/a.sh"), "").unwrap();
+        fs::write(dir.path().join("outside.txt"), "").unwrap();
+        symlink(dir.path().join("outside.txt"), dir.path().join("d/link")).unwrap();
+        symlink(dir.path().join("d"), dir.path().join("d/sub/loop")).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let mode = |p: &str| {
+            fs::metadata(dir.path().join(p))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        };
+
+        let summary = ws
+            .set_permissions(Path::new("d/sub/a.sh"), 0o755, false)
+            .await
+            .unwrap();
+        assert_eq!((summary.changed, summary.failed), (1, 0));
+        assert_eq!(mode("d/sub/a.sh"), 0o755);
+
+        // Without `x` on directories the walk still reaches everything,
+        // and neither link is followed
+        let before = mode("outside.txt");
+        let summary = ws
+            .set_permissions(Path::new("d"), 0o600, true)
+            .await
+            .unwrap();
+        assert_eq!((summary.changed, summary.failed), (3, 0));
+        fs::set_permissions(dir.path().join("d"), fs::Permissions::from_mode(0o700)).unwrap();
+        fs::set_permissions(dir.path().join("d/sub"), fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(mode("d/sub/a.sh"), 0o600);
+        assert_eq!(mode("outside.txt"), before);
+
+        assert!(ws
+            .set_permissions(Path::new("d/link"), 0o600, false)
+            .await
+            .is_err());
+        assert!(matches!(
+            ws.set_permissions(Path::new(""), 0o600, true).await,
+            Err(WorkspaceError::Root)
+        ));
+
+        // Setuid, setgid and sticky bits are refused before anything changes
+        for special in [0o4755, 0o2755, 0o1777] {
+            let err = ws
+                .set_permissions(Path::new("d/sub/a.sh"), special, false)
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, WorkspaceError::Io(e) if e.kind() == std::io::ErrorKind::InvalidInput),
+                "{special:o}"
+            );
+        }
+        assert_eq!(mode("d/sub/a.sh"), 0o600);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn read_dir_reports_symlinks() {
+        use std::os::unix::fs::symlink;
+        let outside = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("real")).unwrap();
+        symlink(dir.path().join("real"), dir.path().join("inside")).unwrap();
+        symlink(outside.path(), dir.path().join("escape")).unwrap();
+        symlink(dir.path().join("missing"), dir.path().join("dangling")).unwrap();
+        symlink(dir.path().join("self"), dir.path().join("self")).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let entries = ws.read_dir(Path::new("")).await.unwrap();
+        let get = |name: &str| entries.iter().find(|e| e.name == name).unwrap();
+        let inside = get("inside");
+        assert!(inside.is_symlink && inside.is_dir && !inside.outside_root);
+        assert_eq!(inside.symlink_target.as_deref(), Some("real"));
+        let escape = get("escape");
+        assert!(escape.is_symlink && escape.outside_root && !escape.is_dir);
+        assert_eq!(escape.symlink_target, None);
+        for name in ["dangling", "self"] {
+            let e = get(name);
+            assert!(e.is_symlink && !e.outside_root && e.symlink_target.is_none());
+        }
+        assert!(!get("real").is_symlink);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlink_metadata_describes_the_link() {
+        use std::os::unix::fs::symlink;
+        let outside = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("real")).unwrap();
+        symlink(outside.path(), dir.path().join("real/escape")).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let meta = ws.symlink_metadata(Path::new("real/escape")).await.unwrap();
+        assert!(meta.file_type().is_symlink());
+        assert!(ws.metadata(Path::new("real/escape")).await.is_err());
+        assert!(ws.symlink_metadata(Path::new("")).await.unwrap().is_dir());
+        assert!(matches!(
+            ws.symlink_metadata(Path::new("../etc")).await,
+            Err(WorkspaceError::PathTraversal(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_follows_links_without_looping() {
+        use std::os::unix::fs::symlink;
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret.txt"), "").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/b/file.txt"), "").unwrap();
+        symlink(dir.path().join("a"), dir.path().join("a/b/up")).unwrap();
+        symlink(dir.path().join("a/b"), dir.path().join("alias")).unwrap();
+        symlink(outside.path(), dir.path().join("escape")).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+
+        let options = WalkOptions {
+            follow_symlinks: true,
+            ..WalkOptions::default()
+        };
+        let mut paths = Vec::new();
+        let summary = ws
+            .walk(Path::new(""), &options, |e| {
+                paths.push(e.path.clone());
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert!(!summary.truncated);
+        assert!(!paths.iter().any(|p| p.contains("secret")));
+        // a/b is walked once, either directly or via the alias
+        let files = paths.iter().filter(|p| p.ends_with("file.txt")).count();
+        assert_eq!(files, 1, "{paths:?}");
+    }
+
+    #[test]
+    fn directory_size_sums_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/one.txt"), "12345").unwrap();
+        fs::write(dir.path().join("a/b/two.txt"), "123").unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let running = AtomicBool::new(false);
+
+        let size = ws
+            .directory_size(Path::new("a"), &WalkOptions::default(), &running)
+            .unwrap();
+        assert_eq!(size.path, "a");
+        assert_eq!(
+            (size.total_bytes, size.file_count, size.dir_count),
+            (8, 2, 1)
+        );
+        assert!(!size.truncated);
+
+        let file = ws
+            .directory_size(Path::new("a/one.txt"), &WalkOptions::default(), &running)
+            .unwrap();
+        assert_eq!((file.total_bytes, file.file_count), (5, 1));
+
+        let cancelled = AtomicBool::new(true);
+        let partial = ws
+            .directory_size(Path::new("a"), &WalkOptions::default(), &cancelled)
+            .unwrap();
+        assert_eq!(partial.file_count, 0);
+    }
+
+    #[test]
+    fn walk_respects_depth_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        let ws = Workspace::new(dir.path()).unwrap();
+        let options = WalkOptions {
+            max_depth: 2,
+            ..WalkOptions::default()
+        };
+        let mut max_depth = 0;
+        let summary = ws
+            .walk(Path::new(""), &options, |e| {
+                max_depth = max_depth.max(e.depth);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(max_depth, 2);
+        assert!(summary.truncated);
+    }
+
+    #[tokio::test]
+    async fn mounts_resolve_under_their_own_roots() {
+        let main = tempfile::tempdir().unwrap();
+        let docs = tempfile::tempdir().unwrap();
+        fs::write(main.path().join("a.txt"), "main").unwrap();
+        fs::write(docs.path().join("guide.md"), "guide").unwrap();
+        let ws = Workspace::new(main.path())
+            .unwrap()
+            .with_mount("docs", docs.path())
+            .unwrap();
+        assert!(Workspace::new(main.path())
+            .unwrap()
+            .with_mount("a/b", docs.path())
+            .is_err());
+        // A mount may not hide an entry already in the root
+        let err = Workspace::new(main.path())
+            .unwrap()
+            .with_mount("a.txt", docs.path())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+        assert_eq!(ws.read(Path::new("docs/guide.md")).await.unwrap(), b"guide");
+        assert_eq!(
+            ws.read(Path::new("/docs/guide.md")).await.unwrap(),
+            b"guide"
+        );
+        let abs = ws.resolve("docs/guide.md").unwrap();
+        assert_eq!(ws.relative(&abs), "docs/guide.md");
+        assert!(matches!(
+            ws.resolve("docs/../a.txt"),
+            Err(WorkspaceError::PathTraversal(_))
+        ));
+        assert!(matches!(
+            ws.remove(Path::new("docs"), true).await,
+            Err(WorkspaceError::Root)
+        ));
+
+        // One made under its name afterwards stays behind the mount
+        fs::create_dir(main.path().join("docs")).unwrap();
+        let names: Vec<_> = ws
+            .read_dir(Path::new(""))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.is_dir))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("docs".to_string(), true), ("a.txt".to_string(), false)]
+        );
+
+        let mut paths = Vec::new();
+        ws.walk(Path::new(""), &WalkOptions::default(), |e| {
+            paths.push(e.path.clone());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        paths.sort();
+        assert_eq!(paths, vec!["a.txt", "docs", "docs/guide.md"]);
     }
 }
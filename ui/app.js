@@ -7,6 +7,7 @@ import hljs from 'highlight.js/lib/common';
 
 let tileTree = { type: 'pane', id: genId(), path: '', projection: null };
 let panes = new Map(); // id -> DOM element
+let watchers = new Map(); // pane id -> EventSource for live reload
 
 function genId() {
   return Math.random().toString(36).slice(2, 9);
@@ -14,17 +15,31 @@ function genId() {
 
 // ─── API ─────────────────────────────────────────────────
 
-async function fetchResource(path, projection) {
+async function fetchResource(path, projection, options = {}) {
   const base = path ? `/api/files/${encodeURI(path)}` : '/api/files/';
-  const url = projection ? `${base}?projection=${encodeURIComponent(projection)}` : base;
-  const res = await fetch(url);
-  if (!res.ok) {
+  const params = new URLSearchParams(options);
+  if (projection) params.set('projection', projection);
+  const query = params.toString();
+  const url = query ? `${base}?${query}` : base;
+  let res = await fetch(url);
+  if (res.status === 401 && promptForToken()) res = await fetch(url);
+  // A timed-out projection still comes with the other projections to try
+  if (!res.ok && res.status !== 504) {
     const body = await res.json().catch(() => ({ error: res.statusText }));
     throw new Error(body.error || res.statusText);
   }
   return res.json();
 }
 
+// The server was started with an access token. Keep it in a cookie so
+// images, downloads and watch streams carry it too.
+function promptForToken() {
+  const token = window.prompt('This workspace requires an access token:');
+  if (!token) return false;
+  document.cookie = `deskspace_token=${token.trim()}; path=/; SameSite=Strict`;
+  return true;
+}
+
 // ─── Tile tree operations ────────────────────────────────
 
 function findNode(tree, id) {
@@ -84,6 +99,7 @@ function closePane(paneId) {
   // Replace parent with sibling
   Object.keys(parent).forEach(k => delete parent[k]);
   Object.assign(parent, sibling);
+  unwatchPane(paneId);
 
   render();
 }
@@ -93,6 +109,7 @@ function navigatePane(paneId, path) {
   if (!node || node.type !== 'pane') return;
   node.path = path;
   node.projection = null;
+  node.reveal = null;
   renderPane(paneId);
 }
 
@@ -207,47 +224,95 @@ async function renderPane(paneId) {
   projBar.innerHTML = '';
 
   try {
-    const data = await fetchResource(node.path, node.projection);
+    const data = await fetchResource(node.path, node.projection, {
+      hidden: String(!!node.showHidden),
+      respect_gitignore: String(!node.showHidden),
+      git_status: 'true',
+      ...(node.reveal ? { reveal: node.reveal } : {}),
+    });
 
     // Breadcrumb
-    buildBreadcrumb(breadcrumb, paneId, data.path);
+    buildBreadcrumb(breadcrumb, paneId, data.breadcrumbs);
 
     // Projection buttons
     for (const proj of data.projections) {
       const btn = document.createElement('button');
       btn.className = 'pane-proj-btn';
       if (proj.id === data.active_projection) btn.classList.add('active');
-      btn.textContent = proj.name;
+      const icon = PROJECTION_ICONS[proj.icon];
+      btn.textContent = icon ? `${icon} ${proj.name}` : proj.name;
+      const title = [];
+      if (proj.description) title.push(proj.description);
+      if (proj.id === data.active_projection && data.selection) {
+        title.push(`Showing as ${proj.name}: ${SELECTION_REASONS[data.selection.reason] || data.selection.reason}, ` +
+          `confidence ${data.selection.confidence.toFixed(2)}`);
+        if (data.selection.reason === 'fallback') {
+          title.push(`Tried first: ${data.attempted.slice(0, -1).join(', ')}`);
+        }
+      }
+      if (title.length) btn.title = title.join('\n');
       btn.onclick = () => switchProjection(paneId, proj.id);
       projBar.appendChild(btn);
     }
+    const download = document.createElement('a');
+    download.className = 'pane-proj-btn';
+    if (data.is_dir) {
+      download.href = `/api/zip/${encodeURI(data.path)}`;
+      download.title = 'Download as zip';
+    } else {
+      download.href = `/api/files/raw/${encodeURI(data.path)}?download=1`;
+      download.title = 'Download';
+    }
+    download.textContent = '↓';
+    projBar.appendChild(download);
 
     // Content
     content.innerHTML = '';
     renderOutput(content, data.output, paneId);
+
+    watchPane(paneId, data.path);
   } catch (err) {
     content.innerHTML = `<div class="error-msg">${escHtml(err.message)}</div>`;
   }
 }
 
-function buildBreadcrumb(container, paneId, path) {
-  const parts = path ? path.split('/') : [];
+// Re-render a pane when its file or directory changes on disk
+function watchPane(paneId, path) {
+  const existing = watchers.get(paneId);
+  if (existing && existing.path === path) return;
+  if (existing) existing.source.close();
+
+  const source = new EventSource(`/api/watch?path=${encodeURIComponent(path)}`);
+  let pending = null;
+  source.onmessage = () => {
+    clearTimeout(pending);
+    pending = setTimeout(() => {
+      if (panes.has(paneId)) renderPane(paneId);
+      else unwatchPane(paneId);
+    }, 200);
+  };
+  watchers.set(paneId, { path, source });
+}
+
+function unwatchPane(paneId) {
+  const existing = watchers.get(paneId);
+  if (existing) existing.source.close();
+  watchers.delete(paneId);
+}
 
+function buildBreadcrumb(container, paneId, crumbs) {
   const rootLink = document.createElement('a');
   rootLink.href = '#';
   rootLink.textContent = '~';
   rootLink.onclick = (e) => { e.preventDefault(); navigatePane(paneId, ''); };
   container.appendChild(rootLink);
 
-  let accumulated = '';
-  for (const part of parts) {
+  for (const crumb of crumbs) {
     container.appendChild(document.createTextNode(' / '));
-    accumulated += (accumulated ? '/' : '') + part;
     const link = document.createElement('a');
     link.href = '#';
-    link.textContent = part;
-    const navPath = accumulated;
-    link.onclick = (e) => { e.preventDefault(); navigatePane(paneId, navPath); };
+    link.textContent = crumb.name;
+    link.onclick = (e) => { e.preventDefault(); navigatePane(paneId, crumb.path); };
     container.appendChild(link);
   }
 }
@@ -257,14 +322,53 @@ function buildBreadcrumb(container, paneId, path) {
 function renderOutput(container, output, paneId) {
   switch (output.type) {
     case 'DirectoryList': return renderDirList(container, output, paneId);
+    case 'Tree': return renderTree(container, output, paneId);
     case 'Text': return renderText(container, output);
+    case 'HighlightedText': return renderHighlighted(container, output);
     case 'Markdown': return renderMarkdown(container, output);
+    case 'Html': return renderHtml(container, output);
     case 'Image': return renderImage(container, output);
+    case 'Svg': return renderSvg(container, output);
+    case 'Table': return renderTable(container, output);
+    case 'Hex': return renderHex(container, output);
+    case 'Json': return renderJson(container, output);
+    case 'Config': return renderConfig(container, output);
+    case 'Env': return renderEnv(container, output, paneId);
+    case 'ArchiveList': return renderArchiveList(container, output);
+    case 'Pdf': return renderPdf(container, output);
+    case 'Error': return renderProjectionError(container, output);
+    case 'Audio': return renderAudio(container, output);
+    case 'Video': return renderVideo(container, output);
+    case 'Notebook': return renderNotebook(container, output);
+    case 'Font': return renderFont(container, output);
+    case 'Diff': return renderDiff(container, output);
+    case 'TooLarge': return renderTooLarge(container, output);
+    case 'Timeout': return renderTimeout(container, output);
     default:
       container.innerHTML = `<div class="error-msg">Unknown output type: ${escHtml(output.type)}</div>`;
   }
 }
 
+// Icons for the identifiers projections report; unknown ones show no icon.
+const PROJECTION_ICONS = {
+  archive: '🗜', audio: '🎵', binary: '🔢', code: '🖋', database: '🗄',
+  diff: '±', folder: '📁', font: '🔤', globe: '🌐', image: '🖼', json: '{}',
+  lock: '🔒', markdown: '📝', notebook: '📓', pdf: '📕', table: '▦', terminal: '⌨',
+  text: '📄', tree: '🌲', video: '🎬',
+};
+
+// Wording for FileResponse.selection.reason.
+const SELECTION_REASONS = {
+  explicit: 'chosen explicitly',
+  highest_confidence: 'highest confidence',
+  override: 'configured override',
+  default: 'configured default for this kind of file',
+  fallback: 'fallback after the better matches failed',
+};
+
+// Raster formats /api/thumb can decode.
+const THUMBNAIL_EXTENSIONS = new Set(['png', 'jpg', 'jpeg', 'gif', 'webp', 'bmp']);
+
 function renderDirList(container, output, paneId) {
   const grid = document.createElement('div');
   grid.className = 'dir-list';
@@ -285,6 +389,18 @@ function renderDirList(container, output, paneId) {
     const icon = document.createElement('span');
     icon.className = 'dir-entry-icon';
     icon.textContent = entry.is_dir ? '📁' : fileIcon(entry.extension);
+    if (!entry.is_dir && THUMBNAIL_EXTENSIONS.has(entry.extension)) {
+      const entryPath = basePath ? `${basePath}/${entry.name}` : entry.name;
+      const thumb = document.createElement('img');
+      thumb.className = 'dir-entry-thumb';
+      thumb.loading = 'lazy';
+      thumb.alt = '';
+      thumb.src = `/api/thumb/${encodeURI(entryPath)}?w=64`;
+      // Drawn over the emoji, which stays if the image can't be decoded
+      thumb.onload = () => icon.replaceChildren(thumb);
+      thumb.onerror = () => thumb.remove();
+      icon.appendChild(thumb);
+    }
     item.appendChild(icon);
 
     const name = document.createElement('span');
@@ -292,6 +408,33 @@ function renderDirList(container, output, paneId) {
     name.textContent = entry.name;
     item.appendChild(name);
 
+    if (entry.git_status != null) {
+      const status = document.createElement('span');
+      status.className = 'dir-entry-git';
+      status.dataset.status = entry.git_status;
+      status.textContent = entry.git_status;
+      item.appendChild(status);
+    }
+
+    if (entry.is_symlink) {
+      const badge = document.createElement('span');
+      badge.className = 'dir-entry-badge';
+      badge.textContent = entry.outside_root ? 'external link' : 'link';
+      if (entry.symlink_target != null) badge.title = `→ ${entry.symlink_target}`;
+      item.appendChild(badge);
+    }
+
+    if (entry.mode != null && (entry.mode & 0o222) === 0) {
+      const badge = document.createElement('span');
+      badge.className = 'dir-entry-badge';
+      badge.textContent = 'read-only';
+      item.appendChild(badge);
+    }
+
+    if (entry.modified != null) {
+      item.title = `Modified ${new Date(entry.modified * 1000).toLocaleString()}`;
+    }
+
     if (!entry.is_dir) {
       const size = document.createElement('span');
       size.className = 'dir-entry-size';
@@ -303,6 +446,42 @@ function renderDirList(container, output, paneId) {
   }
 
   container.appendChild(grid);
+
+  if (output.readme) {
+    const readme = document.createElement('section');
+    readme.className = 'dir-readme';
+    const title = document.createElement('div');
+    title.className = 'dir-readme-title';
+    title.textContent = output.readme.path.split('/').pop();
+    readme.appendChild(title);
+    if (output.readme.html != null) {
+      // Rendered and sanitized server-side
+      const body = document.createElement('div');
+      body.className = 'markdown-body';
+      body.innerHTML = output.readme.html;
+      readme.appendChild(body);
+    } else {
+      renderText(readme, { content: output.readme.raw, language: null });
+    }
+    container.appendChild(readme);
+  }
+
+  const omitted = [];
+  if (output.hidden > 0) omitted.push(`${output.hidden} hidden`);
+  if (output.ignored > 0) omitted.push(`${output.ignored} ignored`);
+  if (omitted.length > 0 && currentNode) {
+    const count = output.hidden + output.ignored;
+    const note = document.createElement('a');
+    note.className = 'table-view-note';
+    note.href = '#';
+    note.textContent = `${omitted.join(', ')} item${count === 1 ? '' : 's'} — show all`;
+    note.onclick = (e) => {
+      e.preventDefault();
+      currentNode.showHidden = true;
+      renderPane(paneId);
+    };
+    container.appendChild(note);
+  }
 }
 
 function renderText(container, output) {
@@ -328,6 +507,29 @@ function renderText(container, output) {
   pre.appendChild(code);
   wrapper.appendChild(pre);
   container.appendChild(wrapper);
+
+  const notes = [];
+  if (output.range) {
+    notes.push(`Lines ${output.range.start + 1}–${output.range.end} of ${output.line_count}`);
+  }
+  if (output.encoding && output.encoding !== 'UTF-8') notes.push(`Decoded from ${output.encoding}`);
+  if (output.lossy) notes.push('Some bytes could not be decoded');
+  if (output.line_ending === 'crlf') notes.push('CRLF line endings');
+  if (output.line_ending === 'mixed') notes.push('Mixed line endings');
+  if (notes.length > 0) {
+    const note = document.createElement('div');
+    note.className = 'table-view-note';
+    note.textContent = notes.join(' · ');
+    container.appendChild(note);
+  }
+}
+
+function renderHighlighted(container, output) {
+  // Server-rendered and escaped by the highlighter
+  const wrapper = document.createElement('div');
+  wrapper.className = 'text-content';
+  wrapper.innerHTML = output.html;
+  container.appendChild(wrapper);
 }
 
 function renderMarkdown(container, output) {
@@ -395,6 +597,508 @@ function renderImage(container, output) {
   img.alt = 'Preview';
   wrapper.appendChild(img);
   container.appendChild(wrapper);
+
+  const meta = output.metadata;
+  if (!meta) return;
+  const facts = [];
+  const camera = [meta.make, meta.model].filter(Boolean).join(' ');
+  if (camera) facts.push(camera);
+  if (meta.width && meta.height) facts.push(`${meta.width}×${meta.height}`);
+  if (meta.orientation && meta.orientation !== 1) facts.push(`orientation ${meta.orientation}`);
+  if (meta.gps) facts.push(`${meta.gps.latitude.toFixed(5)}, ${meta.gps.longitude.toFixed(5)}`);
+  if (facts.length === 0) return;
+  const info = document.createElement('div');
+  info.className = 'image-meta';
+  info.textContent = facts.join(' · ');
+  container.appendChild(info);
+}
+
+function renderSvg(container, output) {
+  // Already sanitized server-side; loading it as an image rather than
+  // inline markup means nothing in it can run even if something slipped by
+  const wrapper = document.createElement('div');
+  wrapper.className = 'image-preview';
+  const img = document.createElement('img');
+  img.src = 'data:image/svg+xml;charset=utf-8,' + encodeURIComponent(output.safe_markup);
+  img.alt = 'Preview';
+  wrapper.appendChild(img);
+  container.appendChild(wrapper);
+
+  if (output.width && output.height) {
+    const info = document.createElement('div');
+    info.className = 'image-meta';
+    info.textContent = `${output.width}×${output.height}`;
+    container.appendChild(info);
+  }
+}
+
+function renderJson(container, output) {
+  if (output.error) {
+    const banner = document.createElement('div');
+    banner.className = 'parse-error';
+    banner.textContent = output.error;
+    container.appendChild(banner);
+  }
+  renderText(container, {
+    content: output.pretty,
+    language: output.error ? null : 'json',
+  });
+}
+
+function renderConfig(container, output) {
+  if (output.error) {
+    const banner = document.createElement('div');
+    banner.className = 'parse-error';
+    const at = output.error.line != null
+      ? `line ${output.error.line}${output.error.column != null ? `, column ${output.error.column}` : ''}: `
+      : '';
+    banner.textContent = at + output.error.message;
+    container.appendChild(banner);
+    renderText(container, { content: output.source, language: output.format });
+    return;
+  }
+  const tree = document.createElement('div');
+  tree.className = 'config-tree';
+  tree.appendChild(renderConfigValue(output.value));
+  container.appendChild(tree);
+}
+
+function renderEnv(container, output, paneId) {
+  const node = findNode(tileTree, paneId);
+  const reveal = (keys) => {
+    node.reveal = keys;
+    renderPane(paneId);
+  };
+  const revealed = node && node.reveal ? node.reveal.split(',') : [];
+
+  const wrapper = document.createElement('div');
+  wrapper.className = 'table-view';
+  const table = document.createElement('table');
+  const head = table.createTHead().insertRow();
+  for (const label of ['Variable', 'Value', '']) {
+    const th = document.createElement('th');
+    th.textContent = label;
+    head.appendChild(th);
+  }
+  const body = table.createTBody();
+  for (const entry of output.entries) {
+    const tr = body.insertRow();
+    tr.insertCell().textContent = entry.exported ? `export ${entry.key}` : entry.key;
+    tr.insertCell().textContent = entry.value;
+    const action = tr.insertCell();
+    if (entry.masked && node) {
+      const link = document.createElement('a');
+      link.href = '#';
+      link.textContent = 'reveal';
+      link.onclick = (e) => {
+        e.preventDefault();
+        reveal([...revealed, entry.key].join(','));
+      };
+      action.appendChild(link);
+    }
+  }
+  wrapper.appendChild(table);
+
+  if (node && output.entries.some(e => e.masked)) {
+    const note = document.createElement('a');
+    note.className = 'table-view-note';
+    note.href = '#';
+    note.textContent = 'Reveal all values';
+    note.onclick = (e) => {
+      e.preventDefault();
+      reveal('all');
+    };
+    wrapper.appendChild(note);
+  } else if (node && node.reveal) {
+    const note = document.createElement('a');
+    note.className = 'table-view-note';
+    note.href = '#';
+    note.textContent = 'Hide values';
+    note.onclick = (e) => {
+      e.preventDefault();
+      reveal(null);
+    };
+    wrapper.appendChild(note);
+  }
+
+  container.appendChild(wrapper);
+}
+
+function renderConfigValue(value, key) {
+  const label = key == null ? '' : `${key}: `;
+  if (value !== null && typeof value === 'object') {
+    const entries = Array.isArray(value)
+      ? value.map((v, i) => [i, v])
+      : Object.entries(value);
+    const details = document.createElement('details');
+    details.open = true;
+    const summary = document.createElement('summary');
+    summary.textContent = label + (Array.isArray(value) ? `[${entries.length}]` : `{${entries.length}}`);
+    details.appendChild(summary);
+    for (const [k, v] of entries) {
+      details.appendChild(renderConfigValue(v, k));
+    }
+    return details;
+  }
+  const leaf = document.createElement('div');
+  leaf.className = 'config-leaf';
+  leaf.textContent = label + JSON.stringify(value);
+  return leaf;
+}
+
+function formatDuration(secs) {
+  const total = Math.round(secs);
+  const m = Math.floor(total / 60);
+  const s = String(total % 60).padStart(2, '0');
+  return `${m}:${s}`;
+}
+
+function renderAudio(container, output) {
+  const wrapper = document.createElement('div');
+  wrapper.className = 'image-preview';
+  const audio = document.createElement('audio');
+  audio.controls = true;
+  audio.preload = 'metadata';
+  audio.src = output.raw_url;
+  wrapper.appendChild(audio);
+  container.appendChild(wrapper);
+
+  const facts = [];
+  if (output.tags.artist || output.tags.title) {
+    facts.push([output.tags.artist, output.tags.title].filter(Boolean).join(' — '));
+  }
+  if (output.tags.album) facts.push(output.tags.album);
+  if (output.duration_secs != null) facts.push(formatDuration(output.duration_secs));
+  if (output.bitrate) facts.push(`${output.bitrate} kbps`);
+  if (output.sample_rate) facts.push(`${output.sample_rate / 1000} kHz`);
+  if (facts.length > 0) {
+    const info = document.createElement('div');
+    info.className = 'image-meta';
+    info.textContent = facts.join(' · ');
+    container.appendChild(info);
+  }
+}
+
+function renderVideo(container, output) {
+  const wrapper = document.createElement('div');
+  wrapper.className = 'image-preview';
+  const video = document.createElement('video');
+  video.controls = true;
+  video.preload = 'metadata';
+  const source = document.createElement('source');
+  source.src = output.raw_url;
+  source.type = output.mime_type;
+  video.appendChild(source);
+  wrapper.appendChild(video);
+  container.appendChild(wrapper);
+
+  const facts = [];
+  if (output.width && output.height) facts.push(`${output.width}×${output.height}`);
+  if (output.duration_secs != null) facts.push(formatDuration(output.duration_secs));
+  if (facts.length > 0) {
+    const info = document.createElement('div');
+    info.className = 'image-meta';
+    info.textContent = facts.join(' · ');
+    container.appendChild(info);
+  }
+}
+
+function renderDiff(container, output) {
+  const view = document.createElement('div');
+  view.className = 'diff-view';
+  for (const file of output.files) {
+    const section = document.createElement('section');
+    section.className = 'diff-file';
+
+    const title = document.createElement('div');
+    title.className = 'diff-file-title';
+    const { old_path: from, new_path: to } = file;
+    if (from == null) title.textContent = `${to} (added)`;
+    else if (to == null) title.textContent = `${from} (deleted)`;
+    else if (from !== to) title.textContent = `${from} → ${to}`;
+    else title.textContent = to;
+    section.appendChild(title);
+
+    if (file.binary) {
+      const note = document.createElement('div');
+      note.className = 'table-view-note';
+      note.textContent = 'Binary file changed';
+      section.appendChild(note);
+    }
+
+    const table = document.createElement('table');
+    table.className = 'diff-table';
+    for (const hunk of file.hunks) {
+      const head = table.insertRow();
+      head.className = 'diff-hunk';
+      const cell = head.insertCell();
+      cell.colSpan = 3;
+      cell.textContent = hunk.header;
+      for (const line of hunk.lines) {
+        const row = table.insertRow();
+        row.className = `diff-${line.kind}`;
+        row.insertCell().textContent = line.old_line ?? '';
+        row.insertCell().textContent = line.new_line ?? '';
+        const marker = { add: '+', remove: '-', context: ' ' }[line.kind];
+        row.insertCell().textContent = marker + line.content;
+      }
+    }
+    section.appendChild(table);
+    view.appendChild(section);
+  }
+  container.appendChild(view);
+}
+
+let fontFaceCounter = 0;
+const FONT_SAMPLE = 'The quick brown fox jumps over the lazy dog 0123456789';
+
+function renderFont(container, output) {
+  const view = document.createElement('div');
+  view.className = 'font-preview';
+  // A unique alias per render so reloading a changed file isn't served
+  // from a stale FontFace with the same name.
+  const alias = `deskspace-font-${++fontFaceCounter}`;
+  for (const size of [14, 20, 32, 48]) {
+    const line = document.createElement('div');
+    line.className = 'font-sample';
+    line.style.fontFamily = `'${alias}', var(--font-sans)`;
+    line.style.fontSize = `${size}px`;
+    line.textContent = FONT_SAMPLE;
+    view.appendChild(line);
+  }
+  container.appendChild(view);
+
+  const face = new FontFace(alias, `url(${JSON.stringify(output.raw_url)})`);
+  face.load()
+    .then((loaded) => document.fonts.add(loaded))
+    .catch((err) => {
+      const note = document.createElement('div');
+      note.className = 'table-view-note';
+      note.textContent = `Font could not be loaded: ${err.message}`;
+      view.prepend(note);
+    });
+
+  const facts = [output.family, output.style].filter(Boolean);
+  if (output.glyph_count != null) facts.push(`${output.glyph_count} glyphs`);
+  if (facts.length > 0) {
+    const info = document.createElement('div');
+    info.className = 'image-meta';
+    info.textContent = facts.join(' · ');
+    container.appendChild(info);
+  }
+}
+
+function renderNotebook(container, output) {
+  const view = document.createElement('div');
+  view.className = 'notebook-view';
+  for (const cell of output.cells) {
+    const cellEl = document.createElement('div');
+    cellEl.className = `notebook-cell notebook-${cell.cell_type}`;
+    if (cell.html != null) {
+      const body = document.createElement('div');
+      body.className = 'markdown-body';
+      body.innerHTML = cell.html; // sanitized server-side
+      cellEl.appendChild(body);
+    } else {
+      if (cell.execution_count != null) {
+        const prompt = document.createElement('div');
+        prompt.className = 'notebook-prompt';
+        prompt.textContent = `[${cell.execution_count}]`;
+        cellEl.appendChild(prompt);
+      }
+      renderText(cellEl, {
+        content: cell.source,
+        language: cell.cell_type === 'code' ? output.language : null,
+      });
+    }
+    for (const out of cell.outputs) {
+      if (out.kind === 'image') {
+        const img = document.createElement('img');
+        img.src = out.data_uri;
+        img.className = 'notebook-image';
+        cellEl.appendChild(img);
+      } else if (out.kind === 'error') {
+        const err = document.createElement('pre');
+        err.className = 'parse-error';
+        err.textContent = out.traceback || `${out.name}: ${out.value}`;
+        cellEl.appendChild(err);
+      } else {
+        const pre = document.createElement('pre');
+        pre.className = 'notebook-output';
+        pre.textContent = out.text;
+        cellEl.appendChild(pre);
+      }
+    }
+    view.appendChild(cellEl);
+  }
+  container.appendChild(view);
+}
+
+function renderProjectionError(container, output) {
+  const banner = document.createElement('div');
+  banner.className = 'parse-error';
+  banner.textContent = `${output.message} — try another view`;
+  container.appendChild(banner);
+}
+
+function renderTooLarge(container, output) {
+  const banner = document.createElement('div');
+  banner.className = 'parse-error';
+  banner.textContent = `File is ${formatSize(output.size)}, over the ${formatSize(output.limit)} limit for this view — `;
+  const link = document.createElement('a');
+  link.href = `${output.raw_url}${output.raw_url.includes('?') ? '&' : '?'}download=1`;
+  link.textContent = 'download it';
+  banner.appendChild(link);
+  banner.appendChild(document.createTextNode(' or try another view'));
+  container.appendChild(banner);
+}
+
+function renderTimeout(container, output) {
+  const banner = document.createElement('div');
+  banner.className = 'parse-error';
+  banner.textContent = `This view took longer than ${output.seconds}s and was stopped — try another view`;
+  container.appendChild(banner);
+}
+
+function renderHtml(container, output) {
+  // Already sanitized server-side; the sandbox (no allow-scripts) is a
+  // second line of defence and keeps the page's styles out of the UI
+  const frame = document.createElement('iframe');
+  frame.className = 'html-preview';
+  frame.setAttribute('sandbox', 'allow-same-origin');
+  frame.srcdoc = output.safe_html;
+  container.appendChild(frame);
+
+  const source = document.createElement('details');
+  const summary = document.createElement('summary');
+  summary.textContent = 'Source';
+  source.appendChild(summary);
+  renderText(source, { content: output.raw, language: 'html' });
+  container.appendChild(source);
+}
+
+function renderPdf(container, output) {
+  if (output.error) {
+    const banner = document.createElement('div');
+    banner.className = 'parse-error';
+    banner.textContent = output.error;
+    container.appendChild(banner);
+  }
+  if (output.page_count != null) {
+    const info = document.createElement('div');
+    info.className = 'image-meta';
+    info.textContent = `${output.page_count} page${output.page_count === 1 ? '' : 's'}`;
+    container.appendChild(info);
+  }
+  if (output.text_excerpt) {
+    renderText(container, { content: output.text_excerpt, language: null });
+  }
+}
+
+function renderHex(container, output) {
+  const lines = [];
+  for (let i = 0; i < output.ascii.length; i += 16) {
+    const offset = (output.offset + i).toString(16).padStart(8, '0');
+    const hex = (output.bytes.slice(i * 2, (i + 16) * 2).match(/../g) || []).join(' ');
+    lines.push(`${offset}  ${hex.padEnd(47)}  ${output.ascii.slice(i, i + 16)}`);
+  }
+  if (output.truncated) {
+    lines.push(`… ${formatSize(output.total_size)} total`);
+  }
+  renderText(container, { content: lines.join('\n'), language: null });
+}
+
+function renderTable(container, output) {
+  const wrapper = document.createElement('div');
+  wrapper.className = 'table-view';
+
+  if (output.table) {
+    const title = document.createElement('div');
+    title.className = 'table-view-title';
+    title.textContent = output.table;
+    wrapper.appendChild(title);
+  }
+
+  const table = document.createElement('table');
+  if (output.headers.length > 0) {
+    const head = table.createTHead().insertRow();
+    for (const header of output.headers) {
+      const th = document.createElement('th');
+      th.textContent = header;
+      head.appendChild(th);
+    }
+  }
+  const body = table.createTBody();
+  for (const row of output.rows) {
+    const tr = body.insertRow();
+    for (const cell of row) {
+      tr.insertCell().textContent = cell;
+    }
+  }
+  wrapper.appendChild(table);
+
+  if (output.truncated) {
+    const note = document.createElement('div');
+    note.className = 'table-view-note';
+    note.textContent = `Showing first ${output.rows.length} rows`;
+    wrapper.appendChild(note);
+  }
+
+  container.appendChild(wrapper);
+}
+
+function renderTree(container, output, paneId) {
+  const build = (nodes) => {
+    const list = document.createElement('ul');
+    list.className = 'tree-list';
+    for (const node of nodes) {
+      const item = document.createElement('li');
+      const link = document.createElement('a');
+      link.className = 'tree-entry' + (node.is_dir ? ' is-dir' : '');
+      link.href = '#';
+      link.textContent = (node.is_dir ? '📁 ' : '') + node.name + (node.is_symlink ? ' →' : '');
+      link.onclick = (e) => {
+        e.preventDefault();
+        navigatePane(paneId, node.path);
+      };
+      item.appendChild(link);
+      if (node.children) {
+        item.appendChild(build(node.children));
+      }
+      if (node.truncated) {
+        const more = document.createElement('span');
+        more.className = 'tree-more';
+        more.textContent = node.children ? ' …' : ' (not expanded)';
+        item.appendChild(more);
+      }
+      list.appendChild(item);
+    }
+    return list;
+  };
+
+  const wrapper = document.createElement('div');
+  wrapper.className = 'tree-view';
+  wrapper.appendChild(build(output.children));
+  if (output.truncated) {
+    const note = document.createElement('div');
+    note.className = 'table-view-note';
+    note.textContent = `Tree limited to ${output.depth} levels and a bounded number of entries per directory`;
+    wrapper.appendChild(note);
+  }
+  container.appendChild(wrapper);
+}
+
+function renderArchiveList(container, output) {
+  renderTable(container, {
+    table: null,
+    headers: ['Path', 'Size', 'Compressed'],
+    rows: output.entries.map(e => [
+      e.path,
+      e.is_dir ? '' : formatSize(e.size),
+      e.is_dir || e.compressed_size == null ? '' : formatSize(e.compressed_size),
+    ]),
+    truncated: output.truncated,
+  });
 }
 
 // ─── Drag resize ─────────────────────────────────────────